@@ -2,8 +2,10 @@ import React, { useEffect, useRef, useState } from 'react';
 import { LineChart, Line, XAxis, YAxis, CartesianGrid, Tooltip, ResponsiveContainer } from 'recharts';
 import { Activity, TrendingUp, TrendingDown } from 'lucide-react';
 import GodPanel from './GodPanel';
+import TradePanel from './TradePanel';
+import { SERVER, SESSION } from './session';
 
-const WS_URL = 'ws://127.0.0.1:3000/ws';
+const WS_URL = `ws://${SERVER}/ws`;
 
 function App() {
   // Rolling window of the most recent ticks, used for the chart and stats.
@@ -11,6 +13,7 @@ function App() {
   const [currentPrice, setCurrentPrice] = useState(0);
   const [prevPrice, setPrevPrice] = useState(0);
   const [tradeCount, setTradeCount] = useState(0);
+  const [leaderboard, setLeaderboard] = useState([]);
 
   const wsRef = useRef(null);
   const reconnectTimerRef = useRef(null);
@@ -68,6 +71,8 @@ function App() {
             });
           } else if (message?.type === 'trade') {
             setTradeCount((prev) => prev + 1);
+          } else if (message?.type === 'leaderboard') {
+            setLeaderboard(message.players);
           }
         };
       } catch {
@@ -113,7 +118,7 @@ function App() {
           <h1 className="text-3xl font-bold flex items-center gap-2">
             <Activity className="text-blue-500" /> Market Sim v1
           </h1>
-          <p className="text-gray-400 text-sm mt-1">Status: <span className={connectionStatus === 'Open' ? "text-green-400" : "text-red-400"}>{connectionStatus}</span></p>
+          <p className="text-gray-400 text-sm mt-1">Status: <span className={connectionStatus === 'Open' ? "text-green-400" : "text-red-400"}>{connectionStatus}</span>{SESSION && ` · session ${SESSION}`}</p>
         </div>
         
         {/* Big Price Display */}
@@ -167,6 +172,9 @@ function App() {
         </div>
       </div>
 
+      {/* Player account, order entry and leaderboard */}
+      <TradePanel currentPrice={currentPrice} leaderboard={leaderboard} />
+
       {/* God Mode Control Panel */}
       <GodPanel />
     </div>
@@ -1,7 +1,8 @@
-import React, { useState } from 'react';
-import { Zap, Pause, Play, TrendingDown, TrendingUp, Rocket, AlertTriangle, Skull, Fish, DollarSign } from 'lucide-react';
+import React, { useEffect, useState } from 'react';
+import { Zap, Pause, Play, TrendingDown, TrendingUp, Rocket, AlertTriangle, Skull, Fish, DollarSign, SkipForward, FastForward, Flag, Camera, History, GitBranch, Gauge, Waves } from 'lucide-react';
+import { SERVER } from './session';
 
-const API_BASE = 'http://127.0.0.1:3000/api/admin';
+const API_BASE = `http://${SERVER}/api/admin`;
 
 function GodPanel() {
   const [isPaused, setIsPaused] = useState(false);
@@ -14,6 +15,72 @@ function GodPanel() {
   const [rugPullMagnitude, setRugPullMagnitude] = useState(2);
   const [whaleMagnitude, setWhaleMagnitude] = useState(2);
 
+  // Time controls
+  const [time, setTime] = useState(null);
+  const [stepTicks, setStepTicks] = useState(10);
+  const [pacingMode, setPacingMode] = useState('ticked');
+  const [tickMs, setTickMs] = useState(110);
+  const [speed, setSpeed] = useState(1);
+  const [untilKind, setUntilKind] = useState('tick');
+  const [untilValue, setUntilValue] = useState('');
+
+  // Snapshots
+  const [snapshots, setSnapshots] = useState([]);
+  const [snapshotName, setSnapshotName] = useState('');
+  const [selectedSnapshot, setSelectedSnapshot] = useState('');
+  const [fork, setFork] = useState(null);
+
+  // Regime
+  const [regime, setRegime] = useState(null);
+  const [volatility, setVolatility] = useState(1);
+
+  const refreshSnapshots = async () => {
+    try {
+      const res = await fetch(`${API_BASE}/snapshots`);
+      const names = await res.json();
+      setSnapshots(names);
+      if (!names.includes(selectedSnapshot)) setSelectedSnapshot(names[0] || '');
+    } catch (err) {
+      // Engine not up yet; the list fills in on the next save
+    }
+  };
+
+  const refreshRegime = async () => {
+    try {
+      const res = await fetch(`${API_BASE}/regime`);
+      const data = await res.json();
+      setRegime(data);
+      setVolatility(data.volatility);
+    } catch (err) {
+      // Engine not up yet; shown on the next change
+    }
+  };
+
+  useEffect(() => {
+    if (isOpen) {
+      refreshSnapshots();
+      refreshRegime();
+    }
+  }, [isOpen]);
+
+  // Poll the clock while the panel is open, so run-until stops show up as a pause
+  useEffect(() => {
+    if (!isOpen) return undefined;
+    const poll = async () => {
+      try {
+        const res = await fetch(`${API_BASE}/time`);
+        const data = await res.json();
+        setTime(data);
+        setIsPaused(data.paused);
+      } catch (err) {
+        // Engine not up yet; try again on the next poll
+      }
+    };
+    poll();
+    const timer = setInterval(poll, 1000);
+    return () => clearInterval(timer);
+  }, [isOpen]);
+
   const showStatus = (message, duration = 3000) => {
     setStatus(message);
     setTimeout(() => setStatus(''), duration);
@@ -45,6 +112,102 @@ function GodPanel() {
     }
   };
 
+  const postTime = async (path, body) => {
+    try {
+      const res = await fetch(`${API_BASE}/time/${path}`, {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify(body),
+      });
+      const data = await res.json();
+      showStatus(data.message);
+    } catch (err) {
+      showStatus(`Error: ${err.message}`);
+    }
+  };
+
+  const handleStep = () => {
+    setIsPaused(true);
+    postTime('step', { ticks: Number(stepTicks) });
+  };
+
+  const handlePacing = () => {
+    const body = { mode: pacingMode };
+    if (pacingMode === 'ticked') body.tick_ms = Number(tickMs);
+    if (pacingMode === 'realtime') body.speed = Number(speed);
+    postTime('pacing', body);
+  };
+
+  const handleRunUntil = () => {
+    const body = { until: untilKind };
+    if (untilKind === 'tick') body.tick = Number(untilValue);
+    // Prices are entered in dollars, the engine wants cents
+    if (untilKind === 'price_above' || untilKind === 'price_below') body.price = Math.round(Number(untilValue) * 100);
+    setIsPaused(false);
+    postTime('until', body);
+  };
+
+  const handleSaveSnapshot = async () => {
+    try {
+      const res = await fetch(`${API_BASE}/snapshots`, {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify({ name: snapshotName }),
+      });
+      const data = await res.json();
+      showStatus(data.message);
+      refreshSnapshots();
+    } catch (err) {
+      showStatus(`Error: ${err.message}`);
+    }
+  };
+
+  const handleLoadSnapshot = async () => {
+    try {
+      const res = await fetch(`${API_BASE}/snapshots/${selectedSnapshot}/load`, { method: 'POST' });
+      const data = await res.json();
+      showStatus(data.message);
+    } catch (err) {
+      showStatus(`Error: ${err.message}`);
+    }
+  };
+
+  // What if it crashed now: the live market run on with and without a flash crash
+  const handleFork = async () => {
+    showStatus('Forking...');
+    try {
+      const res = await fetch(`${API_BASE}/fork`, {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify({ ticks: 500, shock: { kind: 'flash_crash' } }),
+      });
+      const data = await res.json();
+      if (data.message) {
+        showStatus(data.message);
+      } else {
+        setFork(data);
+        showStatus('');
+      }
+    } catch (err) {
+      showStatus(`Error: ${err.message}`);
+    }
+  };
+
+  const postRegime = async (path, body) => {
+    try {
+      const res = await fetch(`${API_BASE}/${path}`, {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify(body),
+      });
+      const data = await res.json();
+      showStatus(data.message);
+      refreshRegime();
+    } catch (err) {
+      showStatus(`Error: ${err.message}`);
+    }
+  };
+
   const handleBuyWall = async () => {
     try {
       const res = await fetch(`${API_BASE}/pump`, {
@@ -178,6 +341,200 @@ function GodPanel() {
               )}
             </button>
 
+            {/* Time */}
+            <div className="space-y-2 p-3 bg-gray-800 rounded-lg">
+              <div className="text-sm text-gray-400">
+                {time
+                  ? `Tick ${time.tick} · $${(time.price / 100).toFixed(2)} · ${time.pacing.mode}${time.until ? ` · until ${time.until.until}` : ''}`
+                  : 'Time'}
+              </div>
+              <div className="flex gap-2">
+                <input
+                  type="number"
+                  min="1"
+                  max="10000"
+                  value={stepTicks}
+                  onChange={(e) => setStepTicks(e.target.value)}
+                  className="w-20 bg-gray-900 text-white text-sm rounded px-2"
+                />
+                <button
+                  onClick={handleStep}
+                  className="flex-1 py-2 px-3 bg-gray-600 hover:bg-gray-700 text-white rounded-lg text-sm font-semibold flex items-center justify-center gap-2 transition-all"
+                >
+                  <SkipForward className="w-4 h-4" />
+                  Step Ticks
+                </button>
+              </div>
+              <div className="flex gap-2">
+                <select
+                  value={pacingMode}
+                  onChange={(e) => setPacingMode(e.target.value)}
+                  className="bg-gray-900 text-white text-sm rounded px-2"
+                >
+                  <option value="ticked">Ticked</option>
+                  <option value="realtime">Real time</option>
+                  <option value="fast">Max speed</option>
+                </select>
+                {pacingMode === 'ticked' && (
+                  <input
+                    type="number"
+                    min="1"
+                    max="10000"
+                    value={tickMs}
+                    onChange={(e) => setTickMs(e.target.value)}
+                    title="Milliseconds per tick"
+                    className="w-20 bg-gray-900 text-white text-sm rounded px-2"
+                  />
+                )}
+                {pacingMode === 'realtime' && (
+                  <input
+                    type="number"
+                    min="0.1"
+                    max="1000"
+                    step="0.1"
+                    value={speed}
+                    onChange={(e) => setSpeed(e.target.value)}
+                    title="Multiple of real time"
+                    className="w-20 bg-gray-900 text-white text-sm rounded px-2"
+                  />
+                )}
+                <button
+                  onClick={handlePacing}
+                  className="flex-1 py-2 px-3 bg-gray-600 hover:bg-gray-700 text-white rounded-lg text-sm font-semibold flex items-center justify-center gap-2 transition-all"
+                >
+                  <FastForward className="w-4 h-4" />
+                  Pace
+                </button>
+              </div>
+              <div className="flex gap-2">
+                <select
+                  value={untilKind}
+                  onChange={(e) => setUntilKind(e.target.value)}
+                  className="bg-gray-900 text-white text-sm rounded px-2"
+                >
+                  <option value="tick">Tick</option>
+                  <option value="price_above">Price ≥ $</option>
+                  <option value="price_below">Price ≤ $</option>
+                  <option value="halt">Halt</option>
+                </select>
+                {untilKind !== 'halt' && (
+                  <input
+                    type="number"
+                    min="0"
+                    value={untilValue}
+                    onChange={(e) => setUntilValue(e.target.value)}
+                    className="w-20 bg-gray-900 text-white text-sm rounded px-2"
+                  />
+                )}
+                <button
+                  onClick={handleRunUntil}
+                  className="flex-1 py-2 px-3 bg-gray-600 hover:bg-gray-700 text-white rounded-lg text-sm font-semibold flex items-center justify-center gap-2 transition-all"
+                >
+                  <Flag className="w-4 h-4" />
+                  Run Until
+                </button>
+              </div>
+            </div>
+
+            {/* Snapshots */}
+            <div className="space-y-2 p-3 bg-gray-800 rounded-lg">
+              <div className="text-sm text-gray-400">Snapshots</div>
+              <div className="flex gap-2">
+                <input
+                  type="text"
+                  placeholder="name"
+                  value={snapshotName}
+                  onChange={(e) => setSnapshotName(e.target.value)}
+                  className="w-28 bg-gray-900 text-white text-sm rounded px-2"
+                />
+                <button
+                  onClick={handleSaveSnapshot}
+                  disabled={!snapshotName}
+                  className="flex-1 py-2 px-3 bg-gray-600 hover:bg-gray-700 disabled:opacity-50 text-white rounded-lg text-sm font-semibold flex items-center justify-center gap-2 transition-all"
+                >
+                  <Camera className="w-4 h-4" />
+                  Save
+                </button>
+              </div>
+              <div className="flex gap-2">
+                <select
+                  value={selectedSnapshot}
+                  onChange={(e) => setSelectedSnapshot(e.target.value)}
+                  className="w-28 bg-gray-900 text-white text-sm rounded px-2"
+                >
+                  {snapshots.map((name) => (
+                    <option key={name} value={name}>{name}</option>
+                  ))}
+                </select>
+                <button
+                  onClick={handleLoadSnapshot}
+                  disabled={!selectedSnapshot}
+                  className="flex-1 py-2 px-3 bg-gray-600 hover:bg-gray-700 disabled:opacity-50 text-white rounded-lg text-sm font-semibold flex items-center justify-center gap-2 transition-all"
+                >
+                  <History className="w-4 h-4" />
+                  Load
+                </button>
+              </div>
+              <button
+                onClick={handleFork}
+                className="w-full py-2 px-3 bg-gray-600 hover:bg-gray-700 text-white rounded-lg text-sm font-semibold flex items-center justify-center gap-2 transition-all"
+              >
+                <GitBranch className="w-4 h-4" />
+                Fork: Crash vs No Crash
+              </button>
+              {fork && (
+                <div className="text-xs text-gray-400">
+                  500 ticks from {fork.from_tick}: ${(fork.baseline.final_price / 100).toFixed(2)} without,
+                  ${(fork.branch.final_price / 100).toFixed(2)} with the crash
+                  (drawdown {(fork.baseline.max_drawdown * 100).toFixed(0)}% vs {(fork.branch.max_drawdown * 100).toFixed(0)}%)
+                </div>
+              )}
+            </div>
+
+            {/* Regime */}
+            <div className="space-y-2 p-3 bg-gray-800 rounded-lg">
+              <div className="text-sm text-gray-400 flex items-center gap-2">
+                <Gauge className="w-4 h-4" />
+                {regime
+                  ? `Regime · fundamental ${regime.fundamental >= 1 ? '+' : ''}${((regime.fundamental - 1) * 100).toFixed(1)}%`
+                  : 'Regime'}
+              </div>
+              <div className="grid grid-cols-2 gap-2">
+                {['calm', 'normal', 'stressed', 'euphoric'].map((name) => (
+                  <button
+                    key={name}
+                    onClick={() => postRegime('regime', { regime: name })}
+                    className={`py-2 px-3 rounded-lg text-sm font-semibold capitalize transition-all ${
+                      regime?.regime === name
+                        ? 'bg-purple-600 hover:bg-purple-700 text-white'
+                        : 'bg-gray-600 hover:bg-gray-700 text-white'
+                    }`}
+                  >
+                    {name}
+                  </button>
+                ))}
+              </div>
+              <div className="flex items-center justify-between">
+                <label className="text-sm text-gray-400">Volatility: {volatility.toFixed(1)}x</label>
+                <input
+                  type="range"
+                  min="0.1"
+                  max="5"
+                  step="0.1"
+                  value={volatility}
+                  onChange={(e) => setVolatility(Number(e.target.value))}
+                  className="w-32"
+                />
+              </div>
+              <button
+                onClick={() => postRegime('volatility', { multiplier: volatility })}
+                className="w-full py-2 px-3 bg-gray-600 hover:bg-gray-700 text-white rounded-lg text-sm font-semibold flex items-center justify-center gap-2 transition-all"
+              >
+                <Waves className="w-4 h-4" />
+                Set Volatility
+              </button>
+            </div>
+
             {/* Flash Crash */}
             <button
               onClick={handleFlashCrash}
@@ -312,6 +669,8 @@ function GodPanel() {
               <li>- <span className="text-red-400">Rug Pull</span>: coordinated insider sell-off</li>
               <li>- <span className="text-blue-400">Whale</span>: large sustained buying</li>
               <li>- <span className="text-purple-400">Pause</span>: freezes all trading</li>
+              <li>- <span className="text-purple-400">Regime</span>: calm, stressed or euphoric agents; volatility scales noise, order flow and the fundamental</li>
+              <li>- <span className="text-gray-300">Step / Run Until</span>: advance a paused market, or run until a tick, price or halt</li>
             </ul>
           </div>
         </div>
@@ -0,0 +1,208 @@
+import React, { useEffect, useState } from 'react';
+import { Trophy, User, X } from 'lucide-react';
+import { SERVER, SESSION } from './session';
+
+const API_BASE = `http://${SERVER}/api/game`;
+// Players belong to one session's game
+const STORAGE_KEY = SESSION ? `market-sim-player-${SESSION}` : 'market-sim-player';
+
+const dollars = (cents) => `$${(cents / 100).toFixed(2)}`;
+
+function TradePanel({ currentPrice, leaderboard }) {
+  // { player_id, key } once joined; kept across reloads so a refresh doesn't lose the account
+  const [player, setPlayer] = useState(() => {
+    try {
+      return JSON.parse(window.localStorage.getItem(STORAGE_KEY));
+    } catch {
+      return null;
+    }
+  });
+  const [account, setAccount] = useState(null);
+  const [name, setName] = useState('');
+  const [price, setPrice] = useState('');
+  const [quantity, setQuantity] = useState(10);
+  const [status, setStatus] = useState('');
+
+  const showStatus = (message, duration = 3000) => {
+    setStatus(message);
+    setTimeout(() => setStatus(''), duration);
+  };
+
+  // Poll the account; fills land on the next tick, so once a second is plenty
+  useEffect(() => {
+    if (!player) return undefined;
+    let cancelled = false;
+
+    const refresh = async () => {
+      try {
+        const res = await fetch(`${API_BASE}/players/${player.player_id}`, {
+          headers: { 'x-player-key': player.key },
+        });
+        if (res.status === 404 || res.status === 401) {
+          // Engine restarted, the account is gone
+          if (!cancelled) {
+            window.localStorage.removeItem(STORAGE_KEY);
+            setPlayer(null);
+            setAccount(null);
+          }
+          return;
+        }
+        const data = await res.json();
+        if (!cancelled) setAccount(data);
+      } catch {
+        // Engine down; try again next interval
+      }
+    };
+
+    refresh();
+    const timer = window.setInterval(refresh, 1000);
+    return () => {
+      cancelled = true;
+      window.clearInterval(timer);
+    };
+  }, [player]);
+
+  const handleJoin = async () => {
+    try {
+      const res = await fetch(`${API_BASE}/join`, {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify({ name }),
+      });
+      const data = await res.json();
+      if (!res.ok) {
+        showStatus(data.message);
+        return;
+      }
+      const joined = { player_id: data.player_id, key: data.key };
+      window.localStorage.setItem(STORAGE_KEY, JSON.stringify(joined));
+      setPlayer(joined);
+    } catch (err) {
+      showStatus(`Error: ${err.message}`);
+    }
+  };
+
+  const handleOrder = async (side) => {
+    // Blank price = cross the spread by a dollar so the order fills now
+    const limit = price === '' ? currentPrice + (side === 'bid' ? 100 : -100) : Math.round(Number(price) * 100);
+    try {
+      const res = await fetch(`${API_BASE}/players/${player.player_id}/orders`, {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json', 'x-player-key': player.key },
+        body: JSON.stringify({ side, price: Math.max(limit, 1), quantity: Number(quantity) }),
+      });
+      const data = await res.json();
+      showStatus(res.ok ? `${side === 'bid' ? 'Buy' : 'Sell'} ${quantity} @ ${dollars(limit)} sent` : data.message);
+    } catch (err) {
+      showStatus(`Error: ${err.message}`);
+    }
+  };
+
+  const handleCancel = async (orderId) => {
+    try {
+      await fetch(`${API_BASE}/players/${player.player_id}/orders/${orderId}`, {
+        method: 'DELETE',
+        headers: { 'x-player-key': player.key },
+      });
+    } catch (err) {
+      showStatus(`Error: ${err.message}`);
+    }
+  };
+
+  const standing = account?.standing;
+
+  return (
+    <div className="grid grid-cols-2 gap-4 mt-8">
+      {/* Account and order entry */}
+      <div className="bg-gray-800 p-4 rounded-lg border border-gray-700">
+        <h3 className="text-gray-400 text-sm flex items-center gap-2 mb-3">
+          <User className="w-4 h-4" /> Trade
+        </h3>
+
+        {status && (
+          <div className="mb-3 p-2 bg-blue-900/50 border border-blue-600 rounded text-blue-300 text-sm">{status}</div>
+        )}
+
+        {!player ? (
+          <div className="flex gap-2">
+            <input
+              value={name}
+              onChange={(e) => setName(e.target.value)}
+              placeholder="Your name"
+              className="flex-1 bg-gray-900 border border-gray-700 rounded px-3 py-2"
+            />
+            <button onClick={handleJoin} className="py-2 px-4 bg-blue-600 hover:bg-blue-700 rounded font-semibold">
+              Join
+            </button>
+          </div>
+        ) : (
+          <>
+            {standing && (
+              <div className="grid grid-cols-4 gap-2 mb-4 text-sm">
+                <div><p className="text-gray-500">Cash</p><p className="font-bold">{dollars(standing.cash)}</p></div>
+                <div><p className="text-gray-500">Position</p><p className="font-bold">{standing.inventory}</p></div>
+                <div><p className="text-gray-500">PnL</p><p className={`font-bold ${standing.pnl >= 0 ? 'text-green-500' : 'text-red-500'}`}>{dollars(standing.pnl)}</p></div>
+                <div><p className="text-gray-500">Rank</p><p className="font-bold">#{account.rank}</p></div>
+              </div>
+            )}
+
+            <div className="flex gap-2 mb-4">
+              <input
+                value={price}
+                onChange={(e) => setPrice(e.target.value)}
+                placeholder="Limit $ (blank = market)"
+                className="flex-1 bg-gray-900 border border-gray-700 rounded px-3 py-2 text-sm"
+              />
+              <input
+                type="number"
+                min="1"
+                value={quantity}
+                onChange={(e) => setQuantity(e.target.value)}
+                className="w-20 bg-gray-900 border border-gray-700 rounded px-3 py-2 text-sm"
+              />
+              <button onClick={() => handleOrder('bid')} className="py-2 px-4 bg-green-600 hover:bg-green-700 rounded font-semibold text-sm">Buy</button>
+              <button onClick={() => handleOrder('ask')} className="py-2 px-4 bg-red-600 hover:bg-red-700 rounded font-semibold text-sm">Sell</button>
+            </div>
+
+            <h4 className="text-gray-500 text-xs mb-1">Open orders</h4>
+            <ul className="text-sm space-y-1">
+              {(account?.open_orders ?? []).map((o) => (
+                <li key={o.id} className="flex justify-between items-center">
+                  <span className={o.side === 'Bid' ? 'text-green-400' : 'text-red-400'}>
+                    {o.side === 'Bid' ? 'BUY' : 'SELL'} {o.amount} @ {dollars(o.price)}
+                  </span>
+                  <button onClick={() => handleCancel(o.id)} className="text-gray-500 hover:text-white"><X className="w-4 h-4" /></button>
+                </li>
+              ))}
+              {account && account.open_orders.length === 0 && <li className="text-gray-600">none</li>}
+            </ul>
+          </>
+        )}
+      </div>
+
+      {/* Leaderboard */}
+      <div className="bg-gray-800 p-4 rounded-lg border border-gray-700">
+        <h3 className="text-gray-400 text-sm flex items-center gap-2 mb-3">
+          <Trophy className="w-4 h-4 text-yellow-500" /> Leaderboard
+        </h3>
+        <table className="w-full text-sm">
+          <tbody>
+            {leaderboard.map((s, i) => (
+              <tr key={s.player_id} className={s.player_id === player?.player_id ? 'text-blue-400' : ''}>
+                <td className="py-1 text-gray-500">{i + 1}</td>
+                <td className="py-1">{s.name}</td>
+                <td className={`py-1 text-right ${s.pnl >= 0 ? 'text-green-500' : 'text-red-500'}`}>{dollars(s.pnl)}</td>
+                <td className="py-1 text-right text-gray-500">{s.return_pct.toFixed(2)}%</td>
+              </tr>
+            ))}
+            {leaderboard.length === 0 && (
+              <tr><td className="text-gray-600">No players yet</td></tr>
+            )}
+          </tbody>
+        </table>
+      </div>
+    </div>
+  );
+}
+
+export default TradePanel;
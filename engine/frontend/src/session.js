@@ -0,0 +1,5 @@
+// Which engine session the dashboard shows: `?session=<id>` in the page URL,
+// or the server's default session without one.
+export const SESSION = new URLSearchParams(window.location.search).get('session');
+
+export const SERVER = `127.0.0.1:3000${SESSION ? `/sessions/${encodeURIComponent(SESSION)}` : ''}`;
@@ -1,16 +1,164 @@
-use crate::types::{Order, OrderSide, Trade};
+use crate::behaviour::BehaviouralTraits;
+use crate::indicators::{Bar, IndicatorSpec, Indicators};
+use crate::manipulation::{IgnitionConfig, ManipulationState, SpoofConfig};
+use crate::margin::MarginAccount;
+use crate::regime::{Conditions, NOISE_JITTER};
+use crate::rl::{Action, Observation, QLearner};
+use crate::say;
+use crate::scenario::Scenario;
+use crate::scripting::{ScriptHandle, ScriptHost, ScriptRuntime};
+use crate::types::{ExternalInstruction, Order, OrderSide, SimRng, Trade};
 use rand::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 
-#[derive(Debug, Clone)]
+/// How far either side of the mid agents look when reading book pressure.
+pub const BOOK_PRESSURE_WINDOW_BPS: u64 = 100;
+
+/// The agent's own random stream for run `seed`. Depends only on the seed and
+/// the id, so it doesn't matter in which order agents are created.
+pub fn agent_rng(seed: u64, id: u64) -> SimRng {
+    SimRng::seed_from_u64(seed ^ id.wrapping_mul(0x9E37_79B9_7F4A_7C15))
+}
+
+/// Prices an agent remembers unless its strategy needs a longer window.
+pub const DEFAULT_MAX_HISTORY: usize = 50;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Strategy {
     RandomWalker,
     TrendFollower { window_size: usize },
     MeanReverter { window_size: usize, std_dev_multiplier: f64 },
     MarketMaker { spread_bps: u64 },
+    /// Brock-Hommes style switching between a chartist and a fundamentalist
+    /// forecasting rule, picked by a logit over each rule's recent profitability.
+    BeliefSwitcher {
+        intensity_of_choice: f64,  // beta, 0.0 = coin flip, higher = herd into the winner
+        fundamental_price: u64,
+        trend_strength: f64,       // chartist extrapolation of the last price change
+        reversion_strength: f64,   // fraction of the gap to fundamental expected to close
+        memory: f64,               // weight on past fitness, 0.0-1.0
+    },
+    /// Copies what its neighbours in the social graph have recently done.
+    Herder { imitation_probability: f64 },
+    /// Avellaneda-Stoikov optimal market maker. Quotes both sides every tick around
+    /// an inventory-adjusted reservation price and pulls its old quotes first.
+    AvellanedaStoikov {
+        risk_aversion: f64,     // gamma, per cent
+        horizon: f64,           // ticks, stands in for T - t
+        arrival_at_touch: f64,  // A, expected fills per tick at zero distance from mid
+        quote_size: u64,
+        inventory_limit: u64,   // max distance from target_inventory before one side is switched off
+        target_inventory: u64,
+    },
+    /// Tabular Q-learner that trains online on its own mark-to-market PnL.
+    QLearner { epsilon: f64, learning_rate: f64, discount: f64 },
+    /// Decision function loaded from a Rhai script (see `scripting`).
+    Scripted { path: String },
+    /// Spoofing/layering manipulator (see `manipulation`).
+    Spoofer(SpoofConfig),
+    /// Momentum-ignition manipulator (see `manipulation`).
+    MomentumIgniter(IgnitionConfig),
+    /// Never acts on its own; orders come from outside (`MarketEnv`, trading sessions).
+    External,
+}
+
+impl Strategy {
+    /// Short label for reports and logs.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Strategy::RandomWalker => "RandomWalker",
+            Strategy::TrendFollower { .. } => "TrendFollower",
+            Strategy::MeanReverter { .. } => "MeanReverter",
+            Strategy::MarketMaker { .. } => "MarketMaker",
+            Strategy::BeliefSwitcher { .. } => "BeliefSwitcher",
+            Strategy::Herder { .. } => "Herder",
+            Strategy::AvellanedaStoikov { .. } => "AvellanedaStoikov",
+            Strategy::QLearner { .. } => "QLearner",
+            Strategy::Scripted { .. } => "Scripted",
+            Strategy::Spoofer(_) => "Spoofer",
+            Strategy::MomentumIgniter(_) => "MomentumIgniter",
+            Strategy::External => "External",
+        }
+    }
+
+    /// Longest price lookback the strategy needs, in ticks.
+    pub fn longest_window(&self) -> usize {
+        match self {
+            Strategy::TrendFollower { window_size } | Strategy::MeanReverter { window_size, .. } => *window_size,
+            _ => 0,
+        }
+    }
+
+    /// Indicators the strategy reads, subscribed to when the agent is created.
+    pub fn indicators(&self) -> Vec<IndicatorSpec> {
+        match self {
+            Strategy::MeanReverter { window_size, .. } => vec![IndicatorSpec::Bollinger { window: *window_size }],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Every value `kind()` can return.
+    pub const KINDS: &'static [&'static str] = &[
+        "RandomWalker", "TrendFollower", "MeanReverter", "MarketMaker", "BeliefSwitcher", "Herder",
+        "AvellanedaStoikov", "QLearner", "Scripted", "Spoofer", "MomentumIgniter", "External",
+    ];
+}
+
+/// Forecasting rules available to a `BeliefSwitcher`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BeliefRule {
+    Chartist,
+    Fundamentalist,
+}
+
+/// Per-agent state for `Strategy::BeliefSwitcher`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BeliefState {
+    pub rule: BeliefRule,
+    pub chartist_fitness: f64,
+    pub fundamentalist_fitness: f64,
+    last_price: Option<u64>,
+    last_forecasts: Option<(f64, f64)>, // (chartist, fundamentalist)
 }
 
-#[derive(Debug)]
+impl BeliefState {
+    fn new() -> Self {
+        Self {
+            rule: BeliefRule::Fundamentalist,
+            chartist_fitness: 0.0,
+            fundamentalist_fitness: 0.0,
+            last_price: None,
+            last_forecasts: None,
+        }
+    }
+}
+
+/// Per-agent estimates for `Strategy::AvellanedaStoikov`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuoteState {
+    pub volatility: f64,    // EWMA of per-tick price change std dev, in cents
+    pub arrival_rate: f64,  // EWMA of fills per tick
+    pub intensity: f64,     // k, decay of fill probability with distance from mid, per cent
+    last_price: Option<u64>,
+    last_half_spread: f64,
+    fills_since_quote: u64,
+}
+
+impl QuoteState {
+    fn new() -> Self {
+        Self {
+            volatility: 10.0,
+            arrival_rate: 0.5,
+            intensity: 0.05,
+            last_price: None,
+            last_half_spread: 20.0,
+            fills_since_quote: 0,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Agent {
     pub id: u64,
     pub name: String,
@@ -19,10 +167,51 @@ pub struct Agent {
     pub strategy: Strategy,
     pub price_history: VecDeque<u64>,
     pub max_history: usize,
+    pub indicators: Indicators,
+    pub belief: Option<BeliefState>,
+    pub traits: Option<BehaviouralTraits>,
+    pub social_signal: f64, // -1.0 (neighbours selling) to +1.0 (neighbours buying)
+    pub quoting: Option<QuoteState>,
+    pub margin: Option<MarginAccount>,
+    pub book_top: (Option<u64>, Option<u64>), // (best bid, best ask) as of the last update
+    pub book_pressure: f64, // -1.0 (resting size all asks) to +1.0 (all bids), near the touch
+    /// The market regime as of the agent's last turn
+    #[serde(default)]
+    pub conditions: Conditions,
+    pub starting_cash: u64,
+    pub starting_inventory: u64,
+    pub learner: Option<QLearner>,
+    /// Saved separately in snapshots, since the compiled script can't be
+    #[serde(skip)]
+    pub script: Option<ScriptRuntime>,
+    pub inbox: Vec<ExternalInstruction>, // queued by a trading session, drained once per tick
+    pub manipulation: Option<ManipulationState>,
+    pub rng: SimRng, // every decision the agent makes draws from this
 }
 
 impl Agent {
     pub fn new(id: u64, name: String, cash: u64, inventory: u64, strategy: Strategy) -> Self {
+        let belief = match strategy {
+            Strategy::BeliefSwitcher { .. } => Some(BeliefState::new()),
+            _ => None,
+        };
+        let quoting = match strategy {
+            Strategy::AvellanedaStoikov { .. } => Some(QuoteState::new()),
+            _ => None,
+        };
+        let learner = match strategy {
+            Strategy::QLearner { epsilon, learning_rate, discount } => Some(QLearner::new(epsilon, learning_rate, discount)),
+            _ => None,
+        };
+        let manipulation = match strategy {
+            Strategy::Spoofer(_) | Strategy::MomentumIgniter(_) => Some(ManipulationState::new()),
+            _ => None,
+        };
+        let mut indicators = Indicators::default();
+        for spec in strategy.indicators() {
+            indicators.subscribe(spec);
+        }
+        let max_history = DEFAULT_MAX_HISTORY.max(strategy.longest_window());
         Self {
             id,
             name,
@@ -30,45 +219,353 @@ impl Agent {
             inventory,
             strategy,
             price_history: VecDeque::new(),
-            max_history: 50, // Default memory size
+            max_history,
+            indicators,
+            belief,
+            traits: None,
+            social_signal: 0.0,
+            quoting,
+            margin: None,
+            book_top: (None, None),
+            book_pressure: 0.0,
+            conditions: Conditions::default(),
+            starting_cash: cash,
+            starting_inventory: inventory,
+            learner,
+            script: None,
+            inbox: Vec::new(),
+            manipulation,
+            rng: agent_rng(0, id),
         }
     }
 
-    pub fn update_market_data(&mut self, price: u64) {
-        if price == 0 { return; }
-        self.price_history.push_back(price);
+    /// Attach the compiled script a `Strategy::Scripted` agent runs.
+    pub fn with_script(mut self, handle: ScriptHandle) -> Self {
+        self.script = Some(ScriptRuntime::new(handle));
+        self
+    }
+
+    /// Layer behavioural biases on top of the agent's strategy.
+    pub fn with_traits(mut self, traits: BehaviouralTraits) -> Self {
+        self.traits = Some(traits);
+        self
+    }
+
+    /// Restart the agent's random stream for run `seed`.
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = agent_rng(seed, self.id);
+    }
+
+    /// Remember `ticks` prices instead of the default, but never fewer than
+    /// the strategy's own window.
+    pub fn with_memory(mut self, ticks: usize) -> Self {
+        self.max_history = ticks.max(self.strategy.longest_window()).max(1);
+        self
+    }
+
+    /// Have the simulation loop keep `spec` up to date for this agent.
+    pub fn subscribe(&mut self, spec: IndicatorSpec) {
+        self.indicators.subscribe(spec);
+    }
+
+    pub fn update_market_data(&mut self, bar: Bar) {
+        if bar.price == 0 { return; }
+        self.price_history.push_back(bar.price);
         if self.price_history.len() > self.max_history {
             self.price_history.pop_front();
         }
+        self.indicators.update(&bar);
+    }
+
+    /// Trade through a margin account: borrow cash to buy and shares to short.
+    pub fn with_margin(mut self, margin: MarginAccount) -> Self {
+        self.margin = Some(margin);
+        self
+    }
+
+    /// Most units this agent can buy at `price`, counting margin financing.
+    pub fn max_buy(&self, price: u64) -> u64 {
+        match &self.margin {
+            Some(margin) => margin.max_buy(self.cash, self.inventory, price),
+            None => self.cash / price.max(1),
+        }
+    }
+
+    /// Most units this agent can sell at `price`, counting short sales.
+    pub fn max_sell(&self, price: u64) -> u64 {
+        match &self.margin {
+            Some(margin) => margin.max_sell(self.cash, self.inventory, price),
+            None => self.inventory,
+        }
+    }
+
+    pub fn can_buy(&self, price: u64, amount: u64) -> bool {
+        self.max_buy(price) >= amount
+    }
+
+    pub fn can_sell(&self, price: u64, amount: u64) -> bool {
+        self.max_sell(price) >= amount
+    }
+
+    /// Mark-to-market net worth, net of any margin loans.
+    pub fn equity(&self, price: u64) -> i64 {
+        match &self.margin {
+            Some(margin) => margin.equity(self.cash, self.inventory, price),
+            None => (self.cash + self.inventory * price) as i64,
+        }
+    }
+
+    /// Charge a tick of margin costs and return the forced trade, if any, needed
+    /// to bring the account back above maintenance.
+    pub fn margin_call(&mut self, current_price: u64) -> Option<(OrderSide, u64)> {
+        let margin = self.margin.as_mut()?;
+        margin.accrue(&mut self.cash, current_price);
+        margin.liquidation(self.cash, self.inventory, current_price)
+    }
+
+    pub fn update_book_top(&mut self, best_bid: Option<u64>, best_ask: Option<u64>) {
+        self.book_top = (best_bid, best_ask);
+    }
+
+    pub fn update_book_pressure(&mut self, imbalance: f64) {
+        self.book_pressure = imbalance.clamp(-1.0, 1.0);
+    }
+
+    pub fn update_conditions(&mut self, conditions: Conditions) {
+        self.conditions = conditions;
+    }
+
+    /// Features for learning agents, built from what this agent has been shown.
+    pub fn observe(&self, current_price: u64) -> Observation {
+        Observation::new(
+            current_price,
+            self.book_top.0,
+            self.book_top.1,
+            &self.price_history,
+            self.inventory as i64 - self.starting_inventory as i64,
+            self.cash,
+        )
+    }
+
+    /// Turn a discrete action into a marketable one-unit order, crossing at the
+    /// opposite touch (or 10 cents through the last price if that side is empty).
+    pub fn order_for(&self, action: Action, current_price: u64, rng: &mut impl Rng) -> Option<Order> {
+        let (side, price) = match action {
+            Action::Hold => return None,
+            Action::Buy => (OrderSide::Bid, self.book_top.1.unwrap_or(current_price + 10)),
+            Action::Sell => (OrderSide::Ask, self.book_top.0.unwrap_or(current_price.saturating_sub(10)).max(1)),
+        };
+        let allowed = match side {
+            OrderSide::Bid => self.can_buy(price, 1),
+            OrderSide::Ask => self.can_sell(price, 1),
+        };
+        if !allowed {
+            return None;
+        }
+        Some(Order {
+            id: rng.next_u64(),
+            trader_id: self.id,
+            side,
+            price,
+            amount: 1,
+            timestamp: 0,
+        })
+    }
+
+    pub fn update_social_signal(&mut self, signal: f64) {
+        self.social_signal = signal.clamp(-1.0, 1.0);
     }
 
     pub fn on_trade(&mut self, trade: &Trade) {
-        if trade.buyer_id == self.id {
+        let inventory_before = self.inventory;
+        let side = if let Some(margin) = self.margin.as_mut() {
+            if trade.buyer_id == self.id {
+                margin.settle_buy(&mut self.cash, &mut self.inventory, trade.price, trade.amount);
+                OrderSide::Bid
+            } else if trade.seller_id == self.id {
+                margin.settle_sell(&mut self.cash, &mut self.inventory, trade.price, trade.amount);
+                OrderSide::Ask
+            } else {
+                return;
+            }
+        } else if trade.buyer_id == self.id {
             let cost = trade.price * trade.amount;
             if self.cash >= cost {
                 self.cash -= cost;
                 self.inventory += trade.amount;
             }
+            OrderSide::Bid
         } else if trade.seller_id == self.id {
             let revenue = trade.price * trade.amount;
             self.cash += revenue;
             if self.inventory >= trade.amount {
                 self.inventory -= trade.amount;
             }
+            OrderSide::Ask
+        } else {
+            return;
+        };
+
+        if let Some(traits) = self.traits.as_mut() {
+            traits.on_fill(side, trade.price, inventory_before, self.inventory);
+        }
+        if let Some(quoting) = self.quoting.as_mut() {
+            quoting.fills_since_quote += 1;
         }
     }
 
-    pub fn act(&mut self, current_price: u64) -> Option<Order> {
+    /// Whether this agent replaces all its resting orders each time it acts.
+    /// Margin accounts do too, otherwise stale orders could fill past the broker's limits.
+    /// Manipulators rely on it to pull their layers before they can fill.
+    pub fn requotes(&self) -> bool {
+        matches!(self.strategy, Strategy::AvellanedaStoikov { .. } | Strategy::Spoofer(_) | Strategy::MomentumIgniter(_))
+            || self.margin.is_some()
+    }
+
+    pub fn act(&mut self, current_price: u64) -> Vec<Order> {
+        // Work on a copy of the stream so the strategies can borrow `self` freely
+        let mut rng = self.rng.clone();
+        let orders = self.act_with(current_price, &mut rng);
+        self.rng = rng;
+        orders
+    }
+
+    fn act_with(&mut self, current_price: u64, rng: &mut SimRng) -> Vec<Order> {
+        let orders = match self.strategy {
+            Strategy::AvellanedaStoikov { .. } => self.quote(current_price, rng),
+            Strategy::Scripted { .. } => self.run_script(current_price, rng),
+            Strategy::Spoofer(_) | Strategy::MomentumIgniter(_) => self.manipulate(current_price, rng),
+            _ => self.decide(current_price, rng).into_iter().collect(),
+        };
+        let Some(traits) = self.traits.take() else {
+            return orders;
+        };
+
+        let orders = if orders.is_empty() {
+            traits.apply(None, self, current_price, rng).into_iter().collect()
+        } else {
+            orders.into_iter()
+                .filter_map(|order| traits.apply(Some(order), self, current_price, rng))
+                .collect()
+        };
+        self.traits = Some(traits);
+        orders
+    }
+
+    /// Ask the agent's script for orders. Script errors are logged and the agent sits out.
+    fn run_script(&mut self, current_price: u64, rng: &mut SimRng) -> Vec<Order> {
+        let Some(mut runtime) = self.script.take() else {
+            return Vec::new();
+        };
+        let orders = runtime.decide(self, current_price, rng).unwrap_or_else(|e| {
+            say!("Script error for {}: {}", self.name, e);
+            Vec::new()
+        });
+        self.script = Some(runtime);
+        orders
+    }
+
+    /// Orders from a manipulation strategy, each one tagged in its state.
+    fn manipulate(&mut self, current_price: u64, rng: &mut SimRng) -> Vec<Order> {
+        if current_price == 0 { return Vec::new(); }
+        let mut state = self.manipulation.take().unwrap_or_default();
+        let orders = match &self.strategy {
+            Strategy::Spoofer(config) => state.spoof(config, self, current_price, rng),
+            Strategy::MomentumIgniter(config) => state.ignite(config, self, current_price, rng),
+            _ => Vec::new(),
+        };
+        self.manipulation = Some(state);
+        orders
+    }
+
+    /// Avellaneda-Stoikov two-sided quotes:
+    ///   reservation r = s - q * gamma * sigma^2 * tau
+    ///   spread        = gamma * sigma^2 * tau + (2 / gamma) * ln(1 + gamma / k)
+    /// with sigma and k estimated online from price changes and our own fill rate.
+    fn quote(&mut self, current_price: u64, rng: &mut SimRng) -> Vec<Order> {
+        let Strategy::AvellanedaStoikov {
+            risk_aversion,
+            horizon,
+            arrival_at_touch,
+            quote_size,
+            inventory_limit,
+            target_inventory,
+        } = self.strategy else {
+            return Vec::new();
+        };
+        if current_price == 0 { return Vec::new(); }
+
+        let state = self.quoting.get_or_insert_with(QuoteState::new);
+
+        // Volatility: EWMA of squared tick-to-tick changes
+        if let Some(last) = state.last_price {
+            let change = current_price as f64 - last as f64;
+            let variance = 0.9 * state.volatility.powi(2) + 0.1 * change * change;
+            state.volatility = variance.sqrt().max(1.0);
+        }
+        state.last_price = Some(current_price);
+
+        // Intensity: fills arrive at A * exp(-k * delta), so k = ln(A / lambda) / delta
+        state.arrival_rate = 0.95 * state.arrival_rate + 0.05 * state.fills_since_quote as f64;
+        state.fills_since_quote = 0;
+        if state.arrival_rate > 0.0 && state.last_half_spread > 0.0 {
+            let k = (arrival_at_touch / state.arrival_rate).ln() / state.last_half_spread;
+            state.intensity = k.clamp(0.005, 1.0);
+        }
+
+        let gamma = risk_aversion.max(1e-6);
+        let q = self.inventory as f64 - target_inventory as f64;
+        let inventory_risk = gamma * state.volatility.powi(2) * horizon;
+        let reservation = current_price as f64 - q * inventory_risk;
+        let half_spread = ((inventory_risk + (2.0 / gamma) * (1.0 + gamma / state.intensity).ln()) / 2.0 * self.conditions.spread).max(1.0);
+        state.last_half_spread = half_spread;
+
+        let bid_price = (reservation - half_spread).round().max(1.0) as u64;
+        let ask_price = (reservation + half_spread).round().max(bid_price as f64 + 1.0) as u64;
+
+        let mut orders = Vec::with_capacity(2);
+        // Hard limit: stop adding to a position that is already at the cap
+        let long_capped = q >= inventory_limit as f64;
+        let short_capped = q <= -(inventory_limit as f64);
+
+        let bid_size = quote_size.min(self.max_buy(bid_price));
+        if !long_capped && bid_size > 0 {
+            orders.push(Order {
+                id: rng.next_u64(),
+                trader_id: self.id,
+                side: OrderSide::Bid,
+                price: bid_price,
+                amount: bid_size,
+                timestamp: 0,
+            });
+        }
+        let ask_size = quote_size.min(self.max_sell(ask_price));
+        if !short_capped && ask_size > 0 {
+            orders.push(Order {
+                id: rng.next_u64(),
+                trader_id: self.id,
+                side: OrderSide::Ask,
+                price: ask_price,
+                amount: ask_size,
+                timestamp: 0,
+            });
+        }
+        orders
+    }
+
+    /// The strategy's own decision, before any behavioural distortion.
+    fn decide(&mut self, current_price: u64, rng: &mut SimRng) -> Option<Order> {
         if current_price == 0 { return None; }
 
-        let mut rng = rand::thread_rng();
         let amount = 1;
 
         match self.strategy {
             Strategy::RandomWalker => {
-                if rng.gen_bool(0.5) {
-                    if self.cash >= current_price * amount {
-                        let price_noise = rng.gen_range(-20..=20);
+                // Leans towards whichever side the book looks heavier on, and the regime's way
+                let reach = (NOISE_JITTER * self.conditions.volatility).round() as i64;
+                if rng.gen_bool((0.5 + 0.25 * self.book_pressure + self.conditions.buy_bias).clamp(0.0, 1.0)) {
+                    if self.can_buy(current_price, amount) {
+                        let price_noise = rng.gen_range(-reach..=reach);
                         let price = (current_price as i64 + price_noise).max(1) as u64;
                         return Some(Order {
                             id: rng.next_u64(),
@@ -80,8 +577,8 @@ impl Agent {
                         });
                     }
                 } else {
-                    if self.inventory >= amount {
-                        let price_noise = rng.gen_range(-20..=20);
+                    if self.can_sell(current_price, amount) {
+                        let price_noise = rng.gen_range(-reach..=reach);
                         let price = (current_price as i64 + price_noise).max(1) as u64;
                         return Some(Order {
                             id: rng.next_u64(),
@@ -100,7 +597,7 @@ impl Agent {
                 let old_price = self.price_history[self.price_history.len() - window_size];
                 
                 if current_price > old_price {
-                    if self.cash >= current_price * amount {
+                    if self.can_buy(current_price, amount) {
                         return Some(Order {
                             id: rng.next_u64(),
                             trader_id: self.id,
@@ -110,38 +607,22 @@ impl Agent {
                             timestamp: 0,
                         });
                     }
-                } else if current_price < old_price {
-                    if self.inventory >= amount {
-                        return Some(Order {
-                            id: rng.next_u64(),
-                            trader_id: self.id,
-                            side: OrderSide::Ask,
-                            price: current_price,
-                            amount,
-                            timestamp: 0,
-                        });
-                    }
+                } else if current_price < old_price && self.can_sell(current_price, amount) {
+                    return Some(Order {
+                        id: rng.next_u64(),
+                        trader_id: self.id,
+                        side: OrderSide::Ask,
+                        price: current_price,
+                        amount,
+                        timestamp: 0,
+                    });
                 }
             }
             Strategy::MeanReverter { window_size, std_dev_multiplier } => {
-                if self.price_history.len() < window_size { return None; }
-                
-                let sum: u64 = self.price_history.iter().sum();
-                let mean = sum as f64 / self.price_history.len() as f64;
-                
-                let variance: f64 = self.price_history.iter()
-                    .map(|&p| {
-                        let diff = mean - p as f64;
-                        diff * diff
-                    })
-                    .sum::<f64>() / self.price_history.len() as f64;
-                let std_dev = variance.sqrt();
-
-                let upper_bound = mean + std_dev_multiplier * std_dev;
-                let lower_bound = mean - std_dev_multiplier * std_dev;
+                let (lower_bound, _, upper_bound) = self.indicators.bollinger(window_size, std_dev_multiplier)?;
 
                 if (current_price as f64) > upper_bound {
-                    if self.inventory >= amount {
+                    if self.can_sell(current_price, amount) {
                         return Some(Order {
                             id: rng.next_u64(),
                             trader_id: self.id,
@@ -151,25 +632,129 @@ impl Agent {
                             timestamp: 0,
                         });
                     }
-                } else if (current_price as f64) < lower_bound {
-                    if self.cash >= current_price * amount {
-                        return Some(Order {
-                            id: rng.next_u64(),
-                            trader_id: self.id,
-                            side: OrderSide::Bid,
-                            price: current_price,
-                            amount,
-                            timestamp: 0,
-                        });
-                    }
+                } else if (current_price as f64) < lower_bound && self.can_buy(current_price, amount) {
+                    return Some(Order {
+                        id: rng.next_u64(),
+                        trader_id: self.id,
+                        side: OrderSide::Bid,
+                        price: current_price,
+                        amount,
+                        timestamp: 0,
+                    });
+                }
+            }
+            Strategy::BeliefSwitcher {
+                intensity_of_choice,
+                fundamental_price,
+                trend_strength,
+                reversion_strength,
+                memory,
+            } => {
+                let belief = self.belief.get_or_insert_with(BeliefState::new);
+
+                // Score last tick's forecasts against the realised move: a rule earns
+                // the price change per unit if it pointed the right way, loses it otherwise.
+                if let (Some(last_price), Some((chartist, fundamentalist))) = (belief.last_price, belief.last_forecasts) {
+                    let change = current_price as f64 - last_price as f64;
+                    let chartist_profit = (chartist - last_price as f64).signum() * change;
+                    let fundamentalist_profit = (fundamentalist - last_price as f64).signum() * change;
+                    belief.chartist_fitness = memory * belief.chartist_fitness + chartist_profit;
+                    belief.fundamentalist_fitness = memory * belief.fundamentalist_fitness + fundamentalist_profit;
+                }
+
+                // Discrete choice: P(chartist) = exp(b*Uc) / (exp(b*Uc) + exp(b*Uf))
+                let fitness_gap = belief.chartist_fitness - belief.fundamentalist_fitness;
+                let p_chartist = 1.0 / (1.0 + (-intensity_of_choice * fitness_gap).exp());
+                belief.rule = if rng.gen_bool(p_chartist.clamp(0.0, 1.0)) {
+                    BeliefRule::Chartist
+                } else {
+                    BeliefRule::Fundamentalist
+                };
+
+                let price = current_price as f64;
+                let previous = if self.price_history.len() >= 2 {
+                    self.price_history[self.price_history.len() - 2] as f64
+                } else {
+                    price
+                };
+                let chartist = price + trend_strength * (price - previous);
+                let fundamentalist = price + reversion_strength * (fundamental_price as f64 * self.conditions.fundamental - price);
+                belief.last_price = Some(current_price);
+                belief.last_forecasts = Some((chartist, fundamentalist));
+
+                let forecast = match belief.rule {
+                    BeliefRule::Chartist => chartist,
+                    BeliefRule::Fundamentalist => fundamentalist,
+                };
+                // Quote halfway towards the forecast, so stronger convictions cross the spread.
+                let limit = ((price + forecast) / 2.0).round().max(1.0) as u64;
+
+                if forecast > price + 1.0 && self.can_buy(limit, amount) {
+                    return Some(Order {
+                        id: rng.next_u64(),
+                        trader_id: self.id,
+                        side: OrderSide::Bid,
+                        price: limit,
+                        amount,
+                        timestamp: 0,
+                    });
+                } else if forecast < price - 1.0 && self.can_sell(current_price, amount) {
+                    return Some(Order {
+                        id: rng.next_u64(),
+                        trader_id: self.id,
+                        side: OrderSide::Ask,
+                        price: limit,
+                        amount,
+                        timestamp: 0,
+                    });
                 }
             }
+            Strategy::Herder { imitation_probability } => {
+                // Imitate with a probability that grows with how one-sided the neighbourhood is.
+                // Herders cross the spread slightly, which is what lets a cascade move the price.
+                let signal = self.social_signal;
+                if !rng.gen_bool((imitation_probability * self.conditions.herding * signal.abs()).clamp(0.0, 1.0)) {
+                    return None;
+                }
+                if signal > 0.0 && self.can_buy(current_price + 10, amount) {
+                    return Some(Order {
+                        id: rng.next_u64(),
+                        trader_id: self.id,
+                        side: OrderSide::Bid,
+                        price: current_price + 10,
+                        amount,
+                        timestamp: 0,
+                    });
+                } else if signal < 0.0 && self.can_sell(current_price, amount) {
+                    return Some(Order {
+                        id: rng.next_u64(),
+                        trader_id: self.id,
+                        side: OrderSide::Ask,
+                        price: current_price.saturating_sub(10).max(1),
+                        amount,
+                        timestamp: 0,
+                    });
+                }
+            }
+            Strategy::QLearner { .. } => {
+                let obs = self.observe(current_price);
+                let equity = self.equity(current_price);
+                let learner = self.learner.get_or_insert_with(|| QLearner::new(0.1, 0.1, 0.95));
+                let action = learner.step(&obs, equity, rng);
+                return self.order_for(action, current_price, rng);
+            }
+            // Run through `run_script` / driven externally
+            Strategy::Scripted { .. } | Strategy::External => {}
+            // Two-sided, handled by `quote`
+            Strategy::AvellanedaStoikov { .. } => {}
+            // Handled by `manipulate`
+            Strategy::Spoofer(_) | Strategy::MomentumIgniter(_) => {}
             Strategy::MarketMaker { spread_bps } => {
-                let spread_amount = (current_price as f64 * (spread_bps as f64 / 10000.0)) as u64;
+                let spread_amount = (current_price as f64 * (spread_bps as f64 / 10000.0) * self.conditions.spread) as u64;
                 let spread_amount = spread_amount.max(10);
                 
                 if rng.gen_bool(0.5) {
-                    if self.cash >= (current_price.saturating_sub(spread_amount)) * amount {
+                    if self.can_buy(current_price.saturating_sub(spread_amount), amount) {
                         return Some(Order {
                             id: rng.next_u64(),
                             trader_id: self.id,
@@ -180,7 +765,7 @@ impl Agent {
                         });
                     }
                 } else {
-                    if self.inventory >= amount {
+                    if self.can_sell(current_price, amount) {
                         return Some(Order {
                             id: rng.next_u64(),
                             trader_id: self.id,
@@ -196,3 +781,44 @@ impl Agent {
         None
     }
 }
+
+/// The stock agent mix used by the server and the RL environment, ids from 1.
+pub fn default_population() -> Vec<Agent> {
+    // The built-in scenario has no scripted groups, so nothing here can fail
+    Scenario::default().population(0, 1, &mut ScriptHost::new()).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Bid and ask from an Avellaneda-Stoikov agent holding `inventory` against a target of 1000.
+    fn quotes(inventory: u64) -> (u64, u64) {
+        let strategy = Strategy::AvellanedaStoikov {
+            risk_aversion: 0.001,
+            horizon: 10.0,
+            arrival_at_touch: 2.0,
+            quote_size: 5,
+            inventory_limit: 500,
+            target_inventory: 1000,
+        };
+        let mut agent = Agent::new(1, "AS".to_string(), 10_000_000, inventory, strategy);
+        let orders = agent.quote(10000, &mut SimRng::seed_from_u64(1));
+        let price = |side| orders.iter().find(|o| o.side == side).map(|o| o.price).unwrap();
+        (price(OrderSide::Bid), price(OrderSide::Ask))
+    }
+
+    #[test]
+    fn reservation_price_skews_against_inventory() {
+        // gamma * sigma^2 * tau = 0.001 * 10^2 * 10 = 1 cent per unit off target
+        let (bid, ask) = quotes(1000);
+        assert_eq!(bid + ask, 2 * 10000);
+
+        let (long_bid, long_ask) = quotes(1300);
+        assert_eq!(long_bid + long_ask, 2 * (10000 - 300));
+        assert_eq!(ask - bid, long_ask - long_bid);
+
+        let (short_bid, short_ask) = quotes(700);
+        assert_eq!(short_bid + short_ask, 2 * (10000 + 300));
+    }
+}
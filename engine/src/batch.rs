@@ -0,0 +1,237 @@
+//! Headless experiments: outcome statistics for one run, and a pool of threads
+//! that works through many runs at once.
+
+use crate::types::Trade;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// A fall of this fraction from the highest close of the last `CRASH_WINDOW`
+/// ticks counts as a crash.
+pub const CRASH_DRAWDOWN: f64 = 0.15;
+pub const CRASH_WINDOW: usize = 50;
+
+/// Outcome metrics for one run. Returns are per-tick log returns of the close.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RunSummary {
+    pub run: usize,
+    pub seed: u64,
+    pub ticks: u64,
+    pub trades: u64,
+    pub volume: u64,
+    pub initial_price: u64,
+    pub final_price: u64,
+    pub min_price: u64,
+    pub max_price: u64,
+    /// Standard deviation of returns
+    pub volatility: f64,
+    /// Excess kurtosis of returns; 0 for a normal distribution
+    pub kurtosis: f64,
+    /// Largest peak-to-trough fall, as a fraction of the peak
+    pub max_drawdown: f64,
+    pub crashes: u64,
+    /// Mean quoted spread over ticks with both sides of the book, in bps of mid
+    pub mean_spread_bps: f64,
+    pub liquidations: u64,
+    pub alerts: u64,
+    pub population: usize,
+}
+
+/// Running central moments of a series, so a run of any length keeps its
+/// return statistics in constant space (Pébay's one-pass update).
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct Moments {
+    n: u64,
+    mean: f64,
+    /// Sums of the 2nd, 3rd and 4th powers of deviations from the mean
+    m2: f64,
+    m3: f64,
+    m4: f64,
+}
+
+impl Moments {
+    fn push(&mut self, x: f64) {
+        let n1 = self.n as f64;
+        self.n += 1;
+        let n = self.n as f64;
+        let delta = x - self.mean;
+        let delta_n = delta / n;
+        let delta_n2 = delta_n * delta_n;
+        let term = delta * delta_n * n1;
+        self.mean += delta_n;
+        self.m4 += term * delta_n2 * (n * n - 3.0 * n + 3.0) + 6.0 * delta_n2 * self.m2 - 4.0 * delta_n * self.m3;
+        self.m3 += term * delta_n * (n - 2.0) - 3.0 * delta_n * self.m2;
+        self.m2 += term;
+    }
+
+    /// Population standard deviation and excess kurtosis; zeros below two values.
+    fn volatility_and_kurtosis(&self) -> (f64, f64) {
+        if self.n < 2 {
+            return (0.0, 0.0);
+        }
+        let n = self.n as f64;
+        let variance = self.m2 / n;
+        (variance.sqrt(), if variance > 0.0 { (self.m4 / n) / (variance * variance) - 3.0 } else { 0.0 })
+    }
+}
+
+/// Accumulates a run's statistics one tick at a time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunStats {
+    initial_price: u64,
+    last_price: u64,
+    ticks: u64,
+    returns: Moments,
+    trades: u64,
+    volume: u64,
+    min_price: u64,
+    max_price: u64,
+    peak: u64,
+    max_drawdown: f64,
+    /// Closes of the last `CRASH_WINDOW` ticks
+    recent: VecDeque<u64>,
+    crashes: u64,
+    /// Ticks until another crash can be counted, so one slide counts once
+    crash_cooldown: usize,
+    spread_sum: f64,
+    spread_ticks: u64,
+    pub liquidations: u64,
+    pub alerts: u64,
+}
+
+impl RunStats {
+    pub fn new(initial_price: u64) -> Self {
+        Self {
+            initial_price,
+            last_price: initial_price,
+            ticks: 0,
+            returns: Moments::default(),
+            trades: 0,
+            volume: 0,
+            min_price: initial_price,
+            max_price: initial_price,
+            peak: initial_price,
+            max_drawdown: 0.0,
+            recent: VecDeque::new(),
+            crashes: 0,
+            crash_cooldown: 0,
+            spread_sum: 0.0,
+            spread_ticks: 0,
+            liquidations: 0,
+            alerts: 0,
+        }
+    }
+
+    /// Record a closed tick: its last price, the touch and its trades.
+    pub fn record_tick(&mut self, price: u64, best_bid: Option<u64>, best_ask: Option<u64>, trades: &[Trade]) {
+        if price > 0 && self.last_price > 0 {
+            self.returns.push((price as f64 / self.last_price as f64).ln());
+        }
+        self.last_price = price;
+        self.ticks += 1;
+        self.trades += trades.len() as u64;
+        self.volume += trades.iter().map(|t| t.amount).sum::<u64>();
+        self.min_price = self.min_price.min(price);
+        self.max_price = self.max_price.max(price);
+
+        self.peak = self.peak.max(price);
+        if self.peak > 0 {
+            self.max_drawdown = self.max_drawdown.max(1.0 - price as f64 / self.peak as f64);
+        }
+
+        let recent_high = self.recent.iter().copied().max().unwrap_or(price);
+        self.crash_cooldown = self.crash_cooldown.saturating_sub(1);
+        if self.crash_cooldown == 0 && recent_high > 0 && (price as f64) <= recent_high as f64 * (1.0 - CRASH_DRAWDOWN) {
+            self.crashes += 1;
+            self.crash_cooldown = CRASH_WINDOW;
+        }
+        self.recent.push_back(price);
+        if self.recent.len() > CRASH_WINDOW {
+            self.recent.pop_front();
+        }
+
+        if let (Some(bid), Some(ask)) = (best_bid, best_ask) {
+            if ask > bid {
+                let mid = (bid + ask) as f64 / 2.0;
+                self.spread_sum += (ask - bid) as f64 / mid * 10000.0;
+                self.spread_ticks += 1;
+            }
+        }
+    }
+
+    pub fn crashes(&self) -> u64 {
+        self.crashes
+    }
+
+    /// `run` is left at 0 for the caller to fill in.
+    pub fn summary(&self, seed: u64, population: usize) -> RunSummary {
+        let (volatility, kurtosis) = self.returns.volatility_and_kurtosis();
+        RunSummary {
+            run: 0,
+            seed,
+            ticks: self.ticks,
+            trades: self.trades,
+            volume: self.volume,
+            initial_price: self.initial_price,
+            final_price: self.last_price,
+            min_price: self.min_price,
+            max_price: self.max_price,
+            volatility,
+            kurtosis,
+            max_drawdown: self.max_drawdown,
+            crashes: self.crashes,
+            mean_spread_bps: if self.spread_ticks > 0 { self.spread_sum / self.spread_ticks as f64 } else { 0.0 },
+            liquidations: self.liquidations,
+            alerts: self.alerts,
+            population,
+        }
+    }
+}
+
+/// Run `run(0..runs)` on `threads` worker threads, results in run order.
+pub fn run_parallel<T, F>(runs: usize, threads: usize, run: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(usize) -> T + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<T>>> = Mutex::new((0..runs).map(|_| None).collect());
+    std::thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, runs.max(1)) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= runs {
+                    break;
+                }
+                let result = run(i);
+                if let Ok(mut results) = results.lock() {
+                    results[i] = Some(result);
+                }
+            });
+        }
+    });
+    results.into_inner().unwrap_or_default().into_iter().flatten().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn running_moments_match_two_passes() {
+        let xs: Vec<f64> = (0..500).map(|i| ((i * 7919 % 101) as f64 - 50.0) / 1000.0 + if i % 97 == 0 { 0.3 } else { 0.0 }).collect();
+        let mut moments = Moments::default();
+        for &x in &xs {
+            moments.push(x);
+        }
+
+        let n = xs.len() as f64;
+        let mean = xs.iter().sum::<f64>() / n;
+        let m2 = xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / n;
+        let m4 = xs.iter().map(|x| (x - mean).powi(4)).sum::<f64>() / n;
+        let (volatility, kurtosis) = moments.volatility_and_kurtosis();
+        assert!((volatility - m2.sqrt()).abs() < 1e-12);
+        assert!((kurtosis - (m4 / (m2 * m2) - 3.0)).abs() < 1e-9);
+    }
+}
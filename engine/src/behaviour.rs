@@ -0,0 +1,155 @@
+use crate::agents::Agent;
+use crate::types::{Order, OrderSide};
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Behavioural biases layered on top of any `Strategy`.
+///
+/// The strategy decides what it would do as a rational rule-follower, then the
+/// traits distort that decision. Each field has a neutral value that switches
+/// the bias off, so `BehaviouralTraits::default()` changes nothing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BehaviouralTraits {
+    /// Prospect-theory lambda. Losses relative to the reference price weigh
+    /// this many times more than gains. 1.0 = neutral, ~2.25 is the classic estimate.
+    pub loss_aversion: f64,
+    /// Disposition effect, 0.0-1.0. Probability scale for realising paper gains
+    /// early and refusing to sell at a paper loss.
+    pub disposition: f64,
+    /// Multiplier on order size. 1.0 = neutral, 2.0 = trades twice as big as it should.
+    pub overconfidence: f64,
+    /// Weight, 0.0-1.0, pulling limit prices towards the highest price in memory.
+    pub anchoring: f64,
+    /// Volume-weighted cost of the current inventory, used as the reference point.
+    pub reference_price: Option<f64>,
+}
+
+impl Default for BehaviouralTraits {
+    fn default() -> Self {
+        Self {
+            loss_aversion: 1.0,
+            disposition: 0.0,
+            overconfidence: 1.0,
+            anchoring: 0.0,
+            reference_price: None,
+        }
+    }
+}
+
+impl BehaviouralTraits {
+    /// Kahneman-Tversky investor: loss averse and prone to the disposition effect.
+    pub fn prospect_theory() -> Self {
+        Self {
+            loss_aversion: 2.25,
+            disposition: 0.5,
+            ..Self::default()
+        }
+    }
+
+    /// Trades too big and anchors on the recent high.
+    pub fn overconfident() -> Self {
+        Self {
+            overconfidence: 3.0,
+            anchoring: 0.3,
+            ..Self::default()
+        }
+    }
+
+    /// Update the reference price after a fill. Buys move the volume-weighted
+    /// cost basis by the units they actually added, sells leave it alone, and
+    /// a flat position forgets it.
+    pub fn on_fill(&mut self, side: OrderSide, price: u64, inventory_before: u64, inventory_after: u64) {
+        match side {
+            OrderSide::Bid => {
+                // A buy the agent couldn't pay for, or one covering a short, adds nothing to hold
+                let added = inventory_after.saturating_sub(inventory_before);
+                let held = inventory_before as f64 * self.reference_price.unwrap_or(price as f64);
+                let bought = price as f64 * added as f64;
+                if added > 0 {
+                    self.reference_price = Some((held + bought) / inventory_after as f64);
+                }
+            }
+            OrderSide::Ask => {
+                if inventory_after == 0 {
+                    self.reference_price = None;
+                }
+            }
+        }
+    }
+
+    /// Distort the strategy's decision. May drop the order, resize it, move its
+    /// price, or produce a sell where the strategy wanted to do nothing.
+    pub fn apply(&self, order: Option<Order>, agent: &Agent, current_price: u64, rng: &mut impl Rng) -> Option<Order> {
+        let inventory = agent.inventory;
+        let price = current_price as f64;
+        // Relative paper gain (+) or loss (-) on the position
+        let gain = match self.reference_price {
+            Some(reference) if inventory > 0 && reference > 0.0 => (price - reference) / reference,
+            _ => 0.0,
+        };
+
+        let mut order = match order {
+            Some(order) => order,
+            None => {
+                // Disposition: lock in a winner even though the strategy is idle
+                if gain > 0.0 && inventory > 0 && rng.gen_bool((self.disposition * (gain * 20.0).min(1.0)).clamp(0.0, 1.0)) {
+                    Order {
+                        id: rng.next_u64(),
+                        trader_id: agent.id,
+                        side: OrderSide::Ask,
+                        price: current_price,
+                        amount: 1,
+                        timestamp: 0,
+                    }
+                } else {
+                    return None;
+                }
+            }
+        };
+
+        if gain < 0.0 {
+            match order.side {
+                // Disposition: hold losers rather than realise the loss
+                OrderSide::Ask => {
+                    if rng.gen_bool(self.disposition.clamp(0.0, 1.0)) {
+                        return None;
+                    }
+                }
+                // Loss aversion: a position under water feels lambda times worse,
+                // so adding to it is only done with probability 1/lambda
+                OrderSide::Bid => {
+                    if self.loss_aversion > 1.0 && !rng.gen_bool(1.0 / self.loss_aversion) {
+                        return None;
+                    }
+                }
+            }
+        }
+
+        // Anchoring: quotes drift towards the recent high
+        if self.anchoring > 0.0 {
+            if let Some(&high) = agent.price_history.iter().max() {
+                if high > order.price {
+                    let pull = (high - order.price) as f64 * self.anchoring.clamp(0.0, 1.0);
+                    order.price += pull.round() as u64;
+                }
+            }
+        }
+
+        // Overconfidence: bigger size, capped by what the agent actually holds
+        if self.overconfidence != 1.0 {
+            let scaled = ((order.amount as f64) * self.overconfidence).round().max(1.0) as u64;
+            let cap = match order.side {
+                OrderSide::Bid => agent.max_buy(order.price),
+                OrderSide::Ask => agent.max_sell(order.price),
+            };
+            order.amount = scaled.min(cap);
+        }
+
+        // Anchoring can lift a bid past what the agent can pay for
+        let affordable = match order.side {
+            OrderSide::Bid => agent.can_buy(order.price, order.amount),
+            OrderSide::Ask => agent.can_sell(order.price, order.amount),
+        };
+        (order.amount > 0 && affordable).then_some(order)
+    }
+}
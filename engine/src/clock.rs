@@ -0,0 +1,120 @@
+//! Simulated time and how it is paced against the wall clock.
+//!
+//! The simulation runs off a queue of timestamped events (see `scheduling`) in
+//! simulated nanoseconds. A tick is `TICK_NS` of simulated time; the end of
+//! each tick is itself an event that settles trades and broadcasts the ticker.
+//! `Clock` only decides when the next event may be released.
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Simulated nanoseconds since the start of the run.
+pub type SimTime = u64;
+
+/// Simulated length of one tick.
+pub const TICK_NS: SimTime = 100_000_000;
+
+/// Default wall-clock length of a tick in `Pacing::Ticked`: the old 10 ms
+/// command window plus the 100 ms sleep. Scenarios can change it (`tick_ms`).
+pub const TICKED_WALL_MS: u64 = 110;
+
+pub fn tick_start(tick: u64) -> SimTime {
+    tick * TICK_NS
+}
+
+/// Fractional ticks, as used by activation profiles, to simulated time.
+pub fn ticks_to_ns(ticks: f64) -> SimTime {
+    (ticks.max(0.0) * TICK_NS as f64) as SimTime
+}
+
+/// Serialized with the `--pacing` names: `{"mode": "realtime", "speed": 2.0}`.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "mode")]
+pub enum Pacing {
+    /// A whole tick at once, then a fixed wall-clock pause before the next
+    #[default]
+    #[serde(rename = "ticked")]
+    Ticked,
+    /// Each event waits until wall-clock time catches up with simulated time;
+    /// `speed` 2.0 runs twice as fast as real time
+    #[serde(rename = "realtime")]
+    RealTime { speed: f64 },
+    /// No waiting at all
+    #[serde(rename = "fast")]
+    AsFastAsPossible,
+}
+
+#[derive(Debug)]
+pub struct Clock {
+    pacing: Pacing,
+    origin_wall: Instant,
+    origin_sim: SimTime,
+    /// Tick of the last released event, for `Pacing::Ticked`
+    tick: u64,
+    tick_started: Instant,
+    /// Wall-clock length of a tick for `Pacing::Ticked`
+    tick_wall: Duration,
+}
+
+impl Clock {
+    pub fn new(pacing: Pacing) -> Self {
+        let now = Instant::now();
+        Self {
+            pacing,
+            origin_wall: now,
+            origin_sim: 0,
+            tick: 0,
+            tick_started: now,
+            tick_wall: Duration::from_millis(TICKED_WALL_MS),
+        }
+    }
+
+    /// Wall-clock milliseconds per tick under `Pacing::Ticked`.
+    pub fn set_tick_ms(&mut self, ms: u64) {
+        self.tick_wall = Duration::from_millis(ms);
+    }
+
+    pub fn tick_ms(&self) -> u64 {
+        self.tick_wall.as_millis() as u64
+    }
+
+    pub fn pacing(&self) -> Pacing {
+        self.pacing
+    }
+
+    /// Switch pacing from the event at `at` on, without catching up or holding back.
+    pub fn set_pacing(&mut self, pacing: Pacing, at: SimTime) {
+        self.pacing = pacing;
+        self.rebase(at);
+    }
+
+    /// Wall-clock instant at which an event at `at` may run.
+    pub fn due(&self, at: SimTime) -> Instant {
+        match self.pacing {
+            Pacing::Ticked if at / TICK_NS > self.tick => self.tick_started + self.tick_wall,
+            Pacing::Ticked | Pacing::AsFastAsPossible => self.tick_started,
+            Pacing::RealTime { speed } => {
+                let elapsed = at.saturating_sub(self.origin_sim) as f64 / speed.max(1e-6);
+                self.origin_wall + Duration::from_nanos(elapsed as u64)
+            }
+        }
+    }
+
+    /// Record that the event at `at` was released.
+    pub fn advance(&mut self, at: SimTime) {
+        if at / TICK_NS > self.tick {
+            self.tick = at / TICK_NS;
+            self.tick_started = Instant::now();
+        }
+    }
+
+    /// Treat `at` as happening now, e.g. after a pause or a reset, so real-time
+    /// pacing doesn't try to catch up on the gap.
+    pub fn rebase(&mut self, at: SimTime) {
+        let now = Instant::now();
+        self.origin_wall = now;
+        self.origin_sim = at;
+        self.tick = at / TICK_NS;
+        self.tick_started = now;
+    }
+}
@@ -0,0 +1,36 @@
+//! Console chatter from the simulation: every trade, God Mode command and
+//! alert. Batch runs switch it off, since many sims printing every fill would
+//! spend most of their time fighting over stdout.
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static THREAD_QUIET: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Silence `say!` for the whole process.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+/// Silence `say!` on this thread only, e.g. for a side run next to the live market.
+pub fn set_thread_quiet(quiet: bool) {
+    THREAD_QUIET.with(|q| q.set(quiet));
+}
+
+pub fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed) || THREAD_QUIET.with(Cell::get)
+}
+
+/// `println!` unless the console has been silenced.
+#[macro_export]
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if !$crate::console::quiet() {
+            println!($($arg)*);
+        }
+    };
+}
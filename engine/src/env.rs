@@ -0,0 +1,194 @@
+use crate::agents::Agent;
+use crate::rl::{Action, Observation};
+use crate::scenario::Scenario;
+use crate::scheduling::SchedulerConfig;
+use crate::simulation::Simulation;
+use crate::types::{ExternalInstruction, SessionEvent, SimRng, SimulationCommand};
+use rand::prelude::*;
+use tokio::sync::mpsc;
+
+/// Settings for a `MarketEnv` episode.
+#[derive(Debug, Clone)]
+pub struct EnvConfig {
+    /// Episode length in ticks
+    pub max_steps: u64,
+    /// Ticks the population trades on its own before the first observation
+    pub warmup_ticks: u64,
+    /// Background population, opening price and shock schedule. `tick_ms` is ignored.
+    pub scenario: Scenario,
+    /// The player's starting balances
+    pub starting_cash: u64,
+    pub starting_inventory: u64,
+    /// Activation rates and latency of the background population
+    pub scheduling: SchedulerConfig,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        Self {
+            max_steps: 1000,
+            warmup_ticks: 20,
+            scenario: Scenario::default(),
+            starting_cash: 1000000,
+            starting_inventory: 1000,
+            scheduling: SchedulerConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StepResult {
+    pub observation: Observation,
+    /// Change in the player's mark-to-market equity this step, in dollars
+    pub reward: f64,
+    /// Episode over: `max_steps` reached or the player is wiped out
+    pub done: bool,
+}
+
+/// Gym-style wrapper around a `Simulation` of a scenario (the built-in one
+/// unless `EnvConfig::scenario` says otherwise).
+///
+/// The player joins the market as a trading session does, as one extra
+/// `Strategy::External` agent. Each `step` queues the player's action and runs
+/// the simulation for a tick, so the player's order reaches the book on its
+/// turn like any session's. The simulation reports to the console as it does
+/// anywhere else; training loops usually want `console::set_quiet(true)`.
+pub struct MarketEnv {
+    config: EnvConfig,
+    sim: Simulation,
+    /// The player's session feed, drained each step since nothing reads it
+    events: mpsc::UnboundedReceiver<SessionEvent>,
+    /// Ids for the player's orders
+    rng: SimRng,
+    player_id: u64,
+    steps: u64,
+}
+
+impl MarketEnv {
+    /// An environment ready for its first episode (seed 0). Fails if the
+    /// scenario cannot be built, e.g. a scripted group's script won't load.
+    pub fn new(config: EnvConfig) -> Result<Self, String> {
+        let sim = Simulation::new(config.scenario.clone(), 0)?;
+        let mut env = Self {
+            config,
+            sim,
+            events: mpsc::unbounded_channel().1,
+            rng: SimRng::seed_from_u64(0),
+            player_id: 0,
+            steps: 0,
+        };
+        env.reset(0)?;
+        Ok(env)
+    }
+
+    /// Start a fresh episode. Equal seeds and equal actions give equal
+    /// episodes, as for any seeded `Simulation`.
+    pub fn reset(&mut self, seed: u64) -> Result<Observation, String> {
+        self.sim = Simulation::new(self.config.scenario.clone(), seed)?;
+        // Tick 1's activations are already drawn; the profiles apply from tick 2
+        let scheduling = self.config.scheduling.clone();
+        self.sim.apply(SimulationCommand::SetActivation { kind: None, profile: scheduling.default });
+        for (kind, profile) in scheduling.by_kind {
+            self.sim.apply(SimulationCommand::SetActivation { kind: Some(kind), profile });
+        }
+
+        let (reply, mut events) = mpsc::unbounded_channel();
+        self.sim.apply(SimulationCommand::RegisterSession {
+            name: "Player".to_string(),
+            cash: self.config.starting_cash,
+            inventory: self.config.starting_inventory,
+            reply,
+        });
+        let Ok(SessionEvent::Registered { agent_id, .. }) = events.try_recv() else {
+            return Err("the simulation did not register the player".to_string());
+        };
+        self.player_id = agent_id;
+        self.events = events;
+        self.rng = SimRng::seed_from_u64(seed);
+        self.steps = 0;
+
+        for _ in 0..self.config.warmup_ticks {
+            self.sim.step();
+        }
+        while self.events.try_recv().is_ok() {}
+        self.observe()
+    }
+
+    pub fn observe(&self) -> Result<Observation, String> {
+        Ok(self.agent()?.observe(self.sim.current_price()))
+    }
+
+    /// The player's agent, while it is in the market.
+    pub fn player(&self) -> Option<&Agent> {
+        self.sim.agents().get(&self.player_id)
+    }
+
+    fn agent(&self) -> Result<&Agent, String> {
+        self.player().ok_or_else(|| format!("player {} is not in the market", self.player_id))
+    }
+
+    /// The market the player trades in.
+    pub fn simulation(&self) -> &Simulation {
+        &self.sim
+    }
+
+    /// Ticks closed this episode, warm-up included.
+    pub fn tick(&self) -> u64 {
+        self.sim.tick() - 1
+    }
+
+    pub fn current_price(&self) -> u64 {
+        self.sim.current_price()
+    }
+
+    pub fn step(&mut self, action: Action) -> Result<StepResult, String> {
+        // Borrow the agent through `sim` alone so `rng` stays free for the order
+        let Some(player) = self.sim.agents().get(&self.player_id) else {
+            return Err(format!("player {} is not in the market", self.player_id));
+        };
+        let equity_before = player.equity(self.sim.current_price());
+        let order = player.order_for(action, self.sim.current_price(), &mut self.rng);
+        if let Some(order) = order {
+            self.sim.apply(SimulationCommand::SessionInstruction {
+                trader_id: self.player_id,
+                instruction: ExternalInstruction::Submit(order),
+            });
+        }
+        self.sim.step();
+        self.steps += 1;
+        while self.events.try_recv().is_ok() {}
+
+        let equity = self.agent()?.equity(self.sim.current_price());
+        Ok(StepResult {
+            observation: self.observe()?,
+            reward: (equity - equity_before) as f64 / 100.0,
+            done: self.steps >= self.config.max_steps || equity <= 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console;
+    use crate::scenario::StrategySpec;
+
+    #[test]
+    fn same_seed_same_episode() {
+        console::set_thread_quiet(true);
+        let episode = |seed| {
+            let mut env = MarketEnv::new(EnvConfig { max_steps: 100, ..EnvConfig::default() }).unwrap();
+            env.reset(seed).unwrap();
+            let actions = [Action::Buy, Action::Hold, Action::Sell];
+            (0..100).map(|i| env.step(actions[i % 3]).unwrap().reward).collect::<Vec<_>>()
+        };
+        assert_eq!(episode(5), episode(5));
+    }
+
+    #[test]
+    fn bad_scenario_is_an_error() {
+        let mut scenario = Scenario::default();
+        scenario.groups[0].strategy = StrategySpec::Scripted { path: "scripts/missing.rhai".to_string() };
+        assert!(MarketEnv::new(EnvConfig { scenario, ..EnvConfig::default() }).is_err());
+    }
+}
@@ -0,0 +1,148 @@
+//! Classroom trading game: people trade against the bot population through
+//! the `/api/game` endpoints and are ranked by PnL on a live leaderboard.
+//!
+//! A player is an `External` agent like a `/ws/trade` session. This module
+//! only keeps the score: each player's starting equity and a short log of what
+//! happened to their orders.
+
+use crate::agents::Agent;
+use crate::orderbook::OrderBook;
+use crate::types::{Order, SessionEvent};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct GameConfig {
+    pub starting_cash: u64,
+    pub starting_inventory: u64,
+    /// Ticks between leaderboard broadcasts
+    pub leaderboard_interval: u64,
+    /// Order events kept per player for `/api/game/players/:id`
+    pub event_log: usize,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            starting_cash: 1000000,
+            starting_inventory: 1000,
+            leaderboard_interval: 10,
+            event_log: 20,
+        }
+    }
+}
+
+/// One row of the leaderboard. Money is in cents.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Standing {
+    pub player_id: u64,
+    pub name: String,
+    pub cash: u64,
+    pub inventory: u64,
+    pub equity: i64,
+    pub pnl: i64,
+    pub return_pct: f64,
+}
+
+/// Everything a player needs to see about their own account.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerReport {
+    pub rank: usize,
+    pub standing: Standing,
+    pub open_orders: Vec<Order>,
+    pub events: Vec<SessionEvent>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Player {
+    starting_equity: i64,
+    events: VecDeque<SessionEvent>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Game {
+    config: GameConfig,
+    players: HashMap<u64, Player>,
+}
+
+impl Game {
+    pub fn new(config: GameConfig) -> Self {
+        Self { config, players: HashMap::new() }
+    }
+
+    pub fn config(&self) -> &GameConfig {
+        &self.config
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.players.is_empty()
+    }
+
+    pub fn is_player(&self, id: u64) -> bool {
+        self.players.contains_key(&id)
+    }
+
+    /// Start scoring `agent` from its current equity.
+    pub fn add_player(&mut self, agent: &Agent, current_price: u64) {
+        self.players.insert(agent.id, Player {
+            starting_equity: agent.equity(current_price),
+            events: VecDeque::new(),
+        });
+    }
+
+    /// Re-baseline everyone, e.g. after God Mode resets balances.
+    pub fn restart(&mut self, agents: &HashMap<u64, Agent>, current_price: u64) {
+        for (id, player) in &mut self.players {
+            if let Some(agent) = agents.get(id) {
+                player.starting_equity = agent.equity(current_price);
+            }
+            player.events.clear();
+        }
+    }
+
+    /// Keep an order event for the player's report. Ignores non-players.
+    pub fn record(&mut self, id: u64, event: &SessionEvent) {
+        if let Some(player) = self.players.get_mut(&id) {
+            if player.events.len() == self.config.event_log {
+                player.events.pop_front();
+            }
+            player.events.push_back(event.clone());
+        }
+    }
+
+    /// All players, best PnL first.
+    pub fn leaderboard(&self, agents: &HashMap<u64, Agent>, current_price: u64) -> Vec<Standing> {
+        let mut standings: Vec<Standing> = self.players.iter()
+            .filter_map(|(id, player)| {
+                let agent = agents.get(id)?;
+                let equity = agent.equity(current_price);
+                let pnl = equity - player.starting_equity;
+                Some(Standing {
+                    player_id: *id,
+                    name: agent.name.clone(),
+                    cash: agent.cash,
+                    inventory: agent.inventory,
+                    equity,
+                    pnl,
+                    return_pct: pnl as f64 / player.starting_equity.max(1) as f64 * 100.0,
+                })
+            })
+            .collect();
+        standings.sort_by(|a, b| b.pnl.cmp(&a.pnl).then(a.player_id.cmp(&b.player_id)));
+        standings
+    }
+
+    pub fn report(&self, id: u64, agents: &HashMap<u64, Agent>, book: &OrderBook, current_price: u64) -> Option<PlayerReport> {
+        let player = self.players.get(&id)?;
+        let (rank, standing) = self.leaderboard(agents, current_price)
+            .into_iter()
+            .enumerate()
+            .find(|(_, s)| s.player_id == id)?;
+        Some(PlayerReport {
+            rank: rank + 1,
+            standing,
+            open_orders: book.trader_orders(id),
+            events: player.events.iter().cloned().collect(),
+        })
+    }
+}
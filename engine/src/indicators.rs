@@ -0,0 +1,390 @@
+//! Incremental technical indicators.
+//!
+//! An agent subscribes to the indicators it wants (`Agent::subscribe`), and the
+//! simulation loop feeds every agent one `Bar` as each tick closes. Every
+//! indicator is updated in O(1) per bar, so windows can be as long as you like
+//! without strategies re-scanning their price history.
+
+use crate::types::Trade;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// One tick of market data: the last price and what traded during the tick.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Bar {
+    pub price: u64,
+    pub volume: u64,
+    /// Sum of price * amount over the tick's trades, in cents
+    pub turnover: u64,
+}
+
+impl Bar {
+    /// A bar with no trades behind it.
+    pub fn at(price: u64) -> Self {
+        Self { price, volume: 0, turnover: 0 }
+    }
+
+    /// The bar for a tick's trades, closing at the last one (or at `previous`
+    /// if nothing traded).
+    pub fn from_trades(previous: u64, trades: &[Trade]) -> Self {
+        Self {
+            price: trades.last().map_or(previous, |t| t.price),
+            volume: trades.iter().map(|t| t.amount).sum(),
+            turnover: trades.iter().map(|t| t.price * t.amount).sum(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum IndicatorSpec {
+    /// Simple moving average of the last `window` prices
+    Sma { window: usize },
+    /// Exponential moving average with alpha = 2 / (period + 1)
+    Ema { period: usize },
+    /// Population standard deviation of the last `window` prices
+    StdDev { window: usize },
+    /// Wilder's relative strength index, 0-100
+    Rsi { period: usize },
+    /// MACD line, signal line and histogram
+    Macd { fast: usize, slow: usize, signal: usize },
+    /// Moving average and standard deviation; bands are `middle +- k * std_dev`
+    Bollinger { window: usize },
+    /// Volume-weighted average price over the last `window` bars
+    Vwap { window: usize },
+}
+
+/// Rolling sum and sum of squares, backing SMA, standard deviation and Bollinger.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Rolling {
+    window: usize,
+    values: VecDeque<f64>,
+    sum: f64,
+    sum_sq: f64,
+}
+
+impl Rolling {
+    fn new(window: usize) -> Self {
+        Self { window: window.max(1), values: VecDeque::new(), sum: 0.0, sum_sq: 0.0 }
+    }
+
+    fn push(&mut self, x: f64) {
+        self.values.push_back(x);
+        self.sum += x;
+        self.sum_sq += x * x;
+        if self.values.len() > self.window {
+            let old = self.values.pop_front().unwrap_or(0.0);
+            self.sum -= old;
+            self.sum_sq -= old * old;
+        }
+    }
+
+    fn ready(&self) -> bool {
+        self.values.len() >= self.window
+    }
+
+    fn mean(&self) -> Option<f64> {
+        self.ready().then(|| self.sum / self.window as f64)
+    }
+
+    fn std_dev(&self) -> Option<f64> {
+        let mean = self.mean()?;
+        Some((self.sum_sq / self.window as f64 - mean * mean).max(0.0).sqrt())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Ema {
+    period: usize,
+    alpha: f64,
+    value: f64,
+    count: usize,
+}
+
+impl Ema {
+    fn new(period: usize) -> Self {
+        let period = period.max(1);
+        Self { period, alpha: 2.0 / (period as f64 + 1.0), value: 0.0, count: 0 }
+    }
+
+    fn push(&mut self, x: f64) {
+        // Seed with the first value rather than zero
+        self.value = if self.count == 0 { x } else { self.value + self.alpha * (x - self.value) };
+        self.count += 1;
+    }
+
+    /// Available once `period` values have been seen.
+    fn value(&self) -> Option<f64> {
+        (self.count >= self.period).then_some(self.value)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Rsi {
+    period: usize,
+    last: Option<f64>,
+    avg_gain: f64,
+    avg_loss: f64,
+    count: usize,
+}
+
+impl Rsi {
+    fn new(period: usize) -> Self {
+        Self { period: period.max(1), last: None, avg_gain: 0.0, avg_loss: 0.0, count: 0 }
+    }
+
+    fn push(&mut self, x: f64) {
+        let Some(last) = self.last.replace(x) else { return };
+        let (gain, loss) = ((x - last).max(0.0), (last - x).max(0.0));
+        self.count += 1;
+        // Plain average over the first period, Wilder smoothing after that
+        let n = self.count.min(self.period) as f64;
+        self.avg_gain += (gain - self.avg_gain) / n;
+        self.avg_loss += (loss - self.avg_loss) / n;
+    }
+
+    fn value(&self) -> Option<f64> {
+        if self.count < self.period {
+            return None;
+        }
+        if self.avg_loss == 0.0 {
+            return Some(if self.avg_gain == 0.0 { 50.0 } else { 100.0 });
+        }
+        Some(100.0 - 100.0 / (1.0 + self.avg_gain / self.avg_loss))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Macd {
+    fast: Ema,
+    slow: Ema,
+    signal: Ema,
+}
+
+impl Macd {
+    fn push(&mut self, x: f64) {
+        self.fast.push(x);
+        self.slow.push(x);
+        if let (Some(fast), Some(slow)) = (self.fast.value(), self.slow.value()) {
+            self.signal.push(fast - slow);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Vwap {
+    window: usize,
+    bars: VecDeque<(u64, u64)>,
+    volume: u64,
+    turnover: u64,
+}
+
+impl Vwap {
+    fn push(&mut self, bar: &Bar) {
+        self.bars.push_back((bar.volume, bar.turnover));
+        self.volume += bar.volume;
+        self.turnover += bar.turnover;
+        if self.bars.len() > self.window {
+            let (volume, turnover) = self.bars.pop_front().unwrap_or_default();
+            self.volume -= volume;
+            self.turnover -= turnover;
+        }
+    }
+
+    /// `None` until something has traded inside the window.
+    fn value(&self) -> Option<f64> {
+        (self.volume > 0).then(|| self.turnover as f64 / self.volume as f64)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum Indicator {
+    Rolling(Rolling),
+    Ema(Ema),
+    Rsi(Rsi),
+    Macd(Macd),
+    Vwap(Vwap),
+}
+
+impl Indicator {
+    fn new(spec: IndicatorSpec) -> Self {
+        match spec {
+            IndicatorSpec::Sma { window } | IndicatorSpec::StdDev { window } | IndicatorSpec::Bollinger { window } => {
+                Indicator::Rolling(Rolling::new(window))
+            }
+            IndicatorSpec::Ema { period } => Indicator::Ema(Ema::new(period)),
+            IndicatorSpec::Rsi { period } => Indicator::Rsi(Rsi::new(period)),
+            IndicatorSpec::Macd { fast, slow, signal } => Indicator::Macd(Macd {
+                fast: Ema::new(fast),
+                slow: Ema::new(slow),
+                signal: Ema::new(signal),
+            }),
+            IndicatorSpec::Vwap { window } => Indicator::Vwap(Vwap {
+                window: window.max(1),
+                bars: VecDeque::new(),
+                volume: 0,
+                turnover: 0,
+            }),
+        }
+    }
+
+    fn push(&mut self, bar: &Bar) {
+        let price = bar.price as f64;
+        match self {
+            Indicator::Rolling(rolling) => rolling.push(price),
+            Indicator::Ema(ema) => ema.push(price),
+            Indicator::Rsi(rsi) => rsi.push(price),
+            Indicator::Macd(macd) => macd.push(price),
+            Indicator::Vwap(vwap) => vwap.push(bar),
+        }
+    }
+}
+
+/// An agent's subscribed indicators. Readers return `None` until the
+/// indicator has seen enough bars, and for indicators not subscribed to.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Indicators {
+    // A handful per agent at most, so a scan beats hashing
+    slots: Vec<(IndicatorSpec, Indicator)>,
+}
+
+impl Indicators {
+    pub fn subscribe(&mut self, spec: IndicatorSpec) {
+        if !self.slots.iter().any(|(s, _)| *s == spec) {
+            self.slots.push((spec, Indicator::new(spec)));
+        }
+    }
+
+    pub fn specs(&self) -> impl Iterator<Item = IndicatorSpec> + '_ {
+        self.slots.iter().map(|(spec, _)| *spec)
+    }
+
+    pub fn update(&mut self, bar: &Bar) {
+        for (_, indicator) in &mut self.slots {
+            indicator.push(bar);
+        }
+    }
+
+    fn get(&self, spec: IndicatorSpec) -> Option<&Indicator> {
+        self.slots.iter().find(|(s, _)| *s == spec).map(|(_, i)| i)
+    }
+
+    pub fn sma(&self, window: usize) -> Option<f64> {
+        match self.get(IndicatorSpec::Sma { window })? {
+            Indicator::Rolling(rolling) => rolling.mean(),
+            _ => None,
+        }
+    }
+
+    pub fn ema(&self, period: usize) -> Option<f64> {
+        match self.get(IndicatorSpec::Ema { period })? {
+            Indicator::Ema(ema) => ema.value(),
+            _ => None,
+        }
+    }
+
+    pub fn std_dev(&self, window: usize) -> Option<f64> {
+        match self.get(IndicatorSpec::StdDev { window })? {
+            Indicator::Rolling(rolling) => rolling.std_dev(),
+            _ => None,
+        }
+    }
+
+    pub fn rsi(&self, period: usize) -> Option<f64> {
+        match self.get(IndicatorSpec::Rsi { period })? {
+            Indicator::Rsi(rsi) => rsi.value(),
+            _ => None,
+        }
+    }
+
+    /// `(macd, signal, histogram)`
+    pub fn macd(&self, fast: usize, slow: usize, signal: usize) -> Option<(f64, f64, f64)> {
+        match self.get(IndicatorSpec::Macd { fast, slow, signal })? {
+            Indicator::Macd(macd) => {
+                let line = macd.fast.value()? - macd.slow.value()?;
+                let signal = macd.signal.value()?;
+                Some((line, signal, line - signal))
+            }
+            _ => None,
+        }
+    }
+
+    /// `(lower, middle, upper)` at `k` standard deviations.
+    pub fn bollinger(&self, window: usize, k: f64) -> Option<(f64, f64, f64)> {
+        match self.get(IndicatorSpec::Bollinger { window })? {
+            Indicator::Rolling(rolling) => {
+                let (middle, std_dev) = (rolling.mean()?, rolling.std_dev()?);
+                Some((middle - k * std_dev, middle, middle + k * std_dev))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn vwap(&self, window: usize) -> Option<f64> {
+        match self.get(IndicatorSpec::Vwap { window })? {
+            Indicator::Vwap(vwap) => vwap.value(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRICES: [u64; 10] = [100, 102, 101, 105, 107, 104, 108, 110, 109, 111];
+
+    fn fed(specs: &[IndicatorSpec], prices: &[u64]) -> Indicators {
+        let mut indicators = Indicators::default();
+        for &spec in specs {
+            indicators.subscribe(spec);
+        }
+        for &price in prices {
+            indicators.update(&Bar::at(price));
+        }
+        indicators
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn sma_and_bollinger_cover_the_last_window() {
+        let indicators = fed(&[IndicatorSpec::Sma { window: 4 }, IndicatorSpec::Bollinger { window: 5 }], &PRICES);
+        assert!(close(indicators.sma(4).unwrap(), (108 + 110 + 109 + 111) as f64 / 4.0));
+
+        let last: Vec<f64> = PRICES[5..].iter().map(|&p| p as f64).collect();
+        let mean = last.iter().sum::<f64>() / 5.0;
+        let std_dev = (last.iter().map(|p| (p - mean).powi(2)).sum::<f64>() / 5.0).sqrt();
+        let (lower, middle, upper) = indicators.bollinger(5, 2.0).unwrap();
+        assert!(close(middle, mean));
+        assert!(close(lower, mean - 2.0 * std_dev));
+        assert!(close(upper, mean + 2.0 * std_dev));
+
+        assert_eq!(fed(&[IndicatorSpec::Sma { window: 4 }], &PRICES[..3]).sma(4), None);
+    }
+
+    #[test]
+    fn ema_matches_the_recursion() {
+        let indicators = fed(&[IndicatorSpec::Ema { period: 3 }], &PRICES);
+        let alpha = 2.0 / 4.0;
+        let expected = PRICES[1..].iter().fold(PRICES[0] as f64, |ema, &p| ema + alpha * (p as f64 - ema));
+        assert!(close(indicators.ema(3).unwrap(), expected));
+    }
+
+    #[test]
+    fn rsi_uses_wilder_smoothing() {
+        let indicators = fed(&[IndicatorSpec::Rsi { period: 4 }], &PRICES);
+        let changes: Vec<f64> = PRICES.windows(2).map(|w| w[1] as f64 - w[0] as f64).collect();
+        // A plain average of the first four changes, then avg = (3 * avg + x) / 4
+        let mut gain = changes[..4].iter().map(|c| c.max(0.0)).sum::<f64>() / 4.0;
+        let mut loss = changes[..4].iter().map(|c| (-c).max(0.0)).sum::<f64>() / 4.0;
+        for c in &changes[4..] {
+            gain = (3.0 * gain + c.max(0.0)) / 4.0;
+            loss = (3.0 * loss + (-c).max(0.0)) / 4.0;
+        }
+        assert!(close(indicators.rsi(4).unwrap(), 100.0 - 100.0 / (1.0 + gain / loss)));
+        assert_eq!(fed(&[IndicatorSpec::Rsi { period: 4 }], &PRICES[..4]).rsi(4), None);
+    }
+}
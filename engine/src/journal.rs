@@ -0,0 +1,292 @@
+//! Append-only record of everything that steers a live run, and replay from it.
+//!
+//! The server writes `journal.bin` next to `trades.csv`. It opens with how the
+//! run began (a scenario and seed, or a snapshot), then holds every command that
+//! reached the simulation (God Mode, trading sessions, game orders), each
+//! stamped with how many events the sim thread had processed when it arrived,
+//! and a mark at the end of every tick (for a God Mode step, one mark once the
+//! whole step is done). Given those the engine is deterministic, so `replay`
+//! rebuilds the run event for event.
+//!
+//! On disk: `MJNL` and the format version (u32 LE), then frames of
+//! `[payload length: u32 LE][CRC-32 of payload: u32 LE][payload]`, where the
+//! payload is one JSON `Entry`. Frames are flushed one at a time, so a crash
+//! loses at most the frame being written. A torn last frame ends the journal;
+//! a bad checksum anywhere is corruption.
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use crate::scenario::Scenario;
+use crate::simulation::Simulation;
+use crate::types::SimulationCommand;
+
+const MAGIC: &[u8; 4] = b"MJNL";
+/// Bumped whenever the frame layout or `Entry` changes shape.
+pub const JOURNAL_VERSION: u32 = 1;
+/// A length past this is read as corruption rather than allocated.
+const MAX_FRAME: usize = 256 << 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "entry", rename_all = "snake_case")]
+pub enum Entry {
+    /// A fresh run of `scenario` under `seed`
+    Start { scenario: Scenario, seed: u64 },
+    /// A run picked up from a snapshot
+    Restore { snapshot: String },
+    /// Applied once the sim thread had processed `events` events
+    Command { events: u64, command: SimulationCommand },
+    /// Tick `tick` closed with the `events`th event
+    Tick { events: u64, tick: u64 },
+}
+
+impl Entry {
+    /// The simulation a `Start` or `Restore` entry begins.
+    pub fn begin(&self) -> Result<Simulation, String> {
+        match self {
+            Entry::Start { scenario, seed } => Simulation::new(scenario.clone(), *seed),
+            Entry::Restore { snapshot } => Simulation::restore(snapshot),
+            _ => Err("a journal opens with a start or restore entry".to_string()),
+        }
+    }
+}
+
+/// Whether `cmd` changes the simulation. Queries only read it, and pacing and
+/// shutdown belong to the session's thread, so none of those are journaled.
+pub fn journaled(cmd: &SimulationCommand) -> bool {
+    !matches!(cmd, SimulationCommand::QueryTime { .. } | SimulationCommand::QueryPlayer { .. }
+        | SimulationCommand::QueryAlerts { .. } | SimulationCommand::QueryRegime { .. } | SimulationCommand::SaveSnapshot { .. }
+        | SimulationCommand::SetPacing { .. } | SimulationCommand::Shutdown)
+}
+
+/// The writing end.
+pub struct Journal {
+    out: BufWriter<File>,
+}
+
+impl Journal {
+    /// Start a new journal at `path`, replacing any old one.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(MAGIC)?;
+        out.write_all(&JOURNAL_VERSION.to_le_bytes())?;
+        out.flush()?;
+        Ok(Journal { out })
+    }
+
+    pub fn append(&mut self, entry: &Entry) -> io::Result<()> {
+        let payload = serde_json::to_vec(entry)?;
+        if payload.len() > MAX_FRAME {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "journal entry too large"));
+        }
+        self.out.write_all(&(payload.len() as u32).to_le_bytes())?;
+        self.out.write_all(&crc32(&payload).to_le_bytes())?;
+        self.out.write_all(&payload)?;
+        self.out.flush()
+    }
+
+    /// Record `cmd`, unless it only reads, as arriving after `events` events.
+    pub fn command(&mut self, events: u64, cmd: &SimulationCommand) -> io::Result<()> {
+        if !journaled(cmd) {
+            return Ok(());
+        }
+        self.append(&Entry::Command { events, command: cmd.clone() })
+    }
+}
+
+/// A journal read back in full.
+#[derive(Debug)]
+pub struct Recording {
+    pub entries: Vec<Entry>,
+    /// The last frame was cut short, most likely by the server stopping mid-write
+    pub torn: bool,
+}
+
+pub fn read(path: &Path) -> Result<Recording, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if bytes.len() < 8 || &bytes[..4] != MAGIC {
+        return Err(format!("{} is not a journal", path.display()));
+    }
+    let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap_or_default());
+    if version != JOURNAL_VERSION {
+        return Err(format!("journal version {} is not supported (this engine writes {})", version, JOURNAL_VERSION));
+    }
+
+    let mut entries = Vec::new();
+    let mut at = 8;
+    while at < bytes.len() {
+        let Some(header) = bytes.get(at..at + 8) else {
+            return Ok(Recording { entries, torn: true });
+        };
+        let len = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let crc = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        if len > MAX_FRAME {
+            return Err(format!("frame {} at byte {}: length {} is corrupt", entries.len(), at, len));
+        }
+        let Some(payload) = bytes.get(at + 8..at + 8 + len) else {
+            return Ok(Recording { entries, torn: true });
+        };
+        if crc32(payload) != crc {
+            return Err(format!("frame {} at byte {}: checksum mismatch", entries.len(), at));
+        }
+        let entry = serde_json::from_slice(payload)
+            .map_err(|e| format!("frame {} at byte {}: {}", entries.len(), at, e))?;
+        entries.push(entry);
+        at += 8 + len;
+    }
+    Ok(Recording { entries, torn: false })
+}
+
+/// Re-drive the run in `entries`, with its logs going to `out_dir`, up to its
+/// last tick mark. Returns the simulation as it stood when that tick closed;
+/// commands after it never reached the trade log. Tick marks are checked on the
+/// way, so a run that drifts fails at the first tick that closes out of place.
+pub fn replay(entries: &[Entry], out_dir: &Path) -> Result<Simulation, String> {
+    let (first, rest) = entries.split_first().ok_or("the journal is empty")?;
+    let mut sim = first.begin()?.with_logs(out_dir).map_err(|e| e.to_string())?;
+    let end = rest.iter().rposition(|e| matches!(e, Entry::Tick { .. })).map_or(0, |i| i + 1);
+    let mut events = 0;
+    for entry in &rest[..end] {
+        match entry {
+            Entry::Command { events: at, command } => {
+                run_to(&mut sim, &mut events, *at)?;
+                sim.apply(command.clone());
+            }
+            Entry::Tick { events: at, tick } => {
+                run_to(&mut sim, &mut events, *at)?;
+                if sim.tick() != tick + 1 {
+                    return Err(format!("replay diverged: tick {} closed at event {}, but the replay is in tick {}", tick, at, sim.tick()));
+                }
+            }
+            _ => return Err("a journal has one start or restore entry, at the beginning".to_string()),
+        }
+    }
+    Ok(sim)
+}
+
+fn run_to(sim: &mut Simulation, events: &mut u64, at: u64) -> Result<(), String> {
+    while *events < at {
+        if sim.next_event_time().is_none() {
+            return Err(format!("replay ran out of events at {} of {}", events, at));
+        }
+        sim.step_event();
+        *events += 1;
+    }
+    Ok(())
+}
+
+/// How a replayed trade log lines up with the original. The server flushes
+/// its log as each tick closes, before marking the tick in the journal, so the
+/// original holds at least every trade the replay does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Identical,
+    /// The replay is a prefix of the original, which carries on past the journal's last tick mark
+    ReplayEndsFirst,
+    /// The original is a prefix of the replay: trades the journal says closed are missing from it
+    OriginalEndsFirst,
+    /// First line that differs, counting from 1
+    Differs { line: usize },
+}
+
+pub fn compare(original: &[u8], replayed: &[u8]) -> Comparison {
+    if original == replayed {
+        return Comparison::Identical;
+    }
+    if replayed.starts_with(original) {
+        return Comparison::OriginalEndsFirst;
+    }
+    if original.starts_with(replayed) {
+        return Comparison::ReplayEndsFirst;
+    }
+    let same = original.iter().zip(replayed).take_while(|(a, b)| a == b).count();
+    Comparison::Differs { line: original[..same].iter().filter(|&&b| b == b'\n').count() + 1 }
+}
+
+/// CRC-32 (IEEE), as in zip and PNG.
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |crc, &b| CRC_TABLE[((crc ^ b as u32) & 0xff) as usize] ^ (crc >> 8))
+}
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// A journal of three frames in the temp dir, under a name unique to the test.
+    fn written(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("journal-{}-{}.bin", name, std::process::id()));
+        let mut journal = Journal::create(&path).unwrap();
+        journal.append(&Entry::Start { scenario: Scenario::default(), seed: 7 }).unwrap();
+        journal.command(3, &SimulationCommand::SetPaused(true)).unwrap();
+        journal.command(4, &SimulationCommand::QueryRegime { reply: tokio::sync::mpsc::unbounded_channel().0 }).unwrap();
+        journal.append(&Entry::Tick { events: 10, tick: 1 }).unwrap();
+        path
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        let path = written("round-trip");
+        let recording = read(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert!(!recording.torn);
+        assert!(matches!(&recording.entries[..], [
+            Entry::Start { seed: 7, .. },
+            Entry::Command { events: 3, command: SimulationCommand::SetPaused(true) },
+            Entry::Tick { events: 10, tick: 1 },
+        ]));
+    }
+
+    #[test]
+    fn torn_tail_ends_the_journal() {
+        let path = written("torn");
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() - 3]).unwrap();
+        let recording = read(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert!(recording.torn);
+        assert_eq!(recording.entries.len(), 2);
+    }
+
+    #[test]
+    fn checksum_mismatch_is_corruption() {
+        let path = written("crc");
+        let mut bytes = std::fs::read(&path).unwrap();
+        // Inside the payload of the last frame, `{"entry":"tick",...}`
+        let at = bytes.len() - 2;
+        bytes[at] ^= 0x01;
+        std::fs::write(&path, &bytes).unwrap();
+        let error = read(&path).unwrap_err();
+        std::fs::remove_file(&path).ok();
+
+        assert!(error.contains("checksum mismatch"), "{}", error);
+    }
+
+    #[test]
+    fn original_must_cover_the_replay() {
+        let replayed = b"header\na\nb\n";
+        assert_eq!(compare(replayed, replayed), Comparison::Identical);
+        assert_eq!(compare(b"header\na\nb\nc\n", replayed), Comparison::ReplayEndsFirst);
+        assert_eq!(compare(b"header\na\n", replayed), Comparison::OriginalEndsFirst);
+        assert_eq!(compare(b"header\na\nx\n", replayed), Comparison::Differs { line: 3 });
+    }
+}
@@ -1,5 +1,26 @@
+pub mod console;
 pub mod types;
 pub mod orderbook;
 pub mod agents;
+pub mod behaviour;
+pub mod indicators;
+pub mod network;
+pub mod margin;
+pub mod manipulation;
+pub mod rl;
+pub mod env;
+pub mod population;
+pub mod scripting;
+pub mod game;
+pub mod surveillance;
+pub mod clock;
+pub mod scheduling;
+pub mod regime;
+pub mod scenario;
+pub mod batch;
+pub mod sweep;
+pub mod simulation;
+pub mod journal;
+pub mod sessions;
 pub mod state;
 pub mod routes;
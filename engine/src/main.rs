@@ -1,51 +1,85 @@
-use market_engine::types::{ServerMessage, SimulationCommand};
-use market_engine::orderbook::OrderBook;
-use market_engine::agents::{Agent, Strategy};
-use market_engine::routes::admin;
+use market_engine::types::ServerMessage;
+use market_engine::clock::Pacing;
+use market_engine::scenario::Scenario;
+use market_engine::batch::{run_parallel, RunSummary};
+use market_engine::simulation::Simulation;
+use market_engine::journal::{self, Comparison, Entry};
+use market_engine::sweep::{Sweep, SweepRow};
+use market_engine::console;
+use market_engine::routes::{admin, game, sessions, snapshots, surveillance, trading};
+use market_engine::sessions::{launch, Sessions, Sim, DEFAULT_SESSION};
 use market_engine::state::AppState;
-use rand::seq::SliceRandom;
-use rand::thread_rng;
-use std::collections::HashMap;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use std::thread;
-use std::time::Duration;
+use std::time::Instant;
 use std::error::Error;
 use csv::Writer;
 use axum::{
     extract::ws::{Message, WebSocket, WebSocketUpgrade},
-    extract::State,
     response::IntoResponse,
     routing::get,
     Router,
 };
 use tower_http::cors::CorsLayer;
-use tokio::sync::{broadcast, mpsc};
+use tokio::sync::broadcast;
 use std::sync::Arc;
 use std::net::SocketAddr;
 
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    // 1. Create the Broadcast Channel for market data
-    let (tx, _rx) = broadcast::channel::<ServerMessage>(100);
-
-    // 2. Create the MPSC Command Channel for control plane
-    let (cmd_tx, cmd_rx) = mpsc::unbounded_channel::<SimulationCommand>();
+    // Sweeps and replays carry their own seed and scenario
+    if let Some(path) = arg("--sweep") {
+        return sweep(&path);
+    }
+    if let Some(path) = arg("--replay") {
+        return replay(Path::new(&path));
+    }
+    let seed = seed();
+    let scenario = scenario();
+    if let Some(ticks) = arg("--batch") {
+        return batch(ticks.parse()?, seed, scenario);
+    }
+    // A snapshot carries its own scenario, seed and random state
+    let start = match arg("--snapshot") {
+        Some(path) => match std::fs::read_to_string(&path) {
+            Ok(snapshot) => Entry::Restore { snapshot },
+            Err(e) => {
+                eprintln!("Could not read snapshot {}: {}", path, e);
+                std::process::exit(1);
+            }
+        },
+        None => Entry::Start { scenario, seed },
+    };
+    // The default session logs to the working directory, as a lone server always has
+    let pacing = pacing();
+    let default = match launch(DEFAULT_SESSION, &start, Path::new("."), pacing) {
+        Ok(session) => session,
+        Err(e) => {
+            eprintln!("Could not start the simulation: {}", e);
+            std::process::exit(1);
+        }
+    };
 
-    // 3. Spawn the Simulation in a separate blocking thread
-    let sim_tx = tx.clone();
-    thread::spawn(move || {
-        simulation_loop(sim_tx, cmd_rx);
+    let app_state = Arc::new(AppState {
+        sessions: Sessions::new(default, pacing),
+        trading_tokens: trading_tokens(),
     });
 
-    // 4. Setup the Web Server
-    let app_state = Arc::new(AppState { 
-        tx, 
-        cmd_tx,
-    });
-    
-    let app = Router::new()
+    // Every session has the full set of routes under /sessions/<id>; the
+    // unprefixed ones are the default session's
+    let routes = Router::new()
         .route("/ws", get(ws_handler))
         .merge(admin::admin_routes())
+        .merge(trading::trading_routes())
+        .merge(game::game_routes())
+        .merge(surveillance::surveillance_routes())
+        .merge(snapshots::snapshot_routes());
+    let app = Router::new()
+        .merge(routes.clone())
+        .nest("/sessions/:session", routes)
+        .merge(sessions::session_routes())
         .with_state(app_state)
         .layer(CorsLayer::permissive());
 
@@ -59,334 +93,229 @@ async fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-fn simulation_loop(
-    sim_tx: broadcast::Sender<ServerMessage>,
-    mut cmd_rx: mpsc::UnboundedReceiver<SimulationCommand>,
-) {
-    let mut book = OrderBook::new();
-    let mut agents: HashMap<u64, Agent> = HashMap::new();
-    let mut agent_ids: Vec<u64> = Vec::new();
-    let mut rng = thread_rng();
-    
-    // CSV Writer
-    let mut wtr = Writer::from_path("trades.csv").unwrap();
+/// Tokens accepted by `/ws/trade`: a comma-separated `MARKET_TRADING_TOKENS`,
+/// or a fresh random one printed at startup.
+fn trading_tokens() -> HashSet<String> {
+    match std::env::var("MARKET_TRADING_TOKENS") {
+        Ok(list) => list.split(',').map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).collect(),
+        Err(_) => {
+            let token = format!("{:016x}", rand::random::<u64>());
+            println!("Trading session token: {}", token);
+            HashSet::from([token])
+        }
+    }
+}
 
-    // Initialize Agents (50 total for more realistic dynamics)
-    let mut id_counter = 0;
+/// Value following `flag` on the command line.
+fn arg(flag: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter().position(|a| a == flag).and_then(|i| args.get(i + 1)).cloned()
+}
 
-    // 1. Random Walkers (20 agents - liquidity providers)
-    for i in 0..20 {
-        id_counter += 1;
-        let agent = Agent::new(id_counter, format!("Noise_{}", i), 1000000, 1000, Strategy::RandomWalker);
-        agents.insert(id_counter, agent);
-        agent_ids.push(id_counter);
+/// `--pacing ticked|realtime|fast`, plus `--speed <x>` for real time. Ticked
+/// (the default) runs a tick every scenario `tick_ms` of wall-clock time.
+fn pacing() -> Pacing {
+    let speed = arg("--speed").and_then(|s| s.parse::<f64>().ok()).filter(|s| *s > 0.0);
+    match arg("--pacing").as_deref() {
+        Some("fast") => Pacing::AsFastAsPossible,
+        Some("realtime") => Pacing::RealTime { speed: speed.unwrap_or(1.0) },
+        _ if speed.is_some() => Pacing::RealTime { speed: speed.unwrap_or(1.0) },
+        _ => Pacing::Ticked,
     }
+}
 
-    // 2. Trend Followers (15 agents - momentum traders)
-    for i in 0..15 {
-        id_counter += 1;
-        let agent = Agent::new(id_counter, format!("Trend_{}", i), 1000000, 1000, Strategy::TrendFollower { window_size: 5 });
-        agents.insert(id_counter, agent);
-        agent_ids.push(id_counter);
-    }
+/// `--seed <n>`, or a random one. Printed either way so the run can be repeated.
+fn seed() -> u64 {
+    let seed = arg("--seed").and_then(|s| s.parse().ok()).unwrap_or_else(rand::random);
+    println!("Seed: {} (rerun with --seed {})", seed, seed);
+    seed
+}
 
-    // 3. Mean Reverters (10 agents - contrarian traders)
-    for i in 0..10 {
-        id_counter += 1;
-        let agent = Agent::new(id_counter, format!("Mean_{}", i), 1000000, 1000, Strategy::MeanReverter { window_size: 10, std_dev_multiplier: 1.5 });
-        agents.insert(id_counter, agent);
-        agent_ids.push(id_counter);
+/// `--scenario <file.json>`, or the built-in population. A scenario that
+/// doesn't parse or validate stops the server before it starts.
+fn scenario() -> Scenario {
+    let Some(path) = arg("--scenario") else { return Scenario::default() };
+    match Scenario::load(&path) {
+        Ok(scenario) => {
+            println!("Scenario: {} from {}", scenario.name, path);
+            scenario
+        }
+        Err(e) => {
+            eprintln!("Invalid scenario {}", e);
+            std::process::exit(1);
+        }
     }
+}
 
-    // 4. Market Makers (5 agents - provide tight spreads)
-    for i in 0..5 {
-        id_counter += 1;
-        let agent = Agent::new(id_counter, format!("MM_{}", i), 2000000, 2000, Strategy::MarketMaker { spread_bps: 50 }); // 0.5% spread
-        agents.insert(id_counter, agent);
-        agent_ids.push(id_counter);
+/// `--batch <ticks>`: no server, no waiting. Runs the scenario for `ticks`
+/// ticks `--runs` times (default 1) on `--threads` threads (default one per
+/// core), with seeds counting up from the run seed. Each run's logs and
+/// `summary.json` go to `<out>/run_<i>/`, and a row per run to
+/// `<out>/summary.csv`, with `--out` defaulting to `runs`. A run whose
+/// scenario cannot be built gets no row; the batch fails only if every run does.
+fn batch(ticks: u64, seed: u64, scenario: Scenario) -> Result<(), Box<dyn Error>> {
+    let runs: usize = arg("--runs").map(|s| s.parse()).transpose()?.unwrap_or(1);
+    let threads = threads()?;
+    let out = PathBuf::from(arg("--out").unwrap_or_else(|| "runs".to_string()));
+    for i in 0..runs {
+        std::fs::create_dir_all(out.join(format!("run_{:03}", i)))?;
     }
 
-    println!("--- Initialized {} agents ---", agents.len());
-
-    let mut current_price = 10000;
-    let mut tick = 0;
-    let mut paused = false;
+    console::set_quiet(true);
+    println!("Batch: {} runs x {} ticks of {} on {} threads", runs, ticks, scenario.name, threads.min(runs));
+    let started = Instant::now();
+    let results = run_parallel(runs, threads, |i| {
+        let summary = run_headless(i, scenario.clone(), seed.wrapping_add(i as u64), ticks, out.join(format!("run_{:03}", i)));
+        match &summary {
+            Ok(summary) => println!("Run {} (seed {}): ${:.2} -> ${:.2}, volatility {:.5}, kurtosis {:.2}, {} crashes, spread {:.1} bps",
+                i, summary.seed, summary.initial_price as f64 / 100.0, summary.final_price as f64 / 100.0,
+                summary.volatility, summary.kurtosis, summary.crashes, summary.mean_spread_bps),
+            Err(e) => println!("Run {} failed: {}", i, e),
+        }
+        summary
+    });
 
-    println!("--- Simulation Started (Background Thread) ---");
+    let summaries: Vec<RunSummary> = results.into_iter().flatten().collect();
+    if summaries.is_empty() && runs > 0 {
+        return Err(format!("all {} runs of {} failed", runs, scenario.name).into());
+    }
+    write_summaries(&out, &summaries)?;
+    println!("Batch: {} runs ({} failed) in {:.1}s, summaries in {}",
+        summaries.len(), runs - summaries.len(), started.elapsed().as_secs_f64(), out.join("summary.csv").display());
+    Ok(())
+}
 
-    loop {
-        // Check for commands with a timeout (10ms per iteration for responsiveness)
-        let deadline = std::time::Instant::now() + Duration::from_millis(10);
-        while std::time::Instant::now() < deadline {
-            match cmd_rx.try_recv() {
-                Ok(cmd) => {
-                    handle_command(&cmd, &mut paused, &mut book, &mut agents, &mut current_price, &mut tick);
-                }
-                Err(mpsc::error::TryRecvError::Empty) => {
-                    thread::sleep(Duration::from_millis(1));
-                }
-                Err(mpsc::error::TryRecvError::Disconnected) => {
-                    println!("Command channel closed, exiting simulation.");
-                    return;
-                }
-            }
+/// `--sweep <file.json>`: a batch per parameter combination (see `sweep`).
+/// Combination `k` writes a batch directory `<out>/combo_<k>/`, and
+/// `<out>/sweep.csv` has one row of averaged outcome metrics per combination.
+/// Runs that fail are left out of the averages and counted in `failed`.
+fn sweep(path: &str) -> Result<(), Box<dyn Error>> {
+    let sweep = Sweep::load(path)?;
+    let threads = threads()?;
+    let out = PathBuf::from(arg("--out").unwrap_or_else(|| "sweep".to_string()));
+    let (combinations, runs, ticks) = (sweep.combinations().len(), sweep.spec.runs, sweep.spec.ticks);
+    let scenarios = sweep.combinations().iter().map(|values| sweep.scenario(values)).collect::<Result<Vec<_>, _>>()?;
+    for k in 0..combinations {
+        for i in 0..runs {
+            std::fs::create_dir_all(out.join(format!("combo_{:03}/run_{:03}", k, i)))?;
         }
+    }
 
-        // Skip tick processing if paused
-        if paused {
-            continue;
+    console::set_quiet(true);
+    println!("Sweep: {} combinations x {} runs x {} ticks on {} threads", combinations, runs, ticks, threads.min(combinations * runs));
+    let started = Instant::now();
+    // Every (combination, run) pair is one job, so all threads stay busy to the end
+    let results = run_parallel(combinations * runs, threads, |job| {
+        let (k, i) = (job / runs, job % runs);
+        let dir = out.join(format!("combo_{:03}/run_{:03}", k, i));
+        let summary = run_headless(i, scenarios[k].clone(), sweep.spec.seed.wrapping_add(i as u64), ticks, dir);
+        if let Err(e) = &summary {
+            println!("Combination {} run {} failed: {}", k, i, e);
         }
+        summary
+    });
 
-        tick += 1;
-        
-        // Shuffle agents
-        agent_ids.shuffle(&mut rng);
+    let mut table = Writer::from_path(out.join("sweep.csv"))?;
+    table.write_record(SweepRow::header(&sweep.spec.params))?;
+    for (k, values) in sweep.combinations().iter().enumerate() {
+        let summaries: Vec<RunSummary> = results[k * runs..(k + 1) * runs].iter().flatten().cloned().collect();
+        write_summaries(&out.join(format!("combo_{:03}", k)), &summaries)?;
+        let row = SweepRow::aggregate(k, values, &summaries, runs - summaries.len());
+        println!("Combination {} {:?}: volatility {:.5}, kurtosis {:.2}, crash probability {:.2}, spread {:.1} bps",
+            k, values, row.volatility, row.kurtosis, row.crash_probability, row.spread_bps);
+        table.write_record(row.record())?;
+    }
+    table.flush()?;
+    println!("Sweep: {} runs in {:.1}s, table in {}", results.len(), started.elapsed().as_secs_f64(), out.join("sweep.csv").display());
+    Ok(())
+}
 
-        // Agents act
-        for id in &agent_ids {
-            if let Some(agent) = agents.get_mut(id) {
-                agent.update_market_data(current_price);
-                if let Some(mut order) = agent.act(current_price) {
-                    order.timestamp = tick;
-                    book.add_order(order);
-                }
-            }
-        }
+/// `--replay <journal>`: re-run a server session from its journal, with logs
+/// going to `--out` (default `replay`), and check the replayed `trades.csv`
+/// against `--trades` (default the `trades.csv` beside the journal).
+fn replay(path: &Path) -> Result<(), Box<dyn Error>> {
+    let original = arg("--trades").map(PathBuf::from).unwrap_or_else(|| path.with_file_name("trades.csv"));
+    let out = PathBuf::from(arg("--out").unwrap_or_else(|| "replay".to_string()));
+    std::fs::create_dir_all(&out)?;
+    let recording = journal::read(path)?;
+    if recording.torn {
+        println!("Replay: {} ends in a partial frame, replaying the entries before it", path.display());
+    }
 
-        // Process trades
-        let new_trades = book.drain_trades();
-        if !new_trades.is_empty() {
-            current_price = new_trades.last().unwrap().price;
-            
-            for trade in &new_trades {
-                if let Some(buyer) = agents.get_mut(&trade.buyer_id) { buyer.on_trade(trade); }
-                if let Some(seller) = agents.get_mut(&trade.seller_id) { seller.on_trade(trade); }
-                
-                // Log to CSV
-                wtr.serialize(trade).ok();
+    console::set_quiet(true);
+    let started = Instant::now();
+    let sim = journal::replay(&recording.entries, &out)?;
+    let tick = sim.tick();
+    // Dropping the sim flushes its logs
+    drop(sim);
+    println!("Replay: {} entries to tick {} in {:.1}s, logs in {}", recording.entries.len(), tick, started.elapsed().as_secs_f64(), out.display());
 
-                // Broadcast Trade
-                let _ = sim_tx.send(ServerMessage::Trade {
-                    price: trade.price,
-                    quantity: trade.amount,
-                    buyer_id: trade.buyer_id,
-                    seller_id: trade.seller_id,
-                });
-                
-                println!("Tick {}: Trade @ ${:.2} ({} units)", tick, trade.price as f64 / 100.0, trade.amount);
-            }
-        }
+    let replayed = std::fs::read(out.join("trades.csv"))?;
+    match journal::compare(&std::fs::read(&original)?, &replayed) {
+        Comparison::Identical => println!("Replay: trades identical to {}", original.display()),
+        Comparison::ReplayEndsFirst => println!("Replay: trades identical up to the journal's last tick; {} runs on past it", original.display()),
+        Comparison::OriginalEndsFirst => return Err(format!("{} ends before the journal's last tick", original.display()).into()),
+        Comparison::Differs { line } => return Err(format!("replayed trades differ from {} at line {}", original.display(), line).into()),
+    }
+    Ok(())
+}
 
-        // Broadcast Ticker (every tick)
-        let _ = sim_tx.send(ServerMessage::Ticker {
-            price: current_price,
-            tick,
-            best_bid: book.best_bid_price().unwrap_or(0),
-            best_ask: book.best_ask_price().unwrap_or(0),
-        });
+/// `--threads <n>`, default one per core.
+fn threads() -> Result<usize, Box<dyn Error>> {
+    Ok(match arg("--threads") {
+        Some(threads) => threads.parse()?,
+        None => thread::available_parallelism().map_or(1, |n| n.get()),
+    })
+}
 
-        // Sleep to control tick rate
-        thread::sleep(Duration::from_millis(100));
+/// Run number `run`, with no server and no waiting. Logs and `summary.json` go
+/// to `out_dir`. Fails if the scenario cannot be built or the logs opened.
+fn run_headless(run: usize, scenario: Scenario, seed: u64, ticks: u64, out_dir: PathBuf) -> Result<RunSummary, String> {
+    let name = scenario.name.clone();
+    let mut sim = Simulation::new(scenario, seed)
+        .and_then(|sim| sim.with_logs(&out_dir).map_err(|e| e.to_string()))
+        .map_err(|e| format!("could not build scenario {}: {}", name, e))?;
+    for _ in 0..ticks {
+        sim.step();
+    }
+    let summary = RunSummary { run, ..sim.summary() };
+    if let Ok(json) = serde_json::to_string_pretty(&summary) {
+        std::fs::write(out_dir.join("summary.json"), json).ok();
     }
+    Ok(summary)
 }
 
-fn handle_command(
-    cmd: &SimulationCommand,
-    paused: &mut bool,
-    book: &mut OrderBook,
-    agents: &mut HashMap<u64, Agent>,
-    current_price: &mut u64,
-    tick: &mut u64,
-) {
-    match cmd {
-        SimulationCommand::InjectOrder(order) => {
-            println!("God Mode: Injecting order from {:?}", order.side);
-            book.add_order(*order);
-        }
-        SimulationCommand::SetPaused(is_paused) => {
-            *paused = *is_paused;
-            println!("God Mode: Simulation {}", if *is_paused { "PAUSED" } else { "RESUMED" });
-        }
-        SimulationCommand::Reset => {
-            println!("God Mode: Resetting simulation!");
-            *book = OrderBook::new();
-            *current_price = 10000;
-            *tick = 0;
-            for agent in agents.values_mut() {
-                agent.cash = 1000000;
-                agent.inventory = 1000;
-            }
-        }
-        SimulationCommand::FlashCrash { seller_id, quantity, price } => {
-            println!("God Mode: FLASH CRASH incoming! {} units @ ${:.2}", quantity, *price as f64 / 100.0);
-            let crash_order = market_engine::types::Order {
-                id: rand::random(),
-                trader_id: *seller_id,
-                side: market_engine::types::OrderSide::Ask,
-                price: *price,
-                amount: *quantity,
-                timestamp: *tick,
-            };
-            book.add_order(crash_order);
-        }
-        SimulationCommand::UpdateVolatility(_multiplier) => {
-            println!("God Mode: Volatility update received (not yet implemented)");
-        }
-        SimulationCommand::Pump { buyer_id, base_price, magnitude } => {
-            println!("God Mode: PUMP initiated! Creating buy pressure (magnitude: {:.1}x)", magnitude);
-            let start_price = if *base_price == 0 { 
-                *current_price + 200
-            } else { 
-                *base_price 
-            };
-            let quantity = (2000.0 * magnitude) as u64;
-            for i in 0..5 {
-                let price = start_price + (i * 50);
-                let order = market_engine::types::Order {
-                    id: rand::random(),
-                    trader_id: *buyer_id,
-                    side: market_engine::types::OrderSide::Bid,
-                    price,
-                    amount: quantity,
-                    timestamp: *tick,
-                };
-                book.add_order(order);
-            }
-        }
-        SimulationCommand::Dump { seller_id, base_price, magnitude } => {
-            println!("God Mode: DUMP initiated! Creating sell pressure (magnitude: {:.1}x)", magnitude);
-            let start_price = if *base_price == 0 {
-                current_price.saturating_sub(200) 
-            } else {
-                *base_price
-            };
-            let quantity = (2000.0 * magnitude) as u64;
-            for i in 0..5 {
-                let price = start_price.saturating_sub(i * 50);
-                let order = market_engine::types::Order {
-                    id: rand::random(),
-                    trader_id: *seller_id,
-                    side: market_engine::types::OrderSide::Ask,
-                    price: price.max(1),
-                    amount: quantity,
-                    timestamp: *tick,
-                };
-                book.add_order(order);
-            }
-        }
-        SimulationCommand::Earnings { surprise_pct } => {
-            println!("God Mode: EARNINGS announced! Surprise: {:.1}%", surprise_pct);
-            let trader_id = 999_999;
-            
-            if *surprise_pct > 0.0 {
-                let magnitude = (surprise_pct.abs() * 30.0) as u64;
-                for i in 0..5 {
-                    let price = *current_price + (i * 100) + 300;
-                    let order = market_engine::types::Order {
-                        id: rand::random(),
-                        trader_id,
-                        side: market_engine::types::OrderSide::Bid,
-                        price,
-                        amount: (1500 + magnitude * 10).min(5000),
-                        timestamp: *tick,
-                    };
-                    book.add_order(order);
-                }
-            } else {
-                let magnitude = (surprise_pct.abs() * 30.0) as u64;
-                for i in 0..5 {
-                    let price = current_price.saturating_sub((i * 100) + 300);
-                    let order = market_engine::types::Order {
-                        id: rand::random(),
-                        trader_id,
-                        side: market_engine::types::OrderSide::Ask,
-                        price: price.max(1),
-                        amount: (1500 + magnitude * 10).min(5000),
-                        timestamp: *tick,
-                    };
-                    book.add_order(order);
-                }
-            }
-        }
-        SimulationCommand::Tariffs { severity } => {
-            println!("God Mode: TARIFFS announced! Severity: {:.1}", severity);
-            let trader_id = 999_998;
-            
-            let quantity = (1000.0 + severity * 500.0) as u64;
-            let price_impact = (severity * 100.0) as u64;
-            
-            for i in 0..7 {
-                let price = current_price.saturating_sub(price_impact + (i * 50));
-                let order = market_engine::types::Order {
-                    id: rand::random(),
-                    trader_id,
-                    side: market_engine::types::OrderSide::Ask,
-                    price: price.max(1),
-                    amount: quantity,
-                    timestamp: *tick,
-                };
-                book.add_order(order);
-            }
-        }
-        SimulationCommand::RugPull { magnitude } => {
-            println!("God Mode: RUG PULL! Magnitude: {:.1}x", magnitude);
-            let trader_id = 999_997;
-            
-
-            let quantity = (3000.0 * magnitude) as u64;
-            let crash_price = ((*current_price as f64) * (1.0 - 0.15 * magnitude)) as u64;
-            
-            for i in 0..10 {
-                let price = crash_price.saturating_sub(i * 20);
-                let order = market_engine::types::Order {
-                    id: rand::random(),
-                    trader_id,
-                    side: market_engine::types::OrderSide::Ask,
-                    price: price.max(1),
-                    amount: quantity,
-                    timestamp: *tick,
-                };
-                book.add_order(order);
-            }
-        }
-        SimulationCommand::WhaleAccumulation { magnitude } => {
-            println!("God Mode: WHALE ACCUMULATION detected! Magnitude: {:.1}x", magnitude);
-            let trader_id = 999_996;
-            
-            let quantity = (2000.0 * magnitude) as u64;
-            let premium = (50.0 * magnitude) as u64;
-            
-            for i in 0..8 {
-                let price = *current_price + premium + (i * 20);
-                let order = market_engine::types::Order {
-                    id: rand::random(),
-                    trader_id,
-                    side: market_engine::types::OrderSide::Bid,
-                    price,
-                    amount: quantity,
-                    timestamp: *tick,
-                };
-                book.add_order(order);
-            }
-        }
+/// A row per run in `<dir>/summary.csv`.
+fn write_summaries(dir: &Path, summaries: &[RunSummary]) -> Result<(), Box<dyn Error>> {
+    let mut wtr = Writer::from_path(dir.join("summary.csv"))?;
+    for summary in summaries {
+        wtr.serialize(summary)?;
     }
+    wtr.flush()?;
+    Ok(())
 }
 
 async fn ws_handler(
     ws: WebSocketUpgrade,
-    State(state): State<Arc<AppState>>,
+    sim: Sim,
 ) -> impl IntoResponse {
-    ws.on_upgrade(|socket| handle_socket(socket, state))
+    // Only the receiver is kept, so the socket closes when the session stops
+    let rx = sim.tx.subscribe();
+    ws.on_upgrade(|socket| handle_socket(socket, rx))
 }
 
-async fn handle_socket(mut socket: WebSocket, state: Arc<AppState>) {
-    let mut rx = state.tx.subscribe();
-
-    while let Ok(msg) = rx.recv().await {
-        if let Ok(json) = serde_json::to_string(&msg) {
-            if socket.send(Message::Text(json)).await.is_err() {
-                break;
+async fn handle_socket(mut socket: WebSocket, mut rx: broadcast::Receiver<ServerMessage>) {
+    loop {
+        match rx.recv().await {
+            Ok(msg) => {
+                let Ok(json) = serde_json::to_string(&msg) else { continue };
+                if socket.send(Message::Text(json)).await.is_err() {
+                    break;
+                }
             }
+            // A burst (e.g. a long step) outran this client: skip to the newest messages
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => break,
         }
     }
 }
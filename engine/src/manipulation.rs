@@ -0,0 +1,241 @@
+//! Adversarial strategies for testing surveillance: spoofing/layering and
+//! momentum ignition. Every order they place is tagged, so a run can be scored
+//! against what was actually manipulative.
+
+use crate::agents::Agent;
+use crate::types::{Order, OrderSide};
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Rests `layers` large orders a little away from the touch on one side to fake
+/// pressure there, works a small genuine order on the other side, and pulls
+/// everything on its next turn. Switches sides every `campaign_ticks`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpoofConfig {
+    pub layers: usize,
+    pub layer_size: u64,
+    /// Distance of the nearest layer from the touch
+    pub distance_bps: u64,
+    /// Gap between consecutive layers
+    pub spacing_bps: u64,
+    pub genuine_size: u64,
+    pub campaign_ticks: u64,
+}
+
+impl Default for SpoofConfig {
+    fn default() -> Self {
+        Self {
+            layers: 3,
+            layer_size: 50,
+            distance_bps: 30,
+            spacing_bps: 10,
+            genuine_size: 2,
+            campaign_ticks: 50,
+        }
+    }
+}
+
+/// Hits the book hard in one direction for `burst_ticks` to set off the
+/// momentum traders, then spends `unwind_ticks` selling (or buying back) into
+/// the move they make, then sits out `cooldown_ticks`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IgnitionConfig {
+    pub burst_size: u64,
+    pub burst_ticks: u64,
+    pub unwind_ticks: u64,
+    pub cooldown_ticks: u64,
+}
+
+impl Default for IgnitionConfig {
+    fn default() -> Self {
+        Self {
+            burst_size: 10,
+            burst_ticks: 3,
+            unwind_ticks: 10,
+            cooldown_ticks: 40,
+        }
+    }
+}
+
+/// What a manipulator meant an order to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ManipulationTag {
+    /// Not meant to trade; cancelled on the agent's next turn
+    Layer,
+    /// The real order that profits from the fake pressure
+    Genuine,
+    /// Aggressive order meant to start a move
+    Ignition,
+    /// Trading out of the ignition position into the move
+    Unwind,
+}
+
+/// One tagged order, for `manipulation.csv`. Join against the order ids in
+/// `trades.csv` to see which ones filled.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ManipulationRecord {
+    pub timestamp: u64,
+    pub trader_id: u64,
+    pub order_id: u64,
+    pub tag: ManipulationTag,
+    pub side: OrderSide,
+    pub price: u64,
+    pub amount: u64,
+}
+
+/// Per-agent state for `Strategy::Spoofer` and `Strategy::MomentumIgniter`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManipulationState {
+    ticks: u64,
+    /// Side being faked (spoofer) or pushed (igniter)
+    pub direction: OrderSide,
+    start_inventory: u64,
+    activity: Vec<ManipulationRecord>,
+}
+
+impl Default for ManipulationState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ManipulationState {
+    pub fn new() -> Self {
+        Self {
+            ticks: 0,
+            direction: OrderSide::Bid,
+            start_inventory: 0,
+            activity: Vec::new(),
+        }
+    }
+
+    /// Tagged orders placed since the last call. Timestamps are left at 0 for
+    /// the caller to fill in, like the orders themselves.
+    pub fn take_activity(&mut self) -> Vec<ManipulationRecord> {
+        std::mem::take(&mut self.activity)
+    }
+
+    pub fn spoof(&mut self, config: &SpoofConfig, agent: &Agent, current_price: u64, rng: &mut impl Rng) -> Vec<Order> {
+        if self.ticks > 0 && self.ticks.is_multiple_of(config.campaign_ticks.max(1)) {
+            self.direction = opposite(self.direction);
+        }
+        self.ticks += 1;
+
+        let (best_bid, best_ask) = agent.book_top;
+        let bid_touch = best_bid.unwrap_or(current_price.saturating_sub(10)).max(1);
+        let ask_touch = best_ask.unwrap_or(current_price + 10);
+        let layers = config.layers.max(1) as u64;
+        let mut orders = Vec::new();
+
+        for i in 0..layers {
+            let offset = config.distance_bps + i * config.spacing_bps;
+            let (price, size) = match self.direction {
+                OrderSide::Bid => {
+                    let price = (bid_touch * 10000u64.saturating_sub(offset) / 10000).max(1);
+                    (price, config.layer_size.min(agent.max_buy(price) / layers))
+                }
+                OrderSide::Ask => {
+                    let price = ask_touch * (10000 + offset) / 10000;
+                    (price, config.layer_size.min(agent.max_sell(price) / layers))
+                }
+            };
+            orders.extend(self.tagged(agent, ManipulationTag::Layer, self.direction, price, size, rng));
+        }
+
+        // Lean on the touch opposite the fake pressure and let the others come to us
+        let (side, price, allowed) = match self.direction {
+            OrderSide::Bid => (OrderSide::Ask, ask_touch, agent.can_sell(ask_touch, config.genuine_size)),
+            OrderSide::Ask => (OrderSide::Bid, bid_touch, agent.can_buy(bid_touch, config.genuine_size)),
+        };
+        if allowed {
+            orders.extend(self.tagged(agent, ManipulationTag::Genuine, side, price, config.genuine_size, rng));
+        }
+        orders
+    }
+
+    pub fn ignite(&mut self, config: &IgnitionConfig, agent: &Agent, current_price: u64, rng: &mut impl Rng) -> Vec<Order> {
+        let cycle = (config.burst_ticks + config.unwind_ticks + config.cooldown_ticks).max(1);
+        let phase = self.ticks % cycle;
+        self.ticks += 1;
+        if phase == 0 {
+            self.direction = if rng.gen_bool(0.5) { OrderSide::Bid } else { OrderSide::Ask };
+            self.start_inventory = agent.inventory;
+        }
+
+        let (best_bid, best_ask) = agent.book_top;
+        let bid_touch = best_bid.unwrap_or(current_price.saturating_sub(10)).max(1);
+        let ask_touch = best_ask.unwrap_or(current_price + 10);
+        let mut orders = Vec::new();
+
+        if phase < config.burst_ticks {
+            // Sweep a few levels through the far touch
+            let (price, size) = match self.direction {
+                OrderSide::Bid => {
+                    let price = ask_touch * 10020 / 10000;
+                    (price, config.burst_size.min(agent.max_buy(price)))
+                }
+                OrderSide::Ask => {
+                    let price = (bid_touch * 9980 / 10000).max(1);
+                    (price, config.burst_size.min(agent.max_sell(price)))
+                }
+            };
+            orders.extend(self.tagged(agent, ManipulationTag::Ignition, self.direction, price, size, rng));
+        } else if phase < config.burst_ticks + config.unwind_ticks {
+            // Spread the exit over the remaining unwind ticks, passive at the
+            // touch the followers are hitting, and cross on the last one
+            let remaining = config.burst_ticks + config.unwind_ticks - phase;
+            let last = remaining == 1;
+            match self.direction {
+                OrderSide::Bid if agent.inventory > self.start_inventory => {
+                    let excess = agent.inventory - self.start_inventory;
+                    let price = if last { bid_touch } else { ask_touch };
+                    let size = excess.div_ceil(remaining).min(agent.max_sell(price));
+                    orders.extend(self.tagged(agent, ManipulationTag::Unwind, OrderSide::Ask, price, size, rng));
+                }
+                OrderSide::Ask if agent.inventory < self.start_inventory => {
+                    let deficit = self.start_inventory - agent.inventory;
+                    let price = if last { ask_touch } else { bid_touch };
+                    let size = deficit.div_ceil(remaining).min(agent.max_buy(price));
+                    orders.extend(self.tagged(agent, ManipulationTag::Unwind, OrderSide::Bid, price, size, rng));
+                }
+                _ => {}
+            }
+        }
+        orders
+    }
+
+    /// Build an order and log it under `tag`. Zero-sized orders are skipped.
+    fn tagged(&mut self, agent: &Agent, tag: ManipulationTag, side: OrderSide, price: u64, amount: u64, rng: &mut impl Rng) -> Option<Order> {
+        if amount == 0 {
+            return None;
+        }
+        let order = Order {
+            id: rng.next_u64(),
+            trader_id: agent.id,
+            side,
+            price,
+            amount,
+            timestamp: 0,
+        };
+        self.activity.push(ManipulationRecord {
+            timestamp: 0,
+            trader_id: agent.id,
+            order_id: order.id,
+            tag,
+            side,
+            price,
+            amount,
+        });
+        Some(order)
+    }
+}
+
+fn opposite(side: OrderSide) -> OrderSide {
+    match side {
+        OrderSide::Bid => OrderSide::Ask,
+        OrderSide::Ask => OrderSide::Bid,
+    }
+}
@@ -0,0 +1,192 @@
+use crate::types::OrderSide;
+use serde::{Deserialize, Serialize};
+
+/// Broker terms for a margin account.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MarginConfig {
+    /// Equity / exposure required to open new positions. 0.5 = 2x leverage.
+    pub initial_margin: f64,
+    /// Equity / exposure below which the account is liquidated.
+    pub maintenance_margin: f64,
+    /// Interest per tick on borrowed cash.
+    pub borrow_rate: f64,
+    /// Fee per tick on the market value of borrowed shares.
+    pub short_fee: f64,
+    /// How many shares the broker can locate for shorting.
+    pub short_locate: u64,
+}
+
+impl Default for MarginConfig {
+    fn default() -> Self {
+        Self {
+            initial_margin: 0.5,
+            maintenance_margin: 0.25,
+            borrow_rate: 0.00001,
+            short_fee: 0.00002,
+            short_locate: 1000,
+        }
+    }
+}
+
+/// Cash and share loans on top of an agent's own `cash` and `inventory`.
+///
+/// Buys that run past the agent's cash borrow the difference, sells that run
+/// past its inventory borrow shares (a short). Proceeds repay loans first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarginAccount {
+    pub config: MarginConfig,
+    pub borrowed_cash: u64,
+    pub borrowed_shares: u64,
+    pub interest_paid: u64,
+    accrued: f64, // fractional cents not yet charged
+}
+
+impl MarginAccount {
+    pub fn new(config: MarginConfig) -> Self {
+        Self {
+            config,
+            borrowed_cash: 0,
+            borrowed_shares: 0,
+            interest_paid: 0,
+            accrued: 0.0,
+        }
+    }
+
+    /// Net liquidation value: what would be left after closing everything at `price`.
+    pub fn equity(&self, cash: u64, inventory: u64, price: u64) -> i64 {
+        cash as i64 + (inventory * price) as i64 - self.borrowed_cash as i64 - (self.borrowed_shares * price) as i64
+    }
+
+    /// Gross market value of long and short positions.
+    pub fn exposure(&self, inventory: u64, price: u64) -> u64 {
+        (inventory + self.borrowed_shares) * price
+    }
+
+    /// Largest buy the broker will finance. Covering a short is always allowed.
+    pub fn max_buy(&self, cash: u64, inventory: u64, price: u64) -> u64 {
+        let price = price.max(1);
+        let headroom = self.headroom(cash, inventory, price);
+        (cash / price).max(headroom / price + self.borrowed_shares)
+    }
+
+    /// Largest sell, including new short sales up to the locate and margin headroom.
+    pub fn max_sell(&self, cash: u64, inventory: u64, price: u64) -> u64 {
+        let price = price.max(1);
+        let locate = self.config.short_locate.saturating_sub(self.borrowed_shares);
+        let headroom = self.headroom(cash, inventory, price) / price;
+        inventory + locate.min(headroom)
+    }
+
+    /// Extra exposure that can be opened while staying above initial margin.
+    fn headroom(&self, cash: u64, inventory: u64, price: u64) -> u64 {
+        let equity = self.equity(cash, inventory, price).max(0) as f64;
+        let allowed = equity / self.config.initial_margin.max(0.01);
+        (allowed - self.exposure(inventory, price) as f64).max(0.0) as u64
+    }
+
+    pub fn settle_buy(&mut self, cash: &mut u64, inventory: &mut u64, price: u64, amount: u64) {
+        let cover = amount.min(self.borrowed_shares);
+        self.borrowed_shares -= cover;
+        *inventory += amount - cover;
+
+        let cost = price * amount;
+        if *cash >= cost {
+            *cash -= cost;
+        } else {
+            self.borrowed_cash += cost - *cash;
+            *cash = 0;
+        }
+    }
+
+    pub fn settle_sell(&mut self, cash: &mut u64, inventory: &mut u64, price: u64, amount: u64) {
+        let from_inventory = amount.min(*inventory);
+        *inventory -= from_inventory;
+        self.borrowed_shares += amount - from_inventory;
+
+        let revenue = price * amount;
+        let repay = revenue.min(self.borrowed_cash);
+        self.borrowed_cash -= repay;
+        *cash += revenue - repay;
+    }
+
+    /// Charge one tick of interest and borrow fees. Whole cents come out of cash,
+    /// or are added to the loan when there is no cash left.
+    pub fn accrue(&mut self, cash: &mut u64, price: u64) {
+        self.accrued += self.borrowed_cash as f64 * self.config.borrow_rate
+            + (self.borrowed_shares * price) as f64 * self.config.short_fee;
+        let due = self.accrued.floor() as u64;
+        if due == 0 {
+            return;
+        }
+        self.accrued -= due as f64;
+        self.interest_paid += due;
+        let from_cash = due.min(*cash);
+        *cash -= from_cash;
+        self.borrowed_cash += due - from_cash;
+    }
+
+    /// If equity has fallen below maintenance, the forced trade that brings the
+    /// account back to initial margin: buy back shorts first, otherwise sell longs.
+    pub fn liquidation(&self, cash: u64, inventory: u64, price: u64) -> Option<(OrderSide, u64)> {
+        let price = price.max(1);
+        let exposure = self.exposure(inventory, price);
+        if exposure == 0 || (self.borrowed_cash == 0 && self.borrowed_shares == 0) {
+            return None;
+        }
+        let equity = self.equity(cash, inventory, price);
+        if equity as f64 >= self.config.maintenance_margin * exposure as f64 {
+            return None;
+        }
+
+        let target = equity.max(0) as f64 / self.config.initial_margin.max(0.01);
+        let excess = ((exposure as f64 - target) / price as f64).ceil().max(1.0) as u64;
+        if self.borrowed_shares > 0 {
+            Some((OrderSide::Bid, excess.min(self.borrowed_shares)))
+        } else if inventory > 0 {
+            Some((OrderSide::Ask, excess.min(inventory)))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn initial_margin_sets_leverage() {
+        let account = MarginAccount::new(MarginConfig::default());
+        // $100 of equity at 50% initial margin finances $200 either way
+        assert_eq!(account.max_buy(10000, 0, 100), 200);
+        assert_eq!(account.max_sell(10000, 0, 100), 200);
+        // The inventory, then shorts up to the headroom ($50 more exposure) or the locate
+        assert_eq!(account.max_sell(0, 50, 100), 50 + 50);
+        assert_eq!(account.max_sell(1_000_000, 0, 100), 1000);
+    }
+
+    #[test]
+    fn long_is_liquidated_below_maintenance() {
+        let mut account = MarginAccount::new(MarginConfig::default());
+        let (mut cash, mut inventory) = (10000, 0);
+        account.settle_buy(&mut cash, &mut inventory, 100, 200);
+        assert_eq!((cash, inventory, account.borrowed_cash), (0, 200, 10000));
+
+        // Equity 4000 on exposure 14000 is still above 25%
+        assert_eq!(account.liquidation(cash, inventory, 70), None);
+        // Equity 2000 on 12000 is not; selling 134 gets back to 50%
+        assert_eq!(account.liquidation(cash, inventory, 60), Some((OrderSide::Ask, 134)));
+    }
+
+    #[test]
+    fn short_is_bought_back_below_maintenance() {
+        let mut account = MarginAccount::new(MarginConfig::default());
+        let (mut cash, mut inventory) = (10000, 0);
+        account.settle_sell(&mut cash, &mut inventory, 100, 100);
+        assert_eq!((cash, inventory, account.borrowed_shares), (20000, 0, 100));
+
+        assert_eq!(account.liquidation(cash, inventory, 150), None);
+        assert_eq!(account.liquidation(cash, inventory, 180), Some((OrderSide::Bid, 78)));
+    }
+}
@@ -0,0 +1,315 @@
+use crate::types::{OrderSide, Trade};
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+/// Shape of the social graph agents observe each other through.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum Topology {
+    /// Erdos-Renyi: every pair is linked with probability `p`.
+    Random { p: f64 },
+    /// Watts-Strogatz: ring lattice with `k` neighbours per node, each edge rewired with probability `rewire`.
+    SmallWorld { k: usize, rewire: f64 },
+    /// Barabasi-Albert: each new node attaches to `m` existing nodes, preferring well-connected ones.
+    ScaleFree { m: usize },
+}
+
+/// Tuning for herding behaviour on top of the graph.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SocialConfig {
+    pub topology: Topology,
+    /// How many ticks a neighbour's trade stays visible
+    pub memory_ticks: u64,
+    /// The highest-degree nodes become influencers
+    pub influencer_count: usize,
+    /// How much louder an influencer's action is than a regular node's
+    pub influencer_weight: f64,
+}
+
+impl Default for SocialConfig {
+    fn default() -> Self {
+        Self {
+            topology: Topology::SmallWorld { k: 6, rewire: 0.1 },
+            memory_ticks: 5,
+            influencer_count: 3,
+            influencer_weight: 5.0,
+        }
+    }
+}
+
+/// A rumour planted at a node by God Mode. It looks like a sustained trade in
+/// one direction for as long as it lasts.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Rumour {
+    side: OrderSide,
+    strength: f64,
+    expires_at: u64,
+}
+
+/// Who-watches-whom between agents, plus what each node was last seen doing.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SocialGraph {
+    config: SocialConfig,
+    neighbours: BTreeMap<u64, Vec<u64>>, // ordered, so builds are reproducible for a given seed
+    influencers: HashSet<u64>,
+    last_action: HashMap<u64, (OrderSide, u64)>, // (side, tick)
+    rumours: HashMap<u64, Rumour>,
+}
+
+impl SocialGraph {
+    pub fn build(ids: &[u64], config: SocialConfig, rng: &mut impl Rng) -> Self {
+        let mut edges: BTreeSet<(u64, u64)> = BTreeSet::new();
+        let n = ids.len();
+
+        match config.topology {
+            Topology::Random { p } => {
+                for i in 0..n {
+                    for j in (i + 1)..n {
+                        if rng.gen_bool(p.clamp(0.0, 1.0)) {
+                            edges.insert(edge(ids[i], ids[j]));
+                        }
+                    }
+                }
+            }
+            Topology::SmallWorld { k, rewire } => {
+                let half = (k / 2).max(1);
+                for i in 0..n {
+                    for step in 1..=half {
+                        let j = (i + step) % n;
+                        if i == j {
+                            continue;
+                        }
+                        // Rewire the far end to a random node, avoiding self-loops and duplicates
+                        let target = if rng.gen_bool(rewire.clamp(0.0, 1.0)) {
+                            let candidate = ids[rng.gen_range(0..n)];
+                            if candidate == ids[i] || edges.contains(&edge(ids[i], candidate)) {
+                                ids[j]
+                            } else {
+                                candidate
+                            }
+                        } else {
+                            ids[j]
+                        };
+                        edges.insert(edge(ids[i], target));
+                    }
+                }
+            }
+            Topology::ScaleFree { m } => {
+                let m = m.max(1).min(n.saturating_sub(1));
+                // Every endpoint appears once per edge, so sampling from this list is degree-proportional
+                let mut endpoints: Vec<u64> = Vec::new();
+                for i in 0..n.min(m + 1) {
+                    for j in (i + 1)..n.min(m + 1) {
+                        edges.insert(edge(ids[i], ids[j]));
+                        endpoints.push(ids[i]);
+                        endpoints.push(ids[j]);
+                    }
+                }
+                for &id in ids.iter().skip(m + 1) {
+                    let mut targets = BTreeSet::new();
+                    while targets.len() < m {
+                        if let Some(&target) = endpoints.choose(rng) {
+                            targets.insert(target);
+                        }
+                    }
+                    for target in targets {
+                        edges.insert(edge(id, target));
+                        endpoints.push(id);
+                        endpoints.push(target);
+                    }
+                }
+            }
+        }
+
+        let mut neighbours: BTreeMap<u64, Vec<u64>> = ids.iter().map(|&id| (id, Vec::new())).collect();
+        for (a, b) in edges {
+            neighbours.entry(a).or_default().push(b);
+            neighbours.entry(b).or_default().push(a);
+        }
+
+        let mut by_degree: Vec<(u64, usize)> = neighbours.iter().map(|(&id, n)| (id, n.len())).collect();
+        by_degree.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        let influencers = by_degree.iter().take(config.influencer_count).map(|&(id, _)| id).collect();
+
+        Self {
+            config,
+            neighbours,
+            influencers,
+            last_action: HashMap::new(),
+            rumours: HashMap::new(),
+        }
+    }
+
+    /// Attach a newcomer to `links` existing nodes, preferring well-connected ones.
+    pub fn add_node(&mut self, id: u64, links: usize, rng: &mut impl Rng) {
+        let endpoints: Vec<u64> = self.neighbours.iter()
+            .filter(|(&other, _)| other != id)
+            .flat_map(|(&other, n)| std::iter::repeat_n(other, n.len().max(1)))
+            .collect();
+        let mut targets = BTreeSet::new();
+        let distinct = self.neighbours.keys().filter(|&&other| other != id).count();
+        while targets.len() < links.min(distinct) {
+            if let Some(&target) = endpoints.choose(rng) {
+                targets.insert(target);
+            }
+        }
+        for &target in &targets {
+            self.neighbours.entry(target).or_default().push(id);
+        }
+        self.neighbours.entry(id).or_default().extend(targets);
+    }
+
+    pub fn remove_node(&mut self, id: u64) {
+        if let Some(links) = self.neighbours.remove(&id) {
+            for other in links {
+                if let Some(n) = self.neighbours.get_mut(&other) {
+                    n.retain(|&x| x != id);
+                }
+            }
+        }
+        self.influencers.remove(&id);
+        self.last_action.remove(&id);
+        self.rumours.remove(&id);
+    }
+
+    pub fn neighbours(&self, id: u64) -> &[u64] {
+        self.neighbours.get(&id).map(|n| n.as_slice()).unwrap_or(&[])
+    }
+
+    pub fn is_influencer(&self, id: u64) -> bool {
+        self.influencers.contains(&id)
+    }
+
+    /// The most connected influencer, a sensible default place to start a rumour.
+    pub fn top_influencer(&self) -> Option<u64> {
+        self.influencers.iter().copied().max_by_key(|id| (self.neighbours(*id).len(), std::cmp::Reverse(*id)))
+    }
+
+    /// Make both sides of a trade visible to their neighbours.
+    pub fn record_trade(&mut self, trade: &Trade) {
+        self.last_action.insert(trade.buyer_id, (OrderSide::Bid, trade.timestamp));
+        self.last_action.insert(trade.seller_id, (OrderSide::Ask, trade.timestamp));
+    }
+
+    /// Plant a rumour at `node` for `duration` ticks from `tick`. Fails if the
+    /// node isn't in the graph or the rumour would expire before anyone heard it.
+    pub fn seed_rumour(&mut self, node: u64, side: OrderSide, strength: f64, duration: u64, tick: u64) -> Result<(), String> {
+        if !self.neighbours.contains_key(&node) {
+            return Err(format!("agent {} is not in the social graph", node));
+        }
+        if duration == 0 {
+            return Err("duration must be at least 1 tick".to_string());
+        }
+        self.rumours.insert(node, Rumour { side, strength, expires_at: tick + duration });
+        Ok(())
+    }
+
+    /// Net direction of what `id`'s neighbours have recently done, in -1.0 (all
+    /// selling) to +1.0 (all buying), weighted towards influencers and rumours.
+    pub fn signal(&self, id: u64, tick: u64) -> f64 {
+        let mut weighted = 0.0;
+        let mut total = 0.0;
+
+        for &other in self.neighbours(id) {
+            let mut weight = if self.is_influencer(other) { self.config.influencer_weight } else { 1.0 };
+
+            let side = match self.rumours.get(&other) {
+                Some(rumour) if rumour.expires_at > tick => {
+                    weight *= rumour.strength;
+                    Some(rumour.side)
+                }
+                _ => self.last_action.get(&other)
+                    .filter(|(_, at)| tick.saturating_sub(*at) <= self.config.memory_ticks)
+                    .map(|(side, _)| *side),
+            };
+
+            total += weight;
+            match side {
+                Some(OrderSide::Bid) => weighted += weight,
+                Some(OrderSide::Ask) => weighted -= weight,
+                None => {}
+            }
+        }
+
+        if total > 0.0 { weighted / total } else { 0.0 }
+    }
+
+    /// Drop rumours that have run their course.
+    pub fn expire(&mut self, tick: u64) {
+        self.rumours.retain(|_, r| r.expires_at > tick);
+    }
+}
+
+fn edge(a: u64, b: u64) -> (u64, u64) {
+    if a < b { (a, b) } else { (b, a) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::SimRng;
+
+    fn graph(n: u64, topology: Topology) -> (Vec<u64>, SocialGraph) {
+        let ids: Vec<u64> = (1..=n).collect();
+        let config = SocialConfig { topology, ..SocialConfig::default() };
+        let graph = SocialGraph::build(&ids, config, &mut SimRng::seed_from_u64(1));
+        (ids, graph)
+    }
+
+    fn degrees(ids: &[u64], graph: &SocialGraph) -> Vec<usize> {
+        ids.iter().map(|&id| graph.neighbours(id).len()).collect()
+    }
+
+    fn edges(ids: &[u64], graph: &SocialGraph) -> usize {
+        degrees(ids, graph).iter().sum::<usize>() / 2
+    }
+
+    #[test]
+    fn random_graph_edges_follow_p() {
+        let (ids, complete) = graph(12, Topology::Random { p: 1.0 });
+        assert!(degrees(&ids, &complete).iter().all(|&d| d == 11));
+        assert_eq!(edges(&ids, &complete), 12 * 11 / 2);
+
+        let (ids, empty) = graph(12, Topology::Random { p: 0.0 });
+        assert_eq!(edges(&ids, &empty), 0);
+    }
+
+    #[test]
+    fn unrewired_small_world_is_a_ring_lattice() {
+        let (ids, graph) = graph(20, Topology::SmallWorld { k: 4, rewire: 0.0 });
+        assert!(degrees(&ids, &graph).iter().all(|&d| d == 4));
+        assert_eq!(edges(&ids, &graph), 20 * 4 / 2);
+        assert_eq!(graph.neighbours(1), &[2, 3, 19, 20]);
+    }
+
+    #[test]
+    fn scale_free_attaches_m_links_per_node() {
+        let m = 2;
+        let (ids, graph) = graph(30, Topology::ScaleFree { m });
+        // A complete core of m + 1 nodes, then m links for each later node
+        assert_eq!(edges(&ids, &graph), 3 + (30 - 3) * m);
+        assert!(degrees(&ids, &graph).iter().all(|&d| d >= m));
+    }
+
+    #[test]
+    fn rumour_leans_neighbours_its_way_until_it_expires() {
+        let (_, mut graph) = graph(20, Topology::SmallWorld { k: 4, rewire: 0.0 });
+        graph.seed_rumour(5, OrderSide::Bid, 3.0, 10, 100).unwrap();
+        assert!(graph.signal(4, 100) > 0.0);
+        assert_eq!(graph.signal(4, 110), 0.0);
+
+        graph.seed_rumour(5, OrderSide::Ask, 3.0, 10, 100).unwrap();
+        assert!(graph.signal(4, 100) < 0.0);
+        // Not a neighbour of node 5
+        assert_eq!(graph.signal(12, 100), 0.0);
+    }
+
+    #[test]
+    fn rumour_needs_a_node_and_a_duration() {
+        let (_, mut graph) = graph(20, Topology::SmallWorld { k: 4, rewire: 0.0 });
+        assert!(graph.seed_rumour(99, OrderSide::Bid, 3.0, 10, 1).is_err());
+        assert!(graph.seed_rumour(5, OrderSide::Bid, 3.0, 0, 1).is_err());
+        graph.remove_node(5);
+        assert!(graph.seed_rumour(5, OrderSide::Bid, 3.0, 10, 1).is_err());
+    }
+}
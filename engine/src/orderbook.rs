@@ -1,8 +1,10 @@
 use std::collections::BinaryHeap;
 use std::cmp::Ordering;
+use crate::say;
 use crate::types::{Order, OrderSide, Trade};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
 struct Bid(Order);
 
 impl Ord for Bid {
@@ -20,7 +22,7 @@ impl PartialOrd for Bid {
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
 struct Ask(Order);
 
 impl Ord for Ask {
@@ -38,10 +40,30 @@ impl PartialOrd for Ask {
     }
 }
 
+/// Everything that happens to the book, in order, for consumers such as
+/// surveillance. Accumulates until `drain_events` is called.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum BookEvent {
+    /// An order arrived, before any matching. Market orders show up with the
+    /// price they were sent with.
+    Placed(Order),
+    /// A resting order was pulled; `amount` is what was left of it
+    Cancelled(Order),
+    Traded(Trade),
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct OrderBook {
     bids: BinaryHeap<Bid>,
     asks: BinaryHeap<Ask>,
     pub trades: Vec<Trade>,
+    events: Vec<BookEvent>,
+}
+
+impl Default for OrderBook {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl OrderBook {
@@ -50,6 +72,7 @@ impl OrderBook {
             bids: BinaryHeap::new(),
             asks: BinaryHeap::new(),
             trades: Vec::new(),
+            events: Vec::new(),
         }
     }
 
@@ -62,13 +85,42 @@ impl OrderBook {
     }
 
     pub fn add_order(&mut self, order: Order) {
+        self.events.push(BookEvent::Placed(order));
         match order.side {
-            OrderSide::Bid => self.match_bid(order),
-            OrderSide::Ask => self.match_ask(order),
+            OrderSide::Bid => {
+                let rest = self.match_bid(order);
+                if rest.amount > 0 {
+                    self.bids.push(Bid(rest));
+                }
+            }
+            OrderSide::Ask => {
+                let rest = self.match_ask(order);
+                if rest.amount > 0 {
+                    self.asks.push(Ask(rest));
+                }
+            }
         }
     }
 
-    fn match_bid(&mut self, mut bid: Order) {
+    /// Match against the book at any price and drop whatever is left unfilled.
+    /// The order's own price is ignored. Returns the unfilled quantity.
+    pub fn add_market_order(&mut self, mut order: Order) -> u64 {
+        self.events.push(BookEvent::Placed(order));
+        let rest = match order.side {
+            OrderSide::Bid => {
+                order.price = u64::MAX;
+                self.match_bid(order)
+            }
+            OrderSide::Ask => {
+                order.price = 0;
+                self.match_ask(order)
+            }
+        };
+        rest.amount
+    }
+
+    /// Cross `bid` against resting asks, returning the unfilled remainder.
+    fn match_bid(&mut self, mut bid: Order) -> Order {
         while bid.amount > 0 {
             if let Some(best_ask_wrapper) = self.asks.peek() {
                 let best_ask = &best_ask_wrapper.0;
@@ -83,9 +135,12 @@ impl OrderBook {
                         price: match_price,
                         amount: match_amount,
                         timestamp: bid.timestamp,
+                        buy_order_id: bid.id,
+                        sell_order_id: best_ask.id,
                     };
                     self.trades.push(trade);
-                    println!("Trade Executed: {} units @ ${:.2}", match_amount, match_price as f64 / 100.0);
+                    self.events.push(BookEvent::Traded(trade));
+                    say!("Trade Executed: {} units @ ${:.2}", match_amount, match_price as f64 / 100.0);
 
                     bid.amount -= match_amount;
                     
@@ -103,12 +158,11 @@ impl OrderBook {
             }
         }
 
-        if bid.amount > 0 {
-            self.bids.push(Bid(bid));
-        }
+        bid
     }
 
-    fn match_ask(&mut self, mut ask: Order) {
+    /// Cross `ask` against resting bids, returning the unfilled remainder.
+    fn match_ask(&mut self, mut ask: Order) -> Order {
         while ask.amount > 0 {
             if let Some(best_bid_wrapper) = self.bids.peek() {
                 let best_bid = &best_bid_wrapper.0;
@@ -123,9 +177,12 @@ impl OrderBook {
                         price: match_price,
                         amount: match_amount,
                         timestamp: ask.timestamp,
+                        buy_order_id: best_bid.id,
+                        sell_order_id: ask.id,
                     };
                     self.trades.push(trade);
-                    println!("Trade Executed: {} units @ ${:.2}", match_amount, match_price as f64 / 100.0);
+                    self.events.push(BookEvent::Traded(trade));
+                    say!("Trade Executed: {} units @ ${:.2}", match_amount, match_price as f64 / 100.0);
 
                     ask.amount -= match_amount;
 
@@ -143,12 +200,72 @@ impl OrderBook {
             }
         }
 
-        if ask.amount > 0 {
-            self.asks.push(Ask(ask));
+        ask
+    }
+
+    /// Remove a resting order by id, if it belongs to `trader_id`. Returns the
+    /// unfilled remainder if it was still in the book.
+    pub fn cancel_order(&mut self, trader_id: u64, order_id: u64) -> Option<Order> {
+        let mut cancelled = None;
+        let mut matches = |o: &Order| {
+            if o.id == order_id && o.trader_id == trader_id { cancelled = Some(*o); false } else { true }
+        };
+        self.bids.retain(|b| matches(&b.0));
+        self.asks.retain(|a| matches(&a.0));
+        if let Some(order) = cancelled {
+            self.events.push(BookEvent::Cancelled(order));
         }
+        cancelled
+    }
+
+    /// Remove every resting order belonging to `trader_id`. Returns how many were pulled.
+    pub fn cancel_trader_orders(&mut self, trader_id: u64) -> usize {
+        let events = &mut self.events;
+        let mut pulled = 0;
+        let mut keep = |o: &Order| {
+            if o.trader_id == trader_id {
+                events.push(BookEvent::Cancelled(*o));
+                pulled += 1;
+                false
+            } else {
+                true
+            }
+        };
+        self.bids.retain(|b| keep(&b.0));
+        self.asks.retain(|a| keep(&a.0));
+        pulled
+    }
+
+    /// Resting size imbalance within `window_bps` of the mid, from -1.0 (all
+    /// asks) to +1.0 (all bids). What an agent reading the book sees as pressure.
+    pub fn imbalance(&self, window_bps: u64) -> f64 {
+        let mid = match (self.best_bid_price(), self.best_ask_price()) {
+            (Some(bid), Some(ask)) => (bid + ask) / 2,
+            (Some(price), None) | (None, Some(price)) => price,
+            (None, None) => return 0.0,
+        };
+        let reach = mid * window_bps / 10000;
+        let bids: u64 = self.bids.iter().filter(|b| b.0.price + reach >= mid).map(|b| b.0.amount).sum();
+        let asks: u64 = self.asks.iter().filter(|a| a.0.price <= mid + reach).map(|a| a.0.amount).sum();
+        if bids + asks == 0 {
+            return 0.0;
+        }
+        (bids as f64 - asks as f64) / (bids + asks) as f64
+    }
+
+    /// Resting orders belonging to `trader_id`, in no particular order.
+    pub fn trader_orders(&self, trader_id: u64) -> Vec<Order> {
+        self.bids.iter().map(|b| b.0)
+            .chain(self.asks.iter().map(|a| a.0))
+            .filter(|o| o.trader_id == trader_id)
+            .collect()
     }
 
     pub fn drain_trades(&mut self) -> Vec<Trade> {
         self.trades.drain(..).collect()
     }
+
+    pub fn drain_events(&mut self) -> Vec<BookEvent> {
+        self.events.drain(..).collect()
+    }
 }
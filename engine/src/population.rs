@@ -0,0 +1,419 @@
+use crate::agents::{Agent, Strategy};
+use crate::behaviour::BehaviouralTraits;
+use crate::indicators::IndicatorSpec;
+use crate::manipulation::{IgnitionConfig, SpoofConfig};
+use crate::margin::{MarginAccount, MarginConfig};
+use crate::network::SocialGraph;
+use crate::scripting::ScriptHandle;
+use crate::orderbook::OrderBook;
+use crate::say;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Genetic algorithm run at each generation boundary.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GeneticConfig {
+    /// Top fraction of agents (by generation return) that get to reproduce
+    pub elite_fraction: f64,
+    /// Bottom fraction of agents replaced by mutated offspring of the elite
+    pub replace_fraction: f64,
+    /// Relative size of parameter mutations, e.g. 0.2 = up to +/-20%
+    pub mutation_scale: f64,
+}
+
+impl Default for GeneticConfig {
+    fn default() -> Self {
+        Self {
+            elite_fraction: 0.2,
+            replace_fraction: 0.2,
+            mutation_scale: 0.2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PopulationConfig {
+    /// Ticks per generation
+    pub generation_length: u64,
+    /// Agents at or below this equity (cents) are removed
+    pub bankruptcy_equity: i64,
+    /// Spawn new entrants every this many ticks (0 = never)
+    pub entry_interval: u64,
+    pub entrants_per_interval: usize,
+    pub entrant_cash: u64,
+    pub entrant_inventory: u64,
+    /// Social graph links for each newcomer
+    pub entrant_links: usize,
+    pub genetic: Option<GeneticConfig>,
+}
+
+impl Default for PopulationConfig {
+    fn default() -> Self {
+        Self {
+            generation_length: 500,
+            bankruptcy_equity: 0,
+            entry_interval: 100,
+            entrants_per_interval: 1,
+            entrant_cash: 1000000,
+            entrant_inventory: 1000,
+            entrant_links: 3,
+            genetic: None,
+        }
+    }
+}
+
+/// End-of-generation summary, broadcast to the dashboard.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerationReport {
+    pub generation: u64,
+    pub tick: u64,
+    pub population: usize,
+    /// Fraction of the population running each strategy
+    pub strategy_shares: BTreeMap<String, f64>,
+    /// Mean return on starting equity per strategy over the generation
+    pub strategy_returns: BTreeMap<String, f64>,
+    pub bankruptcies: usize,
+    pub entrants: usize,
+    pub offspring: usize,
+}
+
+/// Everything that changed membership this tick.
+#[derive(Debug, Default)]
+pub struct PopulationEvents {
+    pub removed: Vec<u64>,
+    pub added: Vec<u64>,
+    pub report: Option<GenerationReport>,
+}
+
+/// Removes bankrupt agents, brings in new ones, and optionally evolves
+/// strategy parameters between generations.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PopulationManager {
+    config: PopulationConfig,
+    next_id: u64,
+    generation: u64,
+    generation_start: HashMap<u64, i64>, // equity at the start of the generation
+    bankruptcies: usize,
+    entrants: usize,
+    seed: u64, // run seed, for the random streams of agents created here
+}
+
+impl PopulationManager {
+    pub fn new(config: PopulationConfig, agents: &HashMap<u64, Agent>, current_price: u64) -> Self {
+        Self {
+            config,
+            next_id: agents.keys().max().copied().unwrap_or(0) + 1,
+            generation: 0,
+            generation_start: agents.iter().map(|(&id, a)| (id, a.equity(current_price))).collect(),
+            bankruptcies: 0,
+            entrants: 0,
+            seed: 0,
+        }
+    }
+
+    /// Run seed that admitted and spawned agents derive their random streams from.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn reseed(&mut self, seed: u64) {
+        self.seed = seed;
+    }
+
+    /// Register an agent created outside the manager (e.g. from God Mode). Build
+    /// it with an id from `next_id`.
+    pub fn admit(&mut self, mut agent: Agent, agents: &mut HashMap<u64, Agent>, agent_ids: &mut Vec<u64>, current_price: u64) {
+        agent.reseed(self.seed);
+        self.generation_start.insert(agent.id, agent.equity(current_price));
+        agent_ids.push(agent.id);
+        agents.insert(agent.id, agent);
+    }
+
+    pub fn next_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Run exit, entry and (at generation boundaries) selection for this tick.
+    /// Membership changes are applied to `agents` and `agent_ids`; pass the
+    /// result to `settle` to bring the order book and social graph in line.
+    pub fn on_tick(
+        &mut self,
+        tick: u64,
+        current_price: u64,
+        agents: &mut HashMap<u64, Agent>,
+        agent_ids: &mut Vec<u64>,
+        rng: &mut impl Rng,
+    ) -> PopulationEvents {
+        let mut events = PopulationEvents::default();
+
+        // Exit: out of equity, or unable to place a single order either way
+        let bankrupt: Vec<u64> = agent_ids.iter().copied()
+            .filter(|id| {
+                let agent = &agents[id];
+                !matches!(agent.strategy, Strategy::External)
+                    && (agent.equity(current_price) <= self.config.bankruptcy_equity
+                        || (agent.max_buy(current_price) == 0 && agent.max_sell(current_price) == 0))
+            })
+            .collect();
+        for id in bankrupt {
+            if let Some(agent) = agents.get(&id) {
+                say!("Tick {}: BANKRUPT {} ({})", tick, agent.name, agent.strategy.kind());
+            }
+            self.remove(id, agents, agent_ids);
+            self.bankruptcies += 1;
+            events.removed.push(id);
+        }
+
+        // Entry: clone the strategy of a random incumbent with fresh capital.
+        // Manipulators stay the few the scenario put in.
+        if self.config.entry_interval > 0 && tick.is_multiple_of(self.config.entry_interval) {
+            for _ in 0..self.config.entrants_per_interval {
+                let Some(template) = agent_ids.choose(rng).and_then(|id| agents.get(id)) else { break };
+                if matches!(template.strategy, Strategy::External | Strategy::Spoofer(_) | Strategy::MomentumIgniter(_)) {
+                    continue;
+                }
+                let inherited = Inherited::from(template);
+                let strategy = template.strategy.clone();
+                let id = self.spawn(strategy, inherited, "New", agents, agent_ids, current_price);
+                self.entrants += 1;
+                events.added.push(id);
+            }
+        }
+
+        if self.config.generation_length > 0 && tick.is_multiple_of(self.config.generation_length) {
+            let returns = self.generation_returns(agents, current_price);
+            // The generation as it traded, before any offspring replace it
+            let mut report = self.report(tick, agents, &returns);
+            if let Some(genetic) = self.config.genetic {
+                let (removed, added) = self.evolve(genetic, &returns, agents, agent_ids, current_price, rng);
+                report.offspring = added.len();
+                events.removed.extend(removed);
+                events.added.extend(added);
+            }
+            events.report = Some(report);
+
+            self.generation += 1;
+            self.bankruptcies = 0;
+            self.entrants = 0;
+            self.generation_start = agents.iter().map(|(&id, a)| (id, a.equity(current_price))).collect();
+        }
+
+        events
+    }
+
+    /// Return on equity since each agent entered this generation.
+    fn generation_returns(&self, agents: &HashMap<u64, Agent>, current_price: u64) -> HashMap<u64, f64> {
+        agents.iter()
+            .filter(|(_, a)| !matches!(a.strategy, Strategy::External))
+            .map(|(&id, a)| {
+                let start = self.generation_start.get(&id).copied().unwrap_or_else(|| a.equity(current_price)).max(1);
+                (id, a.equity(current_price) as f64 / start as f64 - 1.0)
+            })
+            .collect()
+    }
+
+    /// Cancel departed agents' orders and wire newcomers into the social graph.
+    pub fn settle(&self, events: &PopulationEvents, book: &mut OrderBook, graph: &mut SocialGraph, rng: &mut impl Rng) {
+        for &id in &events.added {
+            graph.add_node(id, self.config.entrant_links, rng);
+        }
+        for &id in &events.removed {
+            book.cancel_trader_orders(id);
+            graph.remove_node(id);
+        }
+    }
+
+    /// Replace the worst performers with mutated copies of the best.
+    fn evolve(
+        &mut self,
+        genetic: GeneticConfig,
+        returns: &HashMap<u64, f64>,
+        agents: &mut HashMap<u64, Agent>,
+        agent_ids: &mut Vec<u64>,
+        current_price: u64,
+        rng: &mut impl Rng,
+    ) -> (Vec<u64>, Vec<u64>) {
+        let mut ranked: Vec<(u64, f64)> = returns.iter().map(|(&id, &r)| (id, r)).collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+        let n = ranked.len();
+        let elite = ((n as f64 * genetic.elite_fraction).ceil() as usize).clamp(1, n.max(1));
+        let replace = ((n as f64 * genetic.replace_fraction).floor() as usize).min(n.saturating_sub(elite));
+        if n == 0 || replace == 0 {
+            return (Vec::new(), Vec::new());
+        }
+
+        let parents: Vec<u64> = ranked[..elite].iter().map(|&(id, _)| id).collect();
+        let losers: Vec<u64> = ranked[n - replace..].iter().map(|&(id, _)| id).collect();
+
+        let mut added = Vec::with_capacity(replace);
+        for _ in 0..replace {
+            let parent = &agents[parents.choose(rng).unwrap()];
+            let child = mutate(&parent.strategy, genetic.mutation_scale, rng);
+            let inherited = Inherited::from(parent);
+            let prefix = format!("G{}", self.generation + 1);
+            added.push(self.spawn(child, inherited, &prefix, agents, agent_ids, current_price));
+        }
+        for &id in &losers {
+            self.remove(id, agents, agent_ids);
+        }
+        (losers, added)
+    }
+
+    /// Shares and mean returns of the simulated agents, those in `returns`.
+    fn report(&self, tick: u64, agents: &HashMap<u64, Agent>, returns: &HashMap<u64, f64>) -> GenerationReport {
+        let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+        let mut return_sums: BTreeMap<String, f64> = BTreeMap::new();
+        for (id, &r) in returns {
+            let kind = agents[id].strategy.kind().to_string();
+            *counts.entry(kind.clone()).or_default() += 1;
+            *return_sums.entry(kind).or_default() += r;
+        }
+        let population = returns.len();
+        GenerationReport {
+            generation: self.generation,
+            tick,
+            population,
+            strategy_shares: counts.iter().map(|(k, &c)| (k.clone(), c as f64 / population.max(1) as f64)).collect(),
+            strategy_returns: counts.iter().map(|(k, &c)| (k.clone(), return_sums[k] / c as f64)).collect(),
+            bankruptcies: self.bankruptcies,
+            entrants: self.entrants,
+            offspring: 0,
+        }
+    }
+
+    /// Take an agent out of the market, e.g. when its trading session closes.
+    pub fn remove(&mut self, id: u64, agents: &mut HashMap<u64, Agent>, agent_ids: &mut Vec<u64>) {
+        agents.remove(&id);
+        agent_ids.retain(|&x| x != id);
+        self.generation_start.remove(&id);
+    }
+
+    /// Add a new agent running `strategy`, with inherited behavioural traits,
+    /// memory and indicators, and a fresh margin account on the same terms.
+    fn spawn(
+        &mut self,
+        strategy: Strategy,
+        inherited: Inherited,
+        prefix: &str,
+        agents: &mut HashMap<u64, Agent>,
+        agent_ids: &mut Vec<u64>,
+        current_price: u64,
+    ) -> u64 {
+        let id = self.next_id();
+        let name = format!("{}_{}_{}", prefix, strategy.kind(), id);
+        let mut agent = Agent::new(id, name, self.config.entrant_cash, self.config.entrant_inventory, strategy);
+        if let Some(traits) = inherited.traits {
+            agent = agent.with_traits(traits);
+        }
+        if let Some(margin) = inherited.margin {
+            agent = agent.with_margin(MarginAccount::new(margin));
+        }
+        if let Some(script) = inherited.script {
+            agent = agent.with_script(script);
+        }
+        agent = agent.with_memory(inherited.memory);
+        agent.reseed(self.seed);
+        for spec in inherited.indicators {
+            agent.subscribe(spec);
+        }
+
+        self.generation_start.insert(id, agent.equity(current_price));
+        agents.insert(id, agent);
+        agent_ids.push(id);
+        id
+    }
+}
+
+/// What a newcomer takes over from the agent it was modelled on, besides the strategy.
+struct Inherited {
+    traits: Option<BehaviouralTraits>,
+    margin: Option<MarginConfig>,
+    script: Option<ScriptHandle>,
+    memory: usize,
+    indicators: Vec<IndicatorSpec>,
+}
+
+impl From<&Agent> for Inherited {
+    fn from(agent: &Agent) -> Self {
+        Self {
+            traits: agent.traits.clone().map(|t| BehaviouralTraits { reference_price: None, ..t }),
+            margin: agent.margin.as_ref().map(|m| m.config),
+            script: agent.script.as_ref().map(|s| s.handle.clone()),
+            memory: agent.max_history,
+            // The strategy's own indicators follow its (possibly mutated) parameters
+            indicators: agent.indicators.specs().filter(|s| !agent.strategy.indicators().contains(s)).collect(),
+        }
+    }
+}
+
+/// Perturb every numeric parameter of a strategy by up to `scale` in relative terms.
+pub fn mutate(strategy: &Strategy, scale: f64, rng: &mut impl Rng) -> Strategy {
+    let scale = scale.abs();
+    let mut jitter = |x: f64| x * (1.0 + rng.gen_range(-scale..=scale));
+    let window = |x: f64| (x.round() as usize).max(2);
+
+    match strategy.clone() {
+        Strategy::TrendFollower { window_size } => Strategy::TrendFollower {
+            window_size: window(jitter(window_size as f64)),
+        },
+        Strategy::MeanReverter { window_size, std_dev_multiplier } => Strategy::MeanReverter {
+            window_size: window(jitter(window_size as f64)),
+            std_dev_multiplier: jitter(std_dev_multiplier).max(0.1),
+        },
+        Strategy::MarketMaker { spread_bps } => Strategy::MarketMaker {
+            spread_bps: (jitter(spread_bps as f64).round() as u64).max(1),
+        },
+        Strategy::BeliefSwitcher { intensity_of_choice, fundamental_price, trend_strength, reversion_strength, memory } => {
+            Strategy::BeliefSwitcher {
+                intensity_of_choice: jitter(intensity_of_choice).max(0.0),
+                fundamental_price,
+                trend_strength: jitter(trend_strength),
+                reversion_strength: jitter(reversion_strength).clamp(0.0, 1.0),
+                memory: jitter(memory).clamp(0.0, 0.999),
+            }
+        }
+        Strategy::Herder { imitation_probability } => Strategy::Herder {
+            imitation_probability: jitter(imitation_probability).clamp(0.0, 1.0),
+        },
+        Strategy::AvellanedaStoikov { risk_aversion, horizon, arrival_at_touch, quote_size, inventory_limit, target_inventory } => {
+            Strategy::AvellanedaStoikov {
+                risk_aversion: jitter(risk_aversion).max(1e-6),
+                horizon: jitter(horizon).max(1.0),
+                arrival_at_touch: jitter(arrival_at_touch).max(0.01),
+                quote_size: (jitter(quote_size as f64).round() as u64).max(1),
+                inventory_limit: (jitter(inventory_limit as f64).round() as u64).max(1),
+                target_inventory,
+            }
+        }
+        Strategy::QLearner { epsilon, learning_rate, discount } => Strategy::QLearner {
+            epsilon: jitter(epsilon).clamp(0.0, 1.0),
+            learning_rate: jitter(learning_rate).clamp(0.001, 1.0),
+            discount: jitter(discount).clamp(0.0, 0.999),
+        },
+        Strategy::Spoofer(config) => Strategy::Spoofer(SpoofConfig {
+            layer_size: (jitter(config.layer_size as f64).round() as u64).max(1),
+            distance_bps: jitter(config.distance_bps as f64).round() as u64,
+            spacing_bps: jitter(config.spacing_bps as f64).round() as u64,
+            genuine_size: (jitter(config.genuine_size as f64).round() as u64).max(1),
+            campaign_ticks: (jitter(config.campaign_ticks as f64).round() as u64).max(1),
+            ..config
+        }),
+        Strategy::MomentumIgniter(config) => Strategy::MomentumIgniter(IgnitionConfig {
+            burst_size: (jitter(config.burst_size as f64).round() as u64).max(1),
+            burst_ticks: (jitter(config.burst_ticks as f64).round() as u64).max(1),
+            unwind_ticks: (jitter(config.unwind_ticks as f64).round() as u64).max(1),
+            cooldown_ticks: jitter(config.cooldown_ticks as f64).round() as u64,
+        }),
+        other @ (Strategy::RandomWalker | Strategy::Scripted { .. } | Strategy::External) => other,
+    }
+}
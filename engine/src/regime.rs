@@ -0,0 +1,167 @@
+//! Market regimes: one switch for how noisy, busy and one-sided the market is.
+//!
+//! A `Regime` is a preset of `Conditions`, the handful of parameters agents and
+//! the scheduler read on top of their own: how far noise traders quote from the
+//! last price, how often everyone acts, how wide market makers quote, how
+//! readily herders follow, which way noise traders lean, and how the
+//! fundamental value BeliefSwitchers revert to wanders. God Mode picks the
+//! regime, and a volatility multiplier scales the jitter, the arrival rates and
+//! the fundamental's volatility within it.
+//!
+//! `Normal` is the market as the strategies define it; every condition is
+//! neutral there, so a run that never changes regime is unaffected.
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// How far noise traders quote either side of the last price at volatility 1, in cents.
+pub const NOISE_JITTER: f64 = 20.0;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Regime {
+    #[default]
+    Normal,
+    /// Quiet, tight and slow: less noise, fewer orders, narrow spreads
+    Calm,
+    /// Selling into fear: wide jitter, frantic order flow, wide spreads, a falling fundamental
+    Stressed,
+    /// Buying into hope: busy order flow, tight spreads, herding and a rising fundamental
+    Euphoric,
+}
+
+impl Regime {
+    pub fn conditions(self) -> Conditions {
+        match self {
+            Regime::Normal => Conditions::default(),
+            Regime::Calm => Conditions {
+                volatility: 0.5,
+                activity: 0.8,
+                fundamental_volatility: 0.0002,
+                spread: 0.75,
+                herding: 0.5,
+                ..Conditions::default()
+            },
+            Regime::Stressed => Conditions {
+                volatility: 2.5,
+                activity: 1.5,
+                fundamental_volatility: 0.002,
+                fundamental_drift: -0.0002,
+                buy_bias: -0.1,
+                spread: 2.0,
+                herding: 1.5,
+                ..Conditions::default()
+            },
+            Regime::Euphoric => Conditions {
+                volatility: 1.5,
+                activity: 1.3,
+                fundamental_volatility: 0.001,
+                fundamental_drift: 0.0003,
+                buy_bias: 0.1,
+                spread: 0.8,
+                herding: 1.5,
+                ..Conditions::default()
+            },
+        }
+    }
+}
+
+/// What agents and the scheduler read from the regime.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Conditions {
+    /// Multiple of `NOISE_JITTER` noise traders quote within
+    pub volatility: f64,
+    /// Multiple of every activation rate; agents that act once per tick act a
+    /// Poisson number of times averaging this
+    pub activity: f64,
+    /// Standard deviation of the fundamental's log change per tick
+    pub fundamental_volatility: f64,
+    /// Mean of the fundamental's log change per tick
+    pub fundamental_drift: f64,
+    /// Added to noise traders' probability of buying
+    pub buy_bias: f64,
+    /// Multiple of market makers' spreads
+    pub spread: f64,
+    /// Multiple of herders' imitation probability
+    pub herding: f64,
+    /// Where the fundamental stands, as a multiple of each BeliefSwitcher's own
+    pub fundamental: f64,
+}
+
+impl Default for Conditions {
+    fn default() -> Self {
+        Self {
+            volatility: 1.0,
+            activity: 1.0,
+            fundamental_volatility: 0.0,
+            fundamental_drift: 0.0,
+            buy_bias: 0.0,
+            spread: 1.0,
+            herding: 1.0,
+            fundamental: 1.0,
+        }
+    }
+}
+
+/// The simulation's regime setting and where it has taken the fundamental.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct MarketRegime {
+    pub regime: Regime,
+    /// Multiple of the regime's noise jitter, activity and fundamental volatility
+    pub volatility: f64,
+    /// Where the fundamental stands, as a multiple of where the scenario put it
+    pub fundamental: f64,
+}
+
+impl Default for MarketRegime {
+    fn default() -> Self {
+        Self { regime: Regime::Normal, volatility: 1.0, fundamental: 1.0 }
+    }
+}
+
+impl MarketRegime {
+    /// Switch regime. The volatility multiplier goes back to 1; the fundamental stays where it is.
+    pub fn set(&mut self, regime: Regime) {
+        self.regime = regime;
+        self.volatility = 1.0;
+    }
+
+    /// The regime's conditions with the volatility multiplier applied.
+    pub fn conditions(&self) -> Conditions {
+        let preset = self.regime.conditions();
+        Conditions {
+            volatility: preset.volatility * self.volatility,
+            activity: preset.activity * self.volatility,
+            fundamental_volatility: preset.fundamental_volatility * self.volatility,
+            fundamental: self.fundamental,
+            ..preset
+        }
+    }
+
+    /// Move the fundamental on by one tick. Draws from `rng` only while the
+    /// fundamental is volatile, so a steady market's random streams are untouched.
+    pub fn step(&mut self, rng: &mut impl Rng) {
+        let conditions = self.conditions();
+        let mut change = conditions.fundamental_drift;
+        if conditions.fundamental_volatility > 0.0 {
+            // Box-Muller
+            let (u1, u2): (f64, f64) = (rng.gen_range(f64::EPSILON..1.0), rng.gen());
+            change += conditions.fundamental_volatility * (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+        }
+        self.fundamental *= change.exp();
+    }
+
+    /// Back to the scenario's fundamental, e.g. on a reset. The regime stays.
+    pub fn restart(&mut self) {
+        self.fundamental = 1.0;
+    }
+}
+
+/// The regime as God Mode sees it.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct RegimeStatus {
+    #[serde(flatten)]
+    pub setting: MarketRegime,
+    /// What agents are trading under
+    pub conditions: Conditions,
+}
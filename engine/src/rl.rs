@@ -0,0 +1,144 @@
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+/// Number of recent returns included in an `Observation`.
+pub const RETURN_LAGS: usize = 5;
+
+/// What a learning agent sees each step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Observation {
+    pub price: u64,
+    /// Best ask minus best bid in basis points of the price, 0.0 if a side is empty.
+    pub spread_bps: f64,
+    /// Most recent first, simple returns between consecutive prices.
+    pub returns: [f64; RETURN_LAGS],
+    /// Own inventory relative to what the agent started with.
+    pub inventory: i64,
+    pub cash: u64,
+}
+
+impl Observation {
+    pub fn new(price: u64, best_bid: Option<u64>, best_ask: Option<u64>, history: &VecDeque<u64>, inventory: i64, cash: u64) -> Self {
+        let spread_bps = match (best_bid, best_ask) {
+            (Some(bid), Some(ask)) if ask > bid && price > 0 => (ask - bid) as f64 / price as f64 * 10000.0,
+            _ => 0.0,
+        };
+
+        let mut returns = [0.0; RETURN_LAGS];
+        let recent: Vec<u64> = history.iter().rev().take(RETURN_LAGS + 1).copied().collect();
+        for (lag, pair) in recent.windows(2).enumerate() {
+            if pair[1] > 0 {
+                returns[lag] = pair[0] as f64 / pair[1] as f64 - 1.0;
+            }
+        }
+
+        Self { price, spread_bps, returns, inventory, cash }
+    }
+
+    /// Flat feature vector: spread, returns, inventory, cash (in dollars).
+    pub fn features(&self) -> Vec<f64> {
+        let mut features = Vec::with_capacity(RETURN_LAGS + 3);
+        features.push(self.spread_bps);
+        features.extend_from_slice(&self.returns);
+        features.push(self.inventory as f64);
+        features.push(self.cash as f64 / 100.0);
+        features
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    Hold,
+    Buy,
+    Sell,
+}
+
+impl Action {
+    pub const ALL: [Action; 3] = [Action::Hold, Action::Buy, Action::Sell];
+
+    fn index(self) -> usize {
+        match self {
+            Action::Hold => 0,
+            Action::Buy => 1,
+            Action::Sell => 2,
+        }
+    }
+}
+
+/// Tabular Q-learning over a coarse discretisation of `Observation`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QLearner {
+    pub epsilon: f64,
+    pub learning_rate: f64,
+    pub discount: f64,
+    q: HashMap<u32, [f64; 3]>,
+    last: Option<(u32, Action)>,
+    last_equity: Option<i64>,
+    pub total_reward: f64,
+}
+
+impl QLearner {
+    pub fn new(epsilon: f64, learning_rate: f64, discount: f64) -> Self {
+        Self {
+            epsilon,
+            learning_rate,
+            discount,
+            q: HashMap::new(),
+            last: None,
+            last_equity: None,
+            total_reward: 0.0,
+        }
+    }
+
+    /// Bucket an observation into one of 3 * 3 * 3 * 2 = 54 states: last return,
+    /// cumulative return over all lags, inventory and spread width.
+    pub fn encode(obs: &Observation) -> u32 {
+        let bucket = |x: f64, threshold: f64| -> u32 {
+            if x > threshold { 2 } else if x < -threshold { 0 } else { 1 }
+        };
+        let last = bucket(obs.returns[0], 0.0005);
+        let trend = bucket(obs.returns.iter().sum(), 0.002);
+        let inventory = bucket(obs.inventory as f64, 20.0);
+        let wide = u32::from(obs.spread_bps > 20.0);
+        ((last * 3 + trend) * 3 + inventory) * 2 + wide
+    }
+
+    pub fn values(&self, state: u32) -> [f64; 3] {
+        self.q.get(&state).copied().unwrap_or([0.0; 3])
+    }
+
+    /// Epsilon-greedy choice.
+    pub fn choose(&self, state: u32, rng: &mut impl Rng) -> Action {
+        if rng.gen_bool(self.epsilon.clamp(0.0, 1.0)) {
+            return *Action::ALL.choose(rng).unwrap();
+        }
+        let values = self.values(state);
+        Action::ALL.into_iter()
+            .max_by(|a, b| values[a.index()].total_cmp(&values[b.index()]))
+            .unwrap_or(Action::Hold)
+    }
+
+    /// Q(s, a) += alpha * (r + gamma * max Q(s', .) - Q(s, a))
+    pub fn learn(&mut self, state: u32, action: Action, reward: f64, next_state: u32) {
+        let best_next = self.values(next_state).into_iter().fold(f64::MIN, f64::max);
+        let entry = self.q.entry(state).or_insert([0.0; 3]);
+        let current = entry[action.index()];
+        entry[action.index()] = current + self.learning_rate * (reward + self.discount * best_next - current);
+    }
+
+    /// One online step: credit the previous action with the change in equity
+    /// (in dollars) since it was taken, then pick the next action.
+    pub fn step(&mut self, obs: &Observation, equity: i64, rng: &mut impl Rng) -> Action {
+        let state = Self::encode(obs);
+        if let (Some((last_state, last_action)), Some(last_equity)) = (self.last, self.last_equity) {
+            let reward = (equity - last_equity) as f64 / 100.0;
+            self.total_reward += reward;
+            self.learn(last_state, last_action, reward, state);
+        }
+        let action = self.choose(state, rng);
+        self.last = Some((state, action));
+        self.last_equity = Some(equity);
+        action
+    }
+}
@@ -1 +1,6 @@
 pub mod admin;
+pub mod game;
+pub mod sessions;
+pub mod snapshots;
+pub mod surveillance;
+pub mod trading;
@@ -1,14 +1,22 @@
 use axum::{
-    extract::{State, Json},
+    extract::Json,
     response::{IntoResponse, Response},
     http::StatusCode,
-    routing::post,
+    routing::{get, post},
     Router,
 };
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use crate::types::{SimulationCommand, Order, OrderSide};
+use crate::types::{SimulationCommand, Order, OrderSide, TimeStatus};
+use crate::clock::Pacing;
+use crate::simulation::StopCondition;
+use crate::sessions::Sim;
 use crate::state::AppState;
+use crate::scripting;
+use crate::agents::Strategy;
+use crate::scheduling::ActivationProfile;
+use crate::scenario::Scenario;
+use crate::regime::{Regime, RegimeStatus};
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct InjectOrderRequest {
@@ -23,6 +31,28 @@ pub struct ControlRequest {
     pub action: String,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct ResetRequest {
+    /// Seed for the restarted run; omit to draw one from the current run
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct StepRequest {
+    /// Ticks to run; 1 if omitted
+    pub ticks: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PacingRequest {
+    /// `ticked`, `realtime` or `fast`, as for `--pacing`
+    pub mode: String,
+    /// Multiple of real time for `realtime`
+    pub speed: Option<f64>,
+    /// Wall-clock milliseconds per tick for `ticked`
+    pub tick_ms: Option<u64>,
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct EarningsRequest {
     pub surprise_pct: f64,
@@ -43,6 +73,51 @@ pub struct WhaleRequest {
     pub magnitude: f64,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct RumourRequest {
+    pub node: Option<u64>,
+    pub side: String,
+    pub strength: Option<f64>,
+    pub duration: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SpawnScriptRequest {
+    pub path: String,
+    pub count: Option<usize>,
+    pub cash: Option<u64>,
+    pub inventory: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReloadScriptsRequest {
+    pub path: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ActivationRequest {
+    /// Agent class (`Strategy::kind()`); omit to change the default profile
+    pub kind: Option<String>,
+    /// Poisson activations per tick; omit for exactly once per tick
+    pub rate: Option<f64>,
+    pub latency: Option<f64>,
+    pub jitter: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RegimeRequest {
+    /// `normal`, `calm`, `stressed` or `euphoric`
+    pub regime: Regime,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct VolatilityRequest {
+    /// Multiple of the regime's noise jitter, arrival rates and fundamental
+    /// volatility. The normal regime's fundamental doesn't move, so there it
+    /// scales only the jitter and arrival rates.
+    pub multiplier: f64,
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct AdminResponse {
     pub success: bool,
@@ -54,16 +129,28 @@ pub fn admin_routes() -> Router<Arc<AppState>> {
         .route("/api/admin/order", post(inject_order))
         .route("/api/admin/crash", post(trigger_crash))
         .route("/api/admin/control", post(control_simulation))
+        .route("/api/admin/reset", post(reset_simulation))
         .route("/api/admin/pump", post(pump_market))
         .route("/api/admin/dump", post(dump_market))
         .route("/api/admin/earnings", post(earnings_announcement))
         .route("/api/admin/tariffs", post(tariffs_announcement))
         .route("/api/admin/rugpull", post(rug_pull))
         .route("/api/admin/whale", post(whale_accumulation))
+        .route("/api/admin/rumour", post(seed_rumour))
+        .route("/api/admin/scripts", post(spawn_scripted))
+        .route("/api/admin/scripts/reload", post(reload_scripts))
+        .route("/api/admin/activation", post(set_activation))
+        .route("/api/admin/regime", get(query_regime).post(set_regime))
+        .route("/api/admin/volatility", post(update_volatility))
+        .route("/api/admin/scenario", post(load_scenario))
+        .route("/api/admin/time", get(query_time))
+        .route("/api/admin/time/step", post(step_ticks))
+        .route("/api/admin/time/pacing", post(set_pacing))
+        .route("/api/admin/time/until", post(run_until))
 }
 
 async fn inject_order(
-    State(state): State<Arc<AppState>>,
+    state: Sim,
     Json(req): Json<InjectOrderRequest>,
 ) -> Result<Json<AdminResponse>, AdminError> {
     let side = match req.side.to_lowercase().as_str() {
@@ -73,7 +160,7 @@ async fn inject_order(
     };
 
     let order = Order {
-        id: rand::random(),
+        id: 0, // assigned by the simulation from the run's seed
         trader_id: req.trader_id,
         side,
         price: req.price,
@@ -92,7 +179,7 @@ async fn inject_order(
 }
 
 async fn trigger_crash(
-    State(state): State<Arc<AppState>>,
+    state: Sim,
 ) -> Result<Json<AdminResponse>, AdminError> {
     let crash_quantity = 20000;
     let crash_price = 4000;
@@ -112,7 +199,7 @@ async fn trigger_crash(
 }
 
 async fn control_simulation(
-    State(state): State<Arc<AppState>>,
+    state: Sim,
     Json(req): Json<ControlRequest>,
 ) -> Result<Json<AdminResponse>, AdminError> {
     let action = req.action.to_lowercase();
@@ -131,8 +218,112 @@ async fn control_simulation(
     }))
 }
 
+async fn query_time(
+    state: Sim,
+) -> Result<Json<TimeStatus>, AdminError> {
+    let (reply, mut reply_rx) = tokio::sync::mpsc::unbounded_channel();
+    state.cmd_tx.send(SimulationCommand::QueryTime { reply })
+        .map_err(|_| AdminError::CommandChannelError)?;
+    let status = reply_rx.recv().await.ok_or(AdminError::CommandChannelError)?;
+    Ok(Json(status))
+}
+
+async fn step_ticks(
+    state: Sim,
+    body: Option<Json<StepRequest>>,
+) -> Result<Json<AdminResponse>, AdminError> {
+    let ticks = body.and_then(|Json(req)| req.ticks).unwrap_or(1);
+    if ticks == 0 || ticks > 10000 {
+        return Err(AdminError::InvalidRequest(
+            "ticks must be between 1 and 10000".to_string()
+        ));
+    }
+
+    state.cmd_tx.send(SimulationCommand::Step { ticks })
+        .map_err(|_| AdminError::CommandChannelError)?;
+
+    Ok(Json(AdminResponse {
+        success: true,
+        message: format!("⏭️ Stepping {} ticks (paused)", ticks),
+    }))
+}
+
+async fn set_pacing(
+    state: Sim,
+    Json(req): Json<PacingRequest>,
+) -> Result<Json<AdminResponse>, AdminError> {
+    let pacing = match req.mode.to_lowercase().as_str() {
+        "ticked" => Pacing::Ticked,
+        "realtime" => Pacing::RealTime { speed: req.speed.unwrap_or(1.0) },
+        "fast" => Pacing::AsFastAsPossible,
+        _ => return Err(AdminError::InvalidRequest("mode must be 'ticked', 'realtime' or 'fast'".to_string())),
+    };
+    if let Pacing::RealTime { speed } = pacing {
+        if speed <= 0.0 || speed > 1000.0 {
+            return Err(AdminError::InvalidRequest(
+                "speed must be between 0 and 1000".to_string()
+            ));
+        }
+    }
+    if req.tick_ms.is_some_and(|ms| ms == 0 || ms > 10000) {
+        return Err(AdminError::InvalidRequest(
+            "tick_ms must be between 1 and 10000".to_string()
+        ));
+    }
+
+    state.cmd_tx.send(SimulationCommand::SetPacing { pacing, tick_ms: req.tick_ms })
+        .map_err(|_| AdminError::CommandChannelError)?;
+
+    let message = match (pacing, req.tick_ms) {
+        (Pacing::Ticked, Some(ms)) => format!("⏱️ Ticked pacing, {} ms per tick", ms),
+        (Pacing::Ticked, None) => "⏱️ Ticked pacing".to_string(),
+        (Pacing::RealTime { speed }, _) => format!("⏱️ Real time x{}", speed),
+        (Pacing::AsFastAsPossible, _) => "⏩ Full speed".to_string(),
+    };
+    Ok(Json(AdminResponse { success: true, message }))
+}
+
+async fn run_until(
+    state: Sim,
+    Json(until): Json<StopCondition>,
+) -> Result<Json<AdminResponse>, AdminError> {
+    let message = match until {
+        StopCondition::Tick { tick: 0 } => return Err(AdminError::InvalidRequest("tick must be at least 1".to_string())),
+        StopCondition::PriceAbove { price: 0 } | StopCondition::PriceBelow { price: 0 } => {
+            return Err(AdminError::InvalidRequest("price must be at least 1".to_string()))
+        }
+        StopCondition::Tick { tick } => format!("▶️ Running until tick {}", tick),
+        StopCondition::PriceAbove { price } => format!("▶️ Running until the price reaches ${:.2}", price as f64 / 100.0),
+        StopCondition::PriceBelow { price } => format!("▶️ Running until the price falls to ${:.2}", price as f64 / 100.0),
+        StopCondition::Halt => "▶️ Running until the market halts".to_string(),
+    };
+
+    state.cmd_tx.send(SimulationCommand::RunUntil(until))
+        .map_err(|_| AdminError::CommandChannelError)?;
+
+    Ok(Json(AdminResponse { success: true, message }))
+}
+
+async fn reset_simulation(
+    state: Sim,
+    body: Option<Json<ResetRequest>>,
+) -> Result<Json<AdminResponse>, AdminError> {
+    let seed = body.and_then(|Json(req)| req.seed);
+
+    state.cmd_tx.send(SimulationCommand::Reset { seed })
+        .map_err(|_| AdminError::CommandChannelError)?;
+
+    Ok(Json(AdminResponse {
+        success: true,
+        message: match seed {
+            Some(seed) => format!("♻️ Simulation reset with seed {}", seed),
+            None => "♻️ Simulation reset".to_string(),
+        },
+    }))
+}
+
 async fn pump_market(
-    State(state): State<Arc<AppState>>,
+    state: Sim,
 ) -> Result<Json<AdminResponse>, AdminError> {
     state.cmd_tx.send(SimulationCommand::Pump {
         buyer_id: 888,
@@ -148,7 +339,7 @@ async fn pump_market(
 }
 
 async fn dump_market(
-    State(state): State<Arc<AppState>>,
+    state: Sim,
 ) -> Result<Json<AdminResponse>, AdminError> {
     state.cmd_tx.send(SimulationCommand::Dump {
         seller_id: 888,
@@ -164,7 +355,7 @@ async fn dump_market(
 }
 
 async fn earnings_announcement(
-    State(state): State<Arc<AppState>>,
+    state: Sim,
     Json(req): Json<EarningsRequest>,
 ) -> Result<Json<AdminResponse>, AdminError> {
     if req.surprise_pct < -50.0 || req.surprise_pct > 50.0 {
@@ -186,7 +377,7 @@ async fn earnings_announcement(
 }
 
 async fn tariffs_announcement(
-    State(state): State<Arc<AppState>>,
+    state: Sim,
     Json(req): Json<TariffsRequest>,
 ) -> Result<Json<AdminResponse>, AdminError> {
     if req.severity < 0.0 || req.severity > 10.0 {
@@ -207,7 +398,7 @@ async fn tariffs_announcement(
 }
 
 async fn rug_pull(
-    State(state): State<Arc<AppState>>,
+    state: Sim,
     Json(req): Json<RugPullRequest>,
 ) -> Result<Json<AdminResponse>, AdminError> {
     if req.magnitude < 0.5 || req.magnitude > 5.0 {
@@ -229,7 +420,7 @@ async fn rug_pull(
 }
 
 async fn whale_accumulation(
-    State(state): State<Arc<AppState>>,
+    state: Sim,
     Json(req): Json<WhaleRequest>,
 ) -> Result<Json<AdminResponse>, AdminError> {
     if req.magnitude < 0.5 || req.magnitude > 5.0 {
@@ -249,9 +440,204 @@ async fn whale_accumulation(
     }))
 }
 
+async fn seed_rumour(
+    state: Sim,
+    Json(req): Json<RumourRequest>,
+) -> Result<Json<AdminResponse>, AdminError> {
+    let side = match req.side.to_lowercase().as_str() {
+        "bid" => OrderSide::Bid,
+        "ask" => OrderSide::Ask,
+        _ => return Err(AdminError::InvalidRequest("side must be 'bid' or 'ask'".to_string())),
+    };
+    let strength = req.strength.unwrap_or(3.0);
+    if strength <= 0.0 || strength > 20.0 {
+        return Err(AdminError::InvalidRequest(
+            "strength must be between 0 and 20".to_string()
+        ));
+    }
+    let duration = req.duration.unwrap_or(20);
+    if duration == 0 {
+        return Err(AdminError::InvalidRequest("duration must be at least 1 tick".to_string()));
+    }
+
+    let (reply, mut reply_rx) = tokio::sync::mpsc::unbounded_channel();
+    state.cmd_tx.send(SimulationCommand::SeedRumour {
+        node: req.node,
+        side,
+        strength,
+        duration,
+        reply,
+    })
+    .map_err(|_| AdminError::CommandChannelError)?;
+
+    // An unknown or departed node, or an empty graph, leaves the market alone
+    let node = reply_rx.recv().await.ok_or(AdminError::CommandChannelError)?.map_err(AdminError::InvalidRequest)?;
+    Ok(Json(AdminResponse {
+        success: true,
+        message: format!("🗣️ RUMOUR ({}) seeded at agent {} for {} ticks", req.side, node, duration),
+    }))
+}
+
+async fn spawn_scripted(
+    state: Sim,
+    Json(req): Json<SpawnScriptRequest>,
+) -> Result<Json<AdminResponse>, AdminError> {
+    let count = req.count.unwrap_or(1);
+    if count == 0 || count > 100 {
+        return Err(AdminError::InvalidRequest(
+            "count must be between 1 and 100".to_string()
+        ));
+    }
+    // Compile here too so syntax errors come back in the response
+    check_script(req.path.clone()).await?;
+
+    state.cmd_tx.send(SimulationCommand::SpawnScripted {
+        path: req.path.clone(),
+        count,
+        cash: req.cash.unwrap_or(1000000),
+        inventory: req.inventory.unwrap_or(1000),
+    })
+    .map_err(|_| AdminError::CommandChannelError)?;
+
+    Ok(Json(AdminResponse {
+        success: true,
+        message: format!("📜 Spawned {} agents running {}", count, req.path),
+    }))
+}
+
+/// `scripting::check` off the async runtime: it reads and compiles the file.
+async fn check_script(path: String) -> Result<(), AdminError> {
+    tokio::task::spawn_blocking(move || scripting::check(&path))
+        .await
+        .map_err(|_| AdminError::CommandChannelError)?
+        .map_err(AdminError::InvalidRequest)
+}
+
+async fn reload_scripts(
+    state: Sim,
+    body: Option<Json<ReloadScriptsRequest>>,
+) -> Result<Json<AdminResponse>, AdminError> {
+    let path = body.and_then(|Json(req)| req.path);
+    if let Some(path) = &path {
+        check_script(path.clone()).await?;
+    }
+
+    state.cmd_tx.send(SimulationCommand::ReloadScripts { path: path.clone() })
+        .map_err(|_| AdminError::CommandChannelError)?;
+
+    Ok(Json(AdminResponse {
+        success: true,
+        message: format!("🔄 Reloading {}", path.as_deref().unwrap_or("all scripts")),
+    }))
+}
+
+async fn set_activation(
+    state: Sim,
+    Json(req): Json<ActivationRequest>,
+) -> Result<Json<AdminResponse>, AdminError> {
+    if let Some(kind) = &req.kind {
+        if !Strategy::KINDS.contains(&kind.as_str()) {
+            return Err(AdminError::InvalidRequest(format!(
+                "kind must be one of {}", Strategy::KINDS.join(", ")
+            )));
+        }
+    }
+    if req.rate.is_some_and(|r| !(0.0..=20.0).contains(&r)) {
+        return Err(AdminError::InvalidRequest("rate must be between 0 and 20".to_string()));
+    }
+    let profile = ActivationProfile {
+        rate: req.rate,
+        latency: req.latency.unwrap_or(0.0),
+        jitter: req.jitter.unwrap_or(0.0),
+    };
+    if !(0.0..=50.0).contains(&profile.latency) || !(0.0..=50.0).contains(&profile.jitter) {
+        return Err(AdminError::InvalidRequest("latency and jitter must be between 0 and 50 ticks".to_string()));
+    }
+
+    state.cmd_tx.send(SimulationCommand::SetActivation { kind: req.kind.clone(), profile })
+        .map_err(|_| AdminError::CommandChannelError)?;
+
+    Ok(Json(AdminResponse {
+        success: true,
+        message: format!("⏱️ {} now act {} with {:.2} ticks latency",
+            req.kind.as_deref().unwrap_or("Agents"),
+            req.rate.map(|r| format!("{:.2}x per tick", r)).unwrap_or_else(|| "once per tick".to_string()),
+            profile.latency),
+    }))
+}
+
+async fn query_regime(
+    state: Sim,
+) -> Result<Json<RegimeStatus>, AdminError> {
+    let (reply, mut reply_rx) = tokio::sync::mpsc::unbounded_channel();
+    state.cmd_tx.send(SimulationCommand::QueryRegime { reply })
+        .map_err(|_| AdminError::CommandChannelError)?;
+    let status = reply_rx.recv().await.ok_or(AdminError::CommandChannelError)?;
+    Ok(Json(status))
+}
+
+async fn set_regime(
+    state: Sim,
+    Json(req): Json<RegimeRequest>,
+) -> Result<Json<AdminResponse>, AdminError> {
+    state.cmd_tx.send(SimulationCommand::SetRegime(req.regime))
+        .map_err(|_| AdminError::CommandChannelError)?;
+
+    let message = match req.regime {
+        Regime::Normal => "🌤️ Normal market",
+        Regime::Calm => "😌 Calm market: quiet, tight and slow",
+        Regime::Stressed => "😱 Stressed market: wide, frantic and falling",
+        Regime::Euphoric => "🤑 Euphoric market: busy, herding and rising",
+    };
+    Ok(Json(AdminResponse { success: true, message: message.to_string() }))
+}
+
+async fn update_volatility(
+    state: Sim,
+    Json(req): Json<VolatilityRequest>,
+) -> Result<Json<AdminResponse>, AdminError> {
+    if !(0.1..=5.0).contains(&req.multiplier) {
+        return Err(AdminError::InvalidRequest(
+            "multiplier must be between 0.1 and 5".to_string()
+        ));
+    }
+
+    state.cmd_tx.send(SimulationCommand::UpdateVolatility(req.multiplier))
+        .map_err(|_| AdminError::CommandChannelError)?;
+
+    Ok(Json(AdminResponse {
+        success: true,
+        message: format!("🌪️ Volatility {:.1}x", req.multiplier),
+    }))
+}
+
+async fn load_scenario(
+    state: Sim,
+    Json(scenario): Json<Scenario>,
+) -> Result<Json<AdminResponse>, AdminError> {
+    let scenario = validate_scenario(scenario).await?;
+    let agents: usize = scenario.groups.iter().map(|g| g.count).sum();
+    let message = format!("🎬 Loading scenario {} ({} agents, {} shocks)", scenario.name, agents, scenario.shocks.len());
+
+    state.cmd_tx.send(SimulationCommand::LoadScenario(Box::new(scenario)))
+        .map_err(|_| AdminError::CommandChannelError)?;
+
+    Ok(Json(AdminResponse { success: true, message }))
+}
+
+/// `Scenario::validate` off the async runtime, since it compiles the
+/// scenario's scripts. Hands the scenario back if it is sound.
+pub async fn validate_scenario(scenario: Scenario) -> Result<Scenario, AdminError> {
+    tokio::task::spawn_blocking(move || scenario.validate().map(|()| scenario))
+        .await
+        .map_err(|_| AdminError::CommandChannelError)?
+        .map_err(|errors| AdminError::InvalidRequest(errors.join("; ")))
+}
+
 #[derive(Debug)]
 pub enum AdminError {
     InvalidRequest(String),
+    NotFound(String),
     CommandChannelError,
 }
 
@@ -259,6 +645,7 @@ impl IntoResponse for AdminError {
     fn into_response(self) -> Response {
         let (status, message) = match self {
             AdminError::InvalidRequest(msg) => (StatusCode::BAD_REQUEST, msg),
+            AdminError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
             AdminError::CommandChannelError => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Failed to send command to simulation (channel closed?)".to_string(),
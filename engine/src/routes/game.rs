@@ -0,0 +1,190 @@
+use axum::{
+    extract::{Json, Path},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use crate::game::{PlayerReport, Standing};
+use crate::sessions::{Sim, SimSession};
+use crate::state::AppState;
+use crate::types::{ExternalInstruction, Order, OrderSide, SimulationCommand};
+
+/// Header carrying the key handed out by `/api/game/join`.
+const PLAYER_KEY_HEADER: &str = "x-player-key";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct JoinRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JoinResponse {
+    pub player_id: u64,
+    /// Send back as `x-player-key` on every other game request
+    pub key: String,
+    pub standing: Standing,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlayerOrderRequest {
+    pub side: String,
+    pub price: u64,
+    pub quantity: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayerOrderResponse {
+    pub success: bool,
+    /// The order reaches the book on the player's next turn; fills and
+    /// rejections show up in the player's `events`
+    pub order_id: u64,
+}
+
+/// Path parameters are named, so the `:session` of a session's routes can sit alongside.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlayerPath {
+    pub player_id: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OrderPath {
+    pub player_id: u64,
+    pub order_id: u64,
+}
+
+pub fn game_routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/api/game/join", post(join_game))
+        .route("/api/game/players/:player_id", get(player_account))
+        .route("/api/game/players/:player_id/orders", post(submit_order))
+        .route("/api/game/players/:player_id/orders/:order_id", delete(cancel_order))
+}
+
+async fn join_game(
+    state: Sim,
+    Json(req): Json<JoinRequest>,
+) -> Result<Json<JoinResponse>, GameError> {
+    let name = req.name.trim().to_string();
+    if name.is_empty() || name.chars().count() > 32 {
+        return Err(GameError::InvalidRequest("name must be 1 to 32 characters".to_string()));
+    }
+
+    let (reply, mut reply_rx) = mpsc::unbounded_channel();
+    state.cmd_tx.send(SimulationCommand::JoinGame { name, reply })
+        .map_err(|_| GameError::CommandChannelError)?;
+    let standing = reply_rx.recv().await.ok_or(GameError::CommandChannelError)?;
+
+    let key = format!("{:016x}{:016x}", rand::random::<u64>(), rand::random::<u64>());
+    state.player_keys.write().unwrap().insert(standing.player_id, key.clone());
+
+    Ok(Json(JoinResponse {
+        player_id: standing.player_id,
+        key,
+        standing,
+    }))
+}
+
+async fn player_account(
+    state: Sim,
+    Path(PlayerPath { player_id }): Path<PlayerPath>,
+    headers: HeaderMap,
+) -> Result<Json<PlayerReport>, GameError> {
+    authorize(&state, player_id, &headers)?;
+
+    let (reply, mut reply_rx) = mpsc::unbounded_channel();
+    state.cmd_tx.send(SimulationCommand::QueryPlayer { player_id, reply })
+        .map_err(|_| GameError::CommandChannelError)?;
+    match reply_rx.recv().await {
+        Some(Some(report)) => Ok(Json(report)),
+        Some(None) => Err(GameError::NotFound),
+        None => Err(GameError::CommandChannelError),
+    }
+}
+
+async fn submit_order(
+    state: Sim,
+    Path(PlayerPath { player_id }): Path<PlayerPath>,
+    headers: HeaderMap,
+    Json(req): Json<PlayerOrderRequest>,
+) -> Result<Json<PlayerOrderResponse>, GameError> {
+    authorize(&state, player_id, &headers)?;
+
+    let side = match req.side.to_lowercase().as_str() {
+        "bid" | "buy" => OrderSide::Bid,
+        "ask" | "sell" => OrderSide::Ask,
+        _ => return Err(GameError::InvalidRequest("side must be 'bid' or 'ask'".to_string())),
+    };
+    if req.price == 0 || req.quantity == 0 {
+        return Err(GameError::InvalidRequest("price and quantity must be positive".to_string()));
+    }
+
+    let order = Order {
+        // 53 bits, so the id survives a round trip through a JavaScript number
+        id: rand::random::<u64>() >> 11,
+        trader_id: player_id,
+        side,
+        price: req.price,
+        amount: req.quantity,
+        timestamp: 0,
+    };
+    state.cmd_tx.send(SimulationCommand::SessionInstruction {
+        trader_id: player_id,
+        instruction: ExternalInstruction::Submit(order),
+    })
+    .map_err(|_| GameError::CommandChannelError)?;
+
+    Ok(Json(PlayerOrderResponse { success: true, order_id: order.id }))
+}
+
+async fn cancel_order(
+    state: Sim,
+    Path(OrderPath { player_id, order_id }): Path<OrderPath>,
+    headers: HeaderMap,
+) -> Result<Json<PlayerOrderResponse>, GameError> {
+    authorize(&state, player_id, &headers)?;
+
+    state.cmd_tx.send(SimulationCommand::SessionInstruction {
+        trader_id: player_id,
+        instruction: ExternalInstruction::Cancel { order_id },
+    })
+    .map_err(|_| GameError::CommandChannelError)?;
+
+    Ok(Json(PlayerOrderResponse { success: true, order_id }))
+}
+
+fn authorize(state: &SimSession, player_id: u64, headers: &HeaderMap) -> Result<(), GameError> {
+    let key = headers.get(PLAYER_KEY_HEADER).and_then(|v| v.to_str().ok());
+    match (state.player_keys.read().unwrap().get(&player_id), key) {
+        (None, _) => Err(GameError::NotFound),
+        (Some(expected), Some(key)) if expected == key => Ok(()),
+        _ => Err(GameError::Unauthorized),
+    }
+}
+
+#[derive(Debug)]
+pub enum GameError {
+    InvalidRequest(String),
+    Unauthorized,
+    NotFound,
+    CommandChannelError,
+}
+
+impl IntoResponse for GameError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            GameError::InvalidRequest(msg) => (StatusCode::BAD_REQUEST, msg),
+            GameError::Unauthorized => (StatusCode::UNAUTHORIZED, format!("missing or wrong {} header", PLAYER_KEY_HEADER)),
+            GameError::NotFound => (StatusCode::NOT_FOUND, "no such player".to_string()),
+            GameError::CommandChannelError => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to send command to simulation (channel closed?)".to_string(),
+            ),
+        };
+
+        (status, Json(serde_json::json!({ "success": false, "message": message }))).into_response()
+    }
+}
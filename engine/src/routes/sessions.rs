@@ -0,0 +1,131 @@
+use axum::{
+    extract::{Json, State},
+    routing::get,
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use crate::clock::Pacing;
+use crate::journal::Entry;
+use crate::routes::admin::{validate_scenario, AdminError, AdminResponse};
+use crate::routes::snapshots;
+use crate::scenario::Scenario;
+use crate::sessions::{launch, valid_name, Sim, SimSession, SESSION_DIR};
+use crate::state::AppState;
+use crate::types::{SimulationCommand, TimeStatus};
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreateSessionRequest {
+    /// Letters, digits, '-' and '_'; the first free `s<n>` if omitted
+    pub id: Option<String>,
+    /// The built-in scenario if omitted
+    pub scenario: Option<Scenario>,
+    /// Random if omitted
+    pub seed: Option<u64>,
+    /// Start from this saved snapshot instead of a scenario
+    pub snapshot: Option<String>,
+    /// Start from the live state of this session instead of a scenario
+    pub fork: Option<String>,
+    /// The server's `--pacing` if omitted
+    pub pacing: Option<Pacing>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionInfo {
+    pub id: String,
+    #[serde(flatten)]
+    pub time: TimeStatus,
+}
+
+pub fn session_routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/api/sessions", get(list_sessions).post(create_session))
+        .route("/api/sessions/:session", get(session_info).delete(destroy_session))
+}
+
+/// Where the session is in time, or `None` if its thread has stopped.
+async fn info(session: &SimSession) -> Option<SessionInfo> {
+    let (reply, mut reply_rx) = mpsc::unbounded_channel();
+    session.cmd_tx.send(SimulationCommand::QueryTime { reply }).ok()?;
+    let time = reply_rx.recv().await?;
+    Some(SessionInfo { id: session.id.clone(), time })
+}
+
+async fn list_sessions(State(state): State<Arc<AppState>>) -> Json<Vec<SessionInfo>> {
+    let mut sessions = Vec::new();
+    for id in state.sessions.ids() {
+        if let Some(session) = state.sessions.get(&id) {
+            sessions.extend(info(&session).await);
+        }
+    }
+    Json(sessions)
+}
+
+async fn session_info(Sim(session): Sim) -> Result<Json<SessionInfo>, AdminError> {
+    info(&session).await.map(Json).ok_or(AdminError::CommandChannelError)
+}
+
+async fn create_session(
+    State(state): State<Arc<AppState>>,
+    Json(req): Json<CreateSessionRequest>,
+) -> Result<Json<SessionInfo>, AdminError> {
+    let id = match req.id {
+        Some(id) => id,
+        None => (1..).map(|n| format!("s{}", n)).find(|id| !state.sessions.taken(id)).unwrap_or_default(),
+    };
+    if !valid_name(&id) {
+        return Err(AdminError::InvalidRequest("id must be 1-64 letters, digits, '-' or '_'".to_string()));
+    }
+    // Held from here, so nothing else can start under this id and touch its directory
+    let reservation = state.sessions.reserve(&id).map_err(AdminError::InvalidRequest)?;
+    if let Some(Pacing::RealTime { speed }) = req.pacing {
+        if speed <= 0.0 || speed > 1000.0 {
+            return Err(AdminError::InvalidRequest("speed must be above 0 and at most 1000".to_string()));
+        }
+    }
+
+    let from_snapshot = req.snapshot.is_some() || req.fork.is_some();
+    if from_snapshot && (req.scenario.is_some() || req.seed.is_some()) {
+        return Err(AdminError::InvalidRequest("a snapshot brings its own scenario and seed".to_string()));
+    }
+    let start = match (req.snapshot, req.fork) {
+        (Some(_), Some(_)) => {
+            return Err(AdminError::InvalidRequest("start from a snapshot or a fork, not both".to_string()));
+        }
+        (Some(name), None) => Entry::Restore { snapshot: snapshots::read(&name).await? },
+        (None, Some(from)) => {
+            let from = state.sessions.get(&from).ok_or_else(|| AdminError::NotFound(format!("no session {}", from)))?;
+            Entry::Restore { snapshot: snapshots::live(&from).await? }
+        }
+        (None, None) => {
+            let scenario = validate_scenario(req.scenario.unwrap_or_default()).await?;
+            Entry::Start { scenario, seed: req.seed.unwrap_or_else(rand::random) }
+        }
+    };
+
+    let dir = PathBuf::from(SESSION_DIR).join(&id);
+    let pacing = req.pacing.unwrap_or(state.sessions.pacing);
+    let session = tokio::task::spawn_blocking(move || {
+        std::fs::create_dir_all(&dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+        launch(&id, &start, &dir, pacing)
+    })
+    .await
+    .map_err(|_| AdminError::CommandChannelError)?
+    .map_err(AdminError::InvalidRequest)?;
+
+    reservation.fill(session.clone());
+    info(&session).await.map(Json).ok_or(AdminError::CommandChannelError)
+}
+
+async fn destroy_session(
+    State(state): State<Arc<AppState>>,
+    Sim(session): Sim,
+) -> Result<Json<AdminResponse>, AdminError> {
+    state.sessions.destroy(&session.id).map_err(AdminError::InvalidRequest)?;
+    Ok(Json(AdminResponse {
+        success: true,
+        message: format!("🗑️ Session {} stopped", session.id),
+    }))
+}
@@ -0,0 +1,188 @@
+use axum::{
+    extract::{Json, Path},
+    routing::{get, post},
+    Router,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use crate::batch::RunSummary;
+use crate::console;
+use crate::routes::admin::{AdminError, AdminResponse};
+use crate::scenario::Shock;
+use crate::sessions::{valid_name, Sim, SimSession};
+use crate::simulation::Simulation;
+use crate::state::AppState;
+use crate::types::SimulationCommand;
+
+/// Where named snapshots are kept, relative to the working directory.
+pub const SNAPSHOT_DIR: &str = "snapshots";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SaveRequest {
+    pub name: String,
+}
+
+/// Path parameters are named, so the `:session` of a session's routes can sit alongside.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SnapshotPath {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ForkRequest {
+    /// How far to run both branches; 500 if omitted
+    pub ticks: Option<u64>,
+    /// What happens in the branch, straight away
+    pub shock: Shock,
+}
+
+/// The same market run on with and without a shock, statistics from the fork on.
+#[derive(Debug, Clone, Serialize)]
+pub struct ForkReport {
+    pub from_tick: u64,
+    pub ticks: u64,
+    pub shock: Shock,
+    pub baseline: RunSummary,
+    pub branch: RunSummary,
+}
+
+pub fn snapshot_routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/api/admin/snapshots", get(list_snapshots).post(save_snapshot))
+        .route("/api/admin/snapshots/:name/load", post(load_snapshot))
+        .route("/api/admin/snapshots/:name/fork", post(fork_snapshot))
+        .route("/api/admin/fork", post(fork_live))
+}
+
+/// Snapshot names become file names, so keep them plain.
+fn path(name: &str) -> Result<std::path::PathBuf, AdminError> {
+    if !valid_name(name) {
+        return Err(AdminError::InvalidRequest(
+            "name must be 1-64 letters, digits, '-' or '_'".to_string()
+        ));
+    }
+    Ok(std::path::Path::new(SNAPSHOT_DIR).join(format!("{}.json", name)))
+}
+
+pub(crate) async fn read(name: &str) -> Result<String, AdminError> {
+    tokio::fs::read_to_string(path(name)?).await
+        .map_err(|_| AdminError::InvalidRequest(format!("no snapshot named {}", name)))
+}
+
+/// The live market's snapshot, taken between events.
+pub(crate) async fn live(state: &SimSession) -> Result<String, AdminError> {
+    let (reply, mut reply_rx) = mpsc::unbounded_channel();
+    state.cmd_tx.send(SimulationCommand::SaveSnapshot { reply })
+        .map_err(|_| AdminError::CommandChannelError)?;
+    reply_rx.recv().await
+        .ok_or(AdminError::CommandChannelError)?
+        .map_err(|e| AdminError::InvalidRequest(format!("could not snapshot the market: {}", e)))
+}
+
+async fn list_snapshots() -> Json<Vec<String>> {
+    let mut names = Vec::new();
+    if let Ok(mut entries) = tokio::fs::read_dir(SNAPSHOT_DIR).await {
+        while let Ok(Some(entry)) = entries.next_entry().await {
+            let path = entry.path();
+            if path.extension().is_some_and(|e| e == "json") {
+                if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                    names.push(stem.to_string());
+                }
+            }
+        }
+    }
+    names.sort();
+    Json(names)
+}
+
+async fn save_snapshot(
+    state: Sim,
+    Json(req): Json<SaveRequest>,
+) -> Result<Json<AdminResponse>, AdminError> {
+    let path = path(&req.name)?;
+    let snapshot = live(&state).await?;
+    let written = match tokio::fs::create_dir_all(SNAPSHOT_DIR).await {
+        Ok(()) => tokio::fs::write(&path, &snapshot).await,
+        Err(e) => Err(e),
+    };
+    written.map_err(|e| AdminError::InvalidRequest(format!("could not write {}: {}", path.display(), e)))?;
+
+    Ok(Json(AdminResponse {
+        success: true,
+        message: format!("📸 Saved snapshot {} ({} KB)", req.name, snapshot.len() / 1024),
+    }))
+}
+
+async fn load_snapshot(
+    state: Sim,
+    Path(SnapshotPath { name }): Path<SnapshotPath>,
+) -> Result<Json<AdminResponse>, AdminError> {
+    let snapshot = read(&name).await?;
+    // Restore once here so a bad file is reported instead of ignored
+    let check = snapshot.clone();
+    let tick = tokio::task::spawn_blocking(move || Simulation::restore(&check).map(|sim| sim.tick()))
+        .await
+        .map_err(|_| AdminError::CommandChannelError)?
+        .map_err(|e| AdminError::InvalidRequest(format!("{}: {}", name, e)))?;
+
+    state.cmd_tx.send(SimulationCommand::LoadSnapshot(snapshot))
+        .map_err(|_| AdminError::CommandChannelError)?;
+
+    Ok(Json(AdminResponse {
+        success: true,
+        message: format!("⏪ Loading snapshot {} at tick {}", name, tick),
+    }))
+}
+
+async fn fork_snapshot(
+    Path(SnapshotPath { name }): Path<SnapshotPath>,
+    Json(req): Json<ForkRequest>,
+) -> Result<Json<ForkReport>, AdminError> {
+    let snapshot = read(&name).await?;
+    fork(snapshot, req).await
+}
+
+async fn fork_live(
+    state: Sim,
+    Json(req): Json<ForkRequest>,
+) -> Result<Json<ForkReport>, AdminError> {
+    let snapshot = live(&state).await?;
+    fork(snapshot, req).await
+}
+
+/// Run the snapshot on twice, off to the side of the live market: once as is
+/// and once with the shock.
+async fn fork(snapshot: String, req: ForkRequest) -> Result<Json<ForkReport>, AdminError> {
+    let ticks = req.ticks.unwrap_or(500);
+    if ticks == 0 || ticks > 20000 {
+        return Err(AdminError::InvalidRequest(
+            "ticks must be between 1 and 20000".to_string()
+        ));
+    }
+    if let Some(problem) = req.shock.check() {
+        return Err(AdminError::InvalidRequest(problem.to_string()));
+    }
+
+    let shock = req.shock;
+    let report = tokio::task::spawn_blocking(move || -> Result<ForkReport, String> {
+        let mut baseline = Simulation::restore(&snapshot)?;
+        let mut branch = baseline.fork()?;
+        let from_tick = baseline.tick();
+        console::set_thread_quiet(true);
+        branch.apply(shock.command());
+        for sim in [&mut baseline, &mut branch] {
+            sim.clear_stats();
+            for _ in 0..ticks {
+                sim.step();
+            }
+        }
+        console::set_thread_quiet(false);
+        Ok(ForkReport { from_tick, ticks, shock, baseline: baseline.summary(), branch: branch.summary() })
+    })
+    .await
+    .map_err(|_| AdminError::CommandChannelError)?
+    .map_err(AdminError::InvalidRequest)?;
+
+    Ok(Json(report))
+}
@@ -0,0 +1,59 @@
+use axum::{
+    extract::{Json, Query},
+    routing::{get, post},
+    Router,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use crate::routes::admin::{AdminError, AdminResponse};
+use crate::sessions::Sim;
+use crate::state::AppState;
+use crate::surveillance::{Alert, AlertKind};
+use crate::types::SimulationCommand;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AlertsQuery {
+    /// Only alerts with a higher id than this, for polling
+    pub since: Option<u64>,
+    pub kind: Option<AlertKind>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct LinkRequest {
+    pub trader_ids: Vec<u64>,
+}
+
+pub fn surveillance_routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/api/surveillance/alerts", get(list_alerts))
+        .route("/api/surveillance/links", post(link_accounts))
+}
+
+async fn list_alerts(
+    state: Sim,
+    Query(query): Query<AlertsQuery>,
+) -> Result<Json<Vec<Alert>>, AdminError> {
+    let (reply, mut reply_rx) = mpsc::unbounded_channel();
+    state.cmd_tx.send(SimulationCommand::QueryAlerts { since: query.since, kind: query.kind, reply })
+        .map_err(|_| AdminError::CommandChannelError)?;
+    let alerts = reply_rx.recv().await.ok_or(AdminError::CommandChannelError)?;
+    Ok(Json(alerts))
+}
+
+async fn link_accounts(
+    state: Sim,
+    Json(req): Json<LinkRequest>,
+) -> Result<Json<AdminResponse>, AdminError> {
+    if req.trader_ids.len() < 2 {
+        return Err(AdminError::InvalidRequest("link at least two trader ids".to_string()));
+    }
+
+    state.cmd_tx.send(SimulationCommand::LinkAccounts { trader_ids: req.trader_ids.clone() })
+        .map_err(|_| AdminError::CommandChannelError)?;
+
+    Ok(Json(AdminResponse {
+        success: true,
+        message: format!("🔗 Linked accounts {:?}", req.trader_ids),
+    }))
+}
@@ -0,0 +1,162 @@
+use axum::{
+    extract::ws::{Message, WebSocket, WebSocketUpgrade},
+    extract::State,
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use crate::sessions::{Sim, SimSession};
+use crate::state::AppState;
+use crate::types::{ClientMessage, ExternalInstruction, Order, SessionEvent, SimulationCommand};
+
+/// Starting balances for an external agent.
+const SESSION_CASH: u64 = 1000000;
+const SESSION_INVENTORY: u64 = 1000;
+
+pub fn trading_routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/ws/trade", get(trade_ws_handler))
+}
+
+async fn trade_ws_handler(
+    ws: WebSocketUpgrade,
+    State(state): State<Arc<AppState>>,
+    Sim(sim): Sim,
+) -> impl IntoResponse {
+    ws.on_upgrade(|socket| trading_session(socket, state, sim))
+}
+
+/// One external agent: login, then market data and private events out,
+/// orders and cancels in. Orders wait in the agent's inbox until its turn in
+/// the tick, like any internal agent's decision.
+async fn trading_session(mut socket: WebSocket, state: Arc<AppState>, sim: Arc<SimSession>) {
+    let name = match login(&mut socket, &state).await {
+        Ok(name) => name,
+        Err(message) => {
+            send_event(&mut socket, &SessionEvent::Error { message }).await;
+            return;
+        }
+    };
+
+    let (reply_tx, mut reply_rx) = mpsc::unbounded_channel();
+    let register = SimulationCommand::RegisterSession {
+        name,
+        cash: SESSION_CASH,
+        inventory: SESSION_INVENTORY,
+        reply: reply_tx,
+    };
+    if sim.cmd_tx.send(register).is_err() {
+        send_event(&mut socket, &SessionEvent::Error { message: "simulation is not running".to_string() }).await;
+        return;
+    }
+    let trader_id = match reply_rx.recv().await {
+        Some(event @ SessionEvent::Registered { agent_id, .. }) => {
+            if !send_event(&mut socket, &event).await {
+                let _ = sim.cmd_tx.send(SimulationCommand::CloseSession { trader_id: agent_id });
+                return;
+            }
+            agent_id
+        }
+        _ => return,
+    };
+
+    let mut market_rx = sim.tx.subscribe();
+    loop {
+        tokio::select! {
+            market = market_rx.recv() => match market {
+                Ok(msg) => {
+                    let Ok(json) = serde_json::to_string(&msg) else { continue };
+                    if socket.send(Message::Text(json)).await.is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            event = reply_rx.recv() => match event {
+                Some(event) => {
+                    if !send_event(&mut socket, &event).await {
+                        break;
+                    }
+                }
+                None => break,
+            },
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Text(text))) => {
+                    let reply = handle_client_message(&text, trader_id, &sim);
+                    if !send_event(&mut socket, &reply).await {
+                        break;
+                    }
+                }
+                Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+
+    let _ = sim.cmd_tx.send(SimulationCommand::CloseSession { trader_id });
+}
+
+/// Wait for a `login` message with a known token. Returns the agent name.
+async fn login(socket: &mut WebSocket, state: &AppState) -> Result<String, String> {
+    let first = tokio::time::timeout(Duration::from_secs(10), socket.recv()).await
+        .map_err(|_| "login timed out".to_string())?;
+    let Some(Ok(Message::Text(text))) = first else {
+        return Err("expected a login message".to_string());
+    };
+    match serde_json::from_str::<ClientMessage>(&text) {
+        Ok(ClientMessage::Login { token, name }) => {
+            if state.trading_tokens.contains(&token) {
+                Ok(name)
+            } else {
+                Err("invalid token".to_string())
+            }
+        }
+        Ok(_) => Err("first message must be login".to_string()),
+        Err(e) => Err(format!("bad message: {}", e)),
+    }
+}
+
+/// Forward an order or cancel to the sim. The immediate answer is only a
+/// receipt; acceptance, fills and cancels follow as the sim gets to them.
+fn handle_client_message(text: &str, trader_id: u64, sim: &SimSession) -> SessionEvent {
+    let (instruction, receipt) = match serde_json::from_str::<ClientMessage>(text) {
+        Ok(ClientMessage::Submit { side, price, quantity, client_order_id }) => {
+            if price == 0 || quantity == 0 {
+                return SessionEvent::Error { message: "price and quantity must be positive".to_string() };
+            }
+            let order = Order {
+                id: rand::random(),
+                trader_id,
+                side,
+                price,
+                amount: quantity,
+                timestamp: 0,
+            };
+            (ExternalInstruction::Submit(order), SessionEvent::Received { order_id: order.id, client_order_id })
+        }
+        Ok(ClientMessage::Cancel { order_id }) => {
+            (ExternalInstruction::Cancel { order_id }, SessionEvent::Received { order_id, client_order_id: None })
+        }
+        Ok(ClientMessage::Login { .. }) => {
+            return SessionEvent::Error { message: "already logged in".to_string() };
+        }
+        Err(e) => return SessionEvent::Error { message: format!("bad message: {}", e) },
+    };
+
+    match sim.cmd_tx.send(SimulationCommand::SessionInstruction { trader_id, instruction }) {
+        Ok(()) => receipt,
+        Err(_) => SessionEvent::Error { message: "simulation is not running".to_string() },
+    }
+}
+
+/// Returns false if the socket is gone.
+async fn send_event(socket: &mut WebSocket, event: &SessionEvent) -> bool {
+    match serde_json::to_string(event) {
+        Ok(json) => socket.send(Message::Text(json)).await.is_ok(),
+        Err(_) => true,
+    }
+}
@@ -0,0 +1,666 @@
+//! Scenario files: the instrument, the agent population, starting balances,
+//! tick rate and a schedule of shocks, in one JSON document.
+//!
+//! Any numeric strategy parameter, and each group's cash and inventory, can be
+//! a fixed number or a distribution sampled per agent:
+//!
+//! ```json
+//! { "window_size": 5 }
+//! { "window_size": { "uniform": [3, 12] } }
+//! { "spread_bps": { "normal": { "mean": 50, "std": 10 } } }
+//! ```
+//!
+//! Samples are drawn from the run seed and clamped to the parameter's valid
+//! range, so a scenario and a seed always give the same population.
+//! `Scenario::default()` is the built-in population.
+
+use crate::agents::{Agent, Strategy};
+use crate::behaviour::BehaviouralTraits;
+use crate::clock::TICKED_WALL_MS;
+use crate::manipulation::{IgnitionConfig, SpoofConfig};
+use crate::margin::{MarginAccount, MarginConfig};
+use crate::population::{GeneticConfig, PopulationConfig};
+use crate::scripting::{self, ScriptHost};
+use crate::types::{OrderSide, SimRng, SimulationCommand};
+use rand::prelude::*;
+use serde::de::value::MapAccessDeserializer;
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
+use tokio::sync::mpsc;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    #[serde(default = "default_name")]
+    pub name: String,
+    /// The engine has one order book, so a scenario names exactly one instrument
+    #[serde(default, deserialize_with = "one_instrument")]
+    pub instrument: Instrument,
+    /// Wall-clock length of a tick under the default ticked pacing
+    #[serde(default = "default_tick_ms")]
+    pub tick_ms: u64,
+    /// Balances for groups that don't set their own, and for new entrants
+    #[serde(default)]
+    pub defaults: Balances,
+    /// Exits, entry and selection between generations
+    #[serde(default)]
+    pub population: Population,
+    pub groups: Vec<AgentGroup>,
+    #[serde(default)]
+    pub shocks: Vec<ScheduledShock>,
+}
+
+fn default_name() -> String {
+    "unnamed".to_string()
+}
+
+fn default_tick_ms() -> u64 {
+    TICKED_WALL_MS
+}
+
+/// `instrument` as one object. A list fails with the reason rather than
+/// serde's "invalid type", so a multi-instrument file can't half load.
+fn one_instrument<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Instrument, D::Error> {
+    struct One;
+
+    impl<'de> Visitor<'de> for One {
+        type Value = Instrument;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a single instrument object")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Instrument, A::Error> {
+            Instrument::deserialize(MapAccessDeserializer::new(map))
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, _: A) -> Result<Instrument, A::Error> {
+            Err(de::Error::custom("instrument: the engine trades a single instrument; give one object, not a list"))
+        }
+    }
+
+    deserializer.deserialize_any(One)
+}
+
+/// The one book the engine trades.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Instrument {
+    pub symbol: String,
+    /// Opening price, in cents
+    pub initial_price: u64,
+}
+
+impl Default for Instrument {
+    fn default() -> Self {
+        Self { symbol: "SIM".to_string(), initial_price: 10000 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Balances {
+    pub cash: u64,
+    pub inventory: u64,
+}
+
+impl Default for Balances {
+    fn default() -> Self {
+        Self { cash: 1000000, inventory: 1000 }
+    }
+}
+
+/// How the population changes while the market runs. Entrants get the
+/// scenario's `defaults` balances.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Population {
+    /// An entrant every this many ticks (0 = never)
+    pub entry_interval: u64,
+    /// Agents at or below this equity (cents) are removed
+    pub bankruptcy_equity: i64,
+    /// Evolve strategy parameters at each generation boundary; off unless set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub genetic: Option<GeneticConfig>,
+}
+
+impl Default for Population {
+    fn default() -> Self {
+        let config = PopulationConfig::default();
+        Self { entry_interval: config.entry_interval, bankruptcy_equity: config.bankruptcy_equity, genetic: None }
+    }
+}
+
+/// A number, or a distribution to draw one from for each agent.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Param {
+    Fixed(f64),
+    Uniform { uniform: [f64; 2] },
+    Normal { normal: Normal },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Normal {
+    pub mean: f64,
+    pub std: f64,
+}
+
+impl From<f64> for Param {
+    fn from(value: f64) -> Self {
+        Param::Fixed(value)
+    }
+}
+
+/// `count` agents named `<name>_0`, `<name>_1`, ... running the same strategy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AgentGroup {
+    pub name: String,
+    pub count: usize,
+    pub strategy: StrategySpec,
+    /// Overrides `defaults.cash`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cash: Option<Param>,
+    /// Overrides `defaults.inventory`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inventory: Option<Param>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub traits: Option<TraitSpec>,
+    /// Trade through a margin account on these terms
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub margin: Option<MarginConfig>,
+}
+
+/// Behavioural biases for every `every`-th agent of a group, starting with the first.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TraitSpec {
+    pub preset: TraitPreset,
+    #[serde(default = "one")]
+    pub every: usize,
+}
+
+fn one() -> usize {
+    1
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraitPreset {
+    ProspectTheory,
+    Overconfident,
+}
+
+impl TraitPreset {
+    fn traits(self) -> BehaviouralTraits {
+        match self {
+            TraitPreset::ProspectTheory => BehaviouralTraits::prospect_theory(),
+            TraitPreset::Overconfident => BehaviouralTraits::overconfident(),
+        }
+    }
+}
+
+/// `Strategy` with `Param`s in place of fixed values. `External` agents come
+/// from trading sessions and games, not scenarios.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", deny_unknown_fields)]
+pub enum StrategySpec {
+    RandomWalker,
+    TrendFollower { window_size: Param },
+    MeanReverter { window_size: Param, std_dev_multiplier: Param },
+    MarketMaker { spread_bps: Param },
+    BeliefSwitcher {
+        intensity_of_choice: Param,
+        /// Defaults to the instrument's initial price
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        fundamental_price: Option<Param>,
+        trend_strength: Param,
+        reversion_strength: Param,
+        memory: Param,
+    },
+    Herder { imitation_probability: Param },
+    AvellanedaStoikov {
+        risk_aversion: Param,
+        horizon: Param,
+        arrival_at_touch: Param,
+        quote_size: Param,
+        inventory_limit: Param,
+        /// Defaults to the agent's starting inventory
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        target_inventory: Option<Param>,
+    },
+    QLearner { epsilon: Param, learning_rate: Param, discount: Param },
+    Scripted { path: String },
+    Spoofer(SpoofConfig),
+    MomentumIgniter(IgnitionConfig),
+}
+
+/// A God Mode event fired at the end of `tick`, before that tick's trades settle.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct ScheduledShock {
+    pub tick: u64,
+    #[serde(flatten)]
+    pub shock: Shock,
+}
+
+/// The God Mode shocks, with the same defaults and limits as the admin API.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Shock {
+    FlashCrash {
+        #[serde(default = "crash_quantity")]
+        quantity: u64,
+        #[serde(default = "crash_price")]
+        price: u64,
+    },
+    Pump {
+        #[serde(default = "unit")]
+        magnitude: f64,
+    },
+    Dump {
+        #[serde(default = "unit")]
+        magnitude: f64,
+    },
+    Earnings { surprise_pct: f64 },
+    Tariffs { severity: f64 },
+    RugPull { magnitude: f64 },
+    Whale { magnitude: f64 },
+    Rumour {
+        #[serde(default)]
+        node: Option<u64>,
+        side: OrderSide,
+        #[serde(default = "rumour_strength")]
+        strength: f64,
+        #[serde(default = "rumour_duration")]
+        duration: u64,
+    },
+}
+
+fn crash_quantity() -> u64 {
+    20000
+}
+
+fn crash_price() -> u64 {
+    4000
+}
+
+fn unit() -> f64 {
+    1.0
+}
+
+fn rumour_strength() -> f64 {
+    3.0
+}
+
+fn rumour_duration() -> u64 {
+    20
+}
+
+impl Shock {
+    /// Out-of-range values, as the matching admin endpoint would report them.
+    pub fn check(&self) -> Option<&'static str> {
+        match *self {
+            Shock::FlashCrash { quantity, price } if quantity == 0 || price == 0 => {
+                Some("quantity and price must be positive")
+            }
+            Shock::Pump { magnitude } | Shock::Dump { magnitude } if !(0.0..=10.0).contains(&magnitude) => {
+                Some("magnitude must be between 0 and 10")
+            }
+            Shock::Earnings { surprise_pct } if !(-50.0..=50.0).contains(&surprise_pct) => {
+                Some("surprise_pct must be between -50% and +50%")
+            }
+            Shock::Tariffs { severity } if !(0.0..=10.0).contains(&severity) => {
+                Some("severity must be between 0 and 10")
+            }
+            Shock::RugPull { magnitude } | Shock::Whale { magnitude } if !(0.5..=5.0).contains(&magnitude) => {
+                Some("magnitude must be between 0.5 and 5.0")
+            }
+            Shock::Rumour { strength, .. } if strength <= 0.0 || strength > 20.0 => {
+                Some("strength must be between 0 and 20")
+            }
+            Shock::Rumour { duration: 0, .. } => Some("duration must be at least 1 tick"),
+            _ => None,
+        }
+    }
+
+    /// The command the admin API sends for this shock.
+    pub fn command(&self) -> SimulationCommand {
+        match *self {
+            Shock::FlashCrash { quantity, price } => SimulationCommand::FlashCrash { seller_id: 999, quantity, price },
+            Shock::Pump { magnitude } => SimulationCommand::Pump { buyer_id: 888, base_price: 0, magnitude },
+            Shock::Dump { magnitude } => SimulationCommand::Dump { seller_id: 888, base_price: 0, magnitude },
+            Shock::Earnings { surprise_pct } => SimulationCommand::Earnings { surprise_pct },
+            Shock::Tariffs { severity } => SimulationCommand::Tariffs { severity },
+            Shock::RugPull { magnitude } => SimulationCommand::RugPull { magnitude },
+            Shock::Whale { magnitude } => SimulationCommand::WhaleAccumulation { magnitude },
+            Shock::Rumour { node, side, strength, duration } => SimulationCommand::SeedRumour {
+                node,
+                side,
+                strength,
+                duration,
+                reply: mpsc::unbounded_channel().0,
+            },
+        }
+    }
+}
+
+/// Resolves `Param`s into values. Checking records every out-of-range value
+/// under its path; drawing samples and clamps. Both walk the same code, so the
+/// limits live in one place.
+struct Draw<'a> {
+    rng: Option<&'a mut SimRng>,
+    path: String,
+    errors: Vec<String>,
+}
+
+impl Draw<'_> {
+    fn num(&mut self, field: &str, param: &Param, min: f64, max: f64) -> f64 {
+        let (lo, hi, centre) = match *param {
+            Param::Fixed(value) => (value, value, value),
+            Param::Uniform { uniform: [lo, hi] } => {
+                if lo > hi {
+                    self.error(field, format!("uniform range [{}, {}] is empty", lo, hi));
+                }
+                (lo, hi, (lo + hi) / 2.0)
+            }
+            Param::Normal { normal } => {
+                if normal.std.is_nan() || normal.std < 0.0 {
+                    self.error(field, "std must not be negative".to_string());
+                }
+                (normal.mean, normal.mean, normal.mean)
+            }
+        };
+        if !(min..=max).contains(&lo) || !(min..=max).contains(&hi) {
+            self.error(field, format!("must be between {} and {}", min, max));
+        }
+        let value = match (self.rng.as_deref_mut(), *param) {
+            (Some(rng), Param::Uniform { uniform: [lo, hi] }) if lo < hi => rng.gen_range(lo..hi),
+            (Some(rng), Param::Normal { normal }) if normal.std > 0.0 => {
+                // Box-Muller
+                let (u1, u2): (f64, f64) = (rng.gen_range(f64::EPSILON..1.0), rng.gen());
+                normal.mean + normal.std * (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+            }
+            _ => centre,
+        };
+        value.clamp(min, max)
+    }
+
+    fn int(&mut self, field: &str, param: &Param, min: u64, max: u64) -> u64 {
+        self.num(field, param, min as f64, max as f64).round() as u64
+    }
+
+    fn error(&mut self, field: &str, message: String) {
+        self.errors.push(format!("{}.{}: {}", self.path, field, message));
+    }
+}
+
+impl StrategySpec {
+    fn strategy(&self, d: &mut Draw, initial_price: u64, inventory: u64) -> Strategy {
+        match self {
+            StrategySpec::RandomWalker => Strategy::RandomWalker,
+            StrategySpec::TrendFollower { window_size } => Strategy::TrendFollower {
+                window_size: d.int("window_size", window_size, 1, 1000) as usize,
+            },
+            StrategySpec::MeanReverter { window_size, std_dev_multiplier } => Strategy::MeanReverter {
+                window_size: d.int("window_size", window_size, 2, 1000) as usize,
+                std_dev_multiplier: d.num("std_dev_multiplier", std_dev_multiplier, 0.0, 10.0),
+            },
+            StrategySpec::MarketMaker { spread_bps } => Strategy::MarketMaker {
+                spread_bps: d.int("spread_bps", spread_bps, 1, 5000),
+            },
+            StrategySpec::BeliefSwitcher { intensity_of_choice, fundamental_price, trend_strength, reversion_strength, memory } => {
+                Strategy::BeliefSwitcher {
+                    intensity_of_choice: d.num("intensity_of_choice", intensity_of_choice, 0.0, 100.0),
+                    fundamental_price: match fundamental_price {
+                        Some(price) => d.int("fundamental_price", price, 1, 1_000_000_000),
+                        None => initial_price,
+                    },
+                    trend_strength: d.num("trend_strength", trend_strength, 0.0, 10.0),
+                    reversion_strength: d.num("reversion_strength", reversion_strength, 0.0, 1.0),
+                    memory: d.num("memory", memory, 0.0, 1.0),
+                }
+            }
+            StrategySpec::Herder { imitation_probability } => Strategy::Herder {
+                imitation_probability: d.num("imitation_probability", imitation_probability, 0.0, 1.0),
+            },
+            StrategySpec::AvellanedaStoikov { risk_aversion, horizon, arrival_at_touch, quote_size, inventory_limit, target_inventory } => {
+                Strategy::AvellanedaStoikov {
+                    risk_aversion: d.num("risk_aversion", risk_aversion, 0.0, 1.0),
+                    horizon: d.num("horizon", horizon, 0.1, 1000.0),
+                    arrival_at_touch: d.num("arrival_at_touch", arrival_at_touch, 0.01, 100.0),
+                    quote_size: d.int("quote_size", quote_size, 1, 1_000_000),
+                    inventory_limit: d.int("inventory_limit", inventory_limit, 0, 1_000_000_000),
+                    target_inventory: match target_inventory {
+                        Some(target) => d.int("target_inventory", target, 0, 1_000_000_000),
+                        None => inventory,
+                    },
+                }
+            }
+            StrategySpec::QLearner { epsilon, learning_rate, discount } => Strategy::QLearner {
+                epsilon: d.num("epsilon", epsilon, 0.0, 1.0),
+                learning_rate: d.num("learning_rate", learning_rate, 0.0, 1.0),
+                discount: d.num("discount", discount, 0.0, 1.0),
+            },
+            StrategySpec::Scripted { path } => Strategy::Scripted { path: path.clone() },
+            StrategySpec::Spoofer(config) => {
+                if config.layers == 0 || config.campaign_ticks == 0 {
+                    d.error("layers", "layers and campaign_ticks must be positive".to_string());
+                }
+                Strategy::Spoofer(*config)
+            }
+            StrategySpec::MomentumIgniter(config) => {
+                if config.burst_ticks == 0 {
+                    d.error("burst_ticks", "must be positive".to_string());
+                }
+                Strategy::MomentumIgniter(*config)
+            }
+        }
+    }
+}
+
+impl Scenario {
+    /// Read, parse and validate a scenario file.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let scenario: Scenario = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+        scenario.validate().map_err(|errors| format!("{}: {}", path, errors.join("; ")))?;
+        Ok(scenario)
+    }
+
+    /// Every problem with the scenario, each prefixed with where it is, e.g.
+    /// `groups[2].strategy.window_size: must be between 1 and 1000`.
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut errors = Vec::new();
+        if self.instrument.initial_price == 0 {
+            errors.push("instrument.initial_price: must be positive".to_string());
+        }
+        if !(1..=60_000).contains(&self.tick_ms) {
+            errors.push("tick_ms: must be between 1 and 60000".to_string());
+        }
+        if self.groups.is_empty() {
+            errors.push("groups: at least one group is needed".to_string());
+        }
+        let mut total = 0;
+        for (i, group) in self.groups.iter().enumerate() {
+            let path = format!("groups[{}]", i);
+            if group.name.is_empty() {
+                errors.push(format!("{}.name: must not be empty", path));
+            }
+            if self.groups[..i].iter().any(|g| g.name == group.name) {
+                errors.push(format!("{}.name: '{}' is used by an earlier group", path, group.name));
+            }
+            if group.count == 0 {
+                errors.push(format!("{}.count: must be at least 1", path));
+            }
+            total += group.count;
+            let mut d = Draw { rng: None, path: path.clone(), errors: Vec::new() };
+            let inventory = self.balances(group, &mut d).1;
+            d.path = format!("{}.strategy", path);
+            group.strategy.strategy(&mut d, self.instrument.initial_price, inventory);
+            errors.append(&mut d.errors);
+            if let StrategySpec::Scripted { path: script } = &group.strategy {
+                if let Err(e) = scripting::check(script) {
+                    errors.push(format!("{}.strategy.path: {}", path, e));
+                }
+            }
+            if group.traits.is_some_and(|t| t.every == 0) {
+                errors.push(format!("{}.traits.every: must be at least 1", path));
+            }
+            if let Some(margin) = &group.margin {
+                if !(margin.initial_margin > 0.0 && margin.initial_margin <= 1.0) {
+                    errors.push(format!("{}.margin.initial_margin: must be above 0 and at most 1", path));
+                }
+                if !(margin.maintenance_margin >= 0.0 && margin.maintenance_margin < margin.initial_margin) {
+                    errors.push(format!("{}.margin.maintenance_margin: must be at least 0 and below initial_margin", path));
+                }
+            }
+        }
+        if total > 100_000 {
+            errors.push(format!("groups: {} agents in total, the limit is 100000", total));
+        }
+        if let Some(genetic) = &self.population.genetic {
+            if !(genetic.elite_fraction > 0.0 && genetic.elite_fraction <= 1.0) {
+                errors.push("population.genetic.elite_fraction: must be above 0 and at most 1".to_string());
+            }
+            if !(0.0..1.0).contains(&genetic.replace_fraction) {
+                errors.push("population.genetic.replace_fraction: must be at least 0 and below 1".to_string());
+            }
+            if !(genetic.mutation_scale >= 0.0 && genetic.mutation_scale <= 1.0) {
+                errors.push("population.genetic.mutation_scale: must be between 0 and 1".to_string());
+            }
+        }
+        for (i, shock) in self.shocks.iter().enumerate() {
+            if shock.tick == 0 {
+                errors.push(format!("shocks[{}].tick: ticks start at 1", i));
+            }
+            if let Some(message) = shock.shock.check() {
+                errors.push(format!("shocks[{}]: {}", i, message));
+            }
+        }
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+
+    fn balances(&self, group: &AgentGroup, d: &mut Draw) -> (u64, u64) {
+        let cash = group.cash.unwrap_or(Param::Fixed(self.defaults.cash as f64));
+        let inventory = group.inventory.unwrap_or(Param::Fixed(self.defaults.inventory as f64));
+        (d.int("cash", &cash, 0, 1_000_000_000_000), d.int("inventory", &inventory, 0, 1_000_000_000))
+    }
+
+    /// Build the population with ids from `first_id`, drawing any distributions
+    /// from `seed`. Scripts are compiled into `scripts`.
+    pub fn population(&self, seed: u64, first_id: u64, scripts: &mut ScriptHost) -> Result<Vec<Agent>, String> {
+        let mut rng = SimRng::seed_from_u64(seed);
+        let mut population = Vec::new();
+        let mut id = first_id;
+        for group in &self.groups {
+            let handle = match &group.strategy {
+                StrategySpec::Scripted { path } => Some(scripts.handle(path)?),
+                _ => None,
+            };
+            for i in 0..group.count {
+                let mut d = Draw { rng: Some(&mut rng), path: group.name.clone(), errors: Vec::new() };
+                let (cash, inventory) = self.balances(group, &mut d);
+                let strategy = group.strategy.strategy(&mut d, self.instrument.initial_price, inventory);
+                let mut agent = Agent::new(id, format!("{}_{}", group.name, i), cash, inventory, strategy);
+                if let Some(handle) = &handle {
+                    agent = agent.with_script(handle.clone());
+                }
+                if let Some(traits) = group.traits.filter(|t| i.is_multiple_of(t.every.max(1))) {
+                    agent = agent.with_traits(traits.preset.traits());
+                }
+                if let Some(margin) = group.margin {
+                    agent = agent.with_margin(MarginAccount::new(margin));
+                }
+                population.push(agent);
+                id += 1;
+            }
+        }
+        Ok(population)
+    }
+
+    /// Shocks in the order they fire.
+    pub fn schedule(&self) -> Vec<ScheduledShock> {
+        let mut shocks = self.shocks.clone();
+        shocks.sort_by_key(|s| s.tick);
+        shocks
+    }
+}
+
+impl Default for Scenario {
+    /// The built-in market: 84 agents across every honest strategy,
+    /// opening at $100. `scenarios/manipulation.json` adds manipulators.
+    fn default() -> Self {
+        let group = |name: &str, count, strategy| AgentGroup {
+            name: name.to_string(),
+            count,
+            strategy,
+            cash: None,
+            inventory: None,
+            traits: None,
+            margin: None,
+        };
+        let rich = |mut group: AgentGroup| {
+            group.cash = Some(2000000.0.into());
+            group.inventory = Some(2000.0.into());
+            group
+        };
+        let traits = |mut group: AgentGroup, preset, every| {
+            group.traits = Some(TraitSpec { preset, every });
+            group
+        };
+        let mut leveraged = traits(
+            group("Lev", 8, StrategySpec::TrendFollower { window_size: 5.0.into() }),
+            TraitPreset::Overconfident,
+            1,
+        );
+        leveraged.cash = Some(200000.0.into());
+        leveraged.inventory = Some(0.0.into());
+        leveraged.margin = Some(MarginConfig {
+            initial_margin: 0.25,
+            maintenance_margin: 0.15,
+            ..MarginConfig::default()
+        });
+
+        Self {
+            name: "default".to_string(),
+            instrument: Instrument::default(),
+            tick_ms: TICKED_WALL_MS,
+            defaults: Balances::default(),
+            population: Population::default(),
+            groups: vec![
+                // Liquidity providers
+                traits(group("Noise", 20, StrategySpec::RandomWalker), TraitPreset::ProspectTheory, 2),
+                // Momentum traders
+                traits(group("Trend", 15, StrategySpec::TrendFollower { window_size: 5.0.into() }), TraitPreset::Overconfident, 3),
+                // Contrarians
+                group("Mean", 10, StrategySpec::MeanReverter { window_size: 10.0.into(), std_dev_multiplier: 1.5.into() }),
+                rich(group("MM", 5, StrategySpec::MarketMaker { spread_bps: 50.0.into() })),
+                // Chartist/fundamentalist switching
+                group("Switch", 10, StrategySpec::BeliefSwitcher {
+                    intensity_of_choice: 0.2.into(),
+                    fundamental_price: None,
+                    trend_strength: 1.5.into(),
+                    reversion_strength: 0.2.into(),
+                    memory: 0.9.into(),
+                }),
+                // Inventory-aware two-sided quotes
+                rich(group("AS", 3, StrategySpec::AvellanedaStoikov {
+                    risk_aversion: 0.001.into(),
+                    horizon: 10.0.into(),
+                    arrival_at_touch: 2.0.into(),
+                    quote_size: 5.0.into(),
+                    inventory_limit: 500.0.into(),
+                    target_inventory: None,
+                })),
+                // 4x margin, can short, get liquidated
+                leveraged,
+                group("Herd", 10, StrategySpec::Herder { imitation_probability: 0.6.into() }),
+                group("QL", 3, StrategySpec::QLearner { epsilon: 0.1.into(), learning_rate: 0.1.into(), discount: 0.95.into() }),
+            ],
+            shocks: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,259 @@
+//! The event queue: when agents get to act, how long their orders take to
+//! reach the book, and the end-of-tick timer.
+//!
+//! Times are simulated nanoseconds (`clock::SimTime`). Every activation is
+//! placed at a uniformly random moment within its tick; an agent with a
+//! Poisson profile gets a random number of them. Whatever the agent decides
+//! then travels for `latency` (plus up to `jitter`) ticks before it arrives, so
+//! a fast agent can see the book, decide and land its order before a slow one
+//! that looked earlier. Cancels travel the same way as orders.
+//!
+//! The default profile (once per tick, no latency) is the old shuffled
+//! round-robin.
+//!
+//! Externally driven agents also get one `Instructions` event per tick, at a
+//! random moment like everyone's activations, when what their session queued
+//! reaches the book. Activation profiles and the regime's activity don't touch
+//! it, so a human's orders go in every tick however the market is set up.
+
+use crate::agents::{Agent, Strategy};
+use crate::clock::{tick_start, ticks_to_ns, SimTime, TICK_NS};
+use crate::types::Order;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ActivationProfile {
+    /// Expected activations per tick, Poisson distributed; `None` = exactly once
+    pub rate: Option<f64>,
+    /// Ticks between deciding and the order reaching the book
+    pub latency: f64,
+    /// Extra uniform random delay on top of `latency`, up to this many ticks
+    pub jitter: f64,
+}
+
+impl Default for ActivationProfile {
+    fn default() -> Self {
+        Self { rate: None, latency: 0.0, jitter: 0.0 }
+    }
+}
+
+/// Activation profiles per agent class, keyed by `Strategy::kind()`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SchedulerConfig {
+    pub default: ActivationProfile,
+    pub by_kind: HashMap<String, ActivationProfile>,
+}
+
+impl SchedulerConfig {
+    pub fn profile(&self, kind: &str) -> ActivationProfile {
+        self.by_kind.get(kind).copied().unwrap_or(self.default)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum ScheduledEvent {
+    /// The agent looks at the market and decides
+    Activate(u64),
+    /// A requote's cancel reaches the book
+    CancelAll(u64),
+    /// An order reaches the book
+    Arrive(Order),
+    /// An externally driven agent's queued instructions reach the book
+    Instructions(u64),
+    /// Timer at the last nanosecond of a tick: settle trades, run the
+    /// per-tick bookkeeping and start the next tick
+    TickEnd,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Scheduled {
+    time: SimTime,
+    seq: u64,
+    event: ScheduledEvent,
+}
+
+impl PartialEq for Scheduled {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Scheduled {}
+
+impl Ord for Scheduled {
+    // Earliest first out of the max-heap; ties go in scheduling order
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.time.cmp(&self.time).then(other.seq.cmp(&self.seq))
+    }
+}
+
+impl PartialOrd for Scheduled {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Scheduler {
+    config: SchedulerConfig,
+    queue: BinaryHeap<Scheduled>,
+    seq: u64,
+}
+
+impl Scheduler {
+    pub fn new(config: SchedulerConfig) -> Self {
+        Self { config, queue: BinaryHeap::new(), seq: 0 }
+    }
+
+    pub fn config(&self) -> &SchedulerConfig {
+        &self.config
+    }
+
+    /// Set the profile for one agent class, or the default when `kind` is `None`.
+    pub fn set_profile(&mut self, kind: Option<&str>, profile: ActivationProfile) {
+        match kind {
+            Some(kind) => { self.config.by_kind.insert(kind.to_string(), profile); }
+            None => self.config.default = profile,
+        }
+    }
+
+    /// Drop everything in flight, e.g. when the book is reset.
+    pub fn clear(&mut self) {
+        self.queue.clear();
+    }
+
+    /// Draw this tick's activations for every agent and set its end timer.
+    /// `activity` scales every simulated agent's rate; away from 1 the
+    /// once-per-tick agents turn Poisson too, at that rate.
+    pub fn begin_tick(&mut self, tick: u64, agents: &HashMap<u64, Agent>, agent_ids: &[u64], activity: f64, rng: &mut impl Rng) {
+        self.push(tick_start(tick + 1) - 1, ScheduledEvent::TickEnd);
+        for id in agent_ids {
+            let Some(agent) = agents.get(id) else { continue };
+            let external = matches!(agent.strategy, Strategy::External);
+            if external {
+                let time = tick_start(tick) + rng.gen_range(0..TICK_NS - 1);
+                self.push(time, ScheduledEvent::Instructions(*id));
+            }
+            let activity = if external { 1.0 } else { activity };
+            let activations = match self.config.profile(agent.strategy.kind()).rate {
+                None if activity == 1.0 => 1,
+                None => poisson(activity, rng),
+                Some(rate) => poisson(rate * activity, rng),
+            };
+            for _ in 0..activations {
+                let time = tick_start(tick) + rng.gen_range(0..TICK_NS - 1);
+                self.push(time, ScheduledEvent::Activate(*id));
+            }
+        }
+    }
+
+    /// Send what `agent` decided at `time`: its cancel (if it requotes) and then
+    /// its orders, all arriving together after the agent's latency.
+    pub fn send(&mut self, agent: &Agent, time: SimTime, cancel_first: bool, orders: Vec<Order>, rng: &mut impl Rng) {
+        let profile = self.config.profile(agent.strategy.kind());
+        let jitter = if profile.jitter > 0.0 { rng.gen_range(0.0..profile.jitter) } else { 0.0 };
+        let arrival = time + ticks_to_ns(profile.latency + jitter);
+        if cancel_first {
+            self.push(arrival, ScheduledEvent::CancelAll(agent.id));
+        }
+        for order in orders {
+            self.push(arrival, ScheduledEvent::Arrive(order));
+        }
+    }
+
+    /// Time of the earliest pending event.
+    pub fn peek_time(&self) -> Option<SimTime> {
+        self.queue.peek().map(|s| s.time)
+    }
+
+    /// The earliest pending event, with its time.
+    pub fn pop(&mut self) -> Option<(SimTime, ScheduledEvent)> {
+        self.queue.pop().map(|s| (s.time, s.event))
+    }
+
+    fn push(&mut self, time: SimTime, event: ScheduledEvent) {
+        self.seq += 1;
+        self.queue.push(Scheduled { time, seq: self.seq, event });
+    }
+}
+
+/// Knuth's method; fine for the handful of activations per tick used here.
+fn poisson(rate: f64, rng: &mut impl Rng) -> u64 {
+    let limit = (-rate.max(0.0)).exp();
+    let mut k = 0;
+    let mut p = rng.gen::<f64>();
+    while p > limit {
+        k += 1;
+        p *= rng.gen::<f64>();
+    }
+    k
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{OrderSide, SimRng};
+
+    fn walker(id: u64) -> Agent {
+        Agent::new(id, format!("Noise_{}", id), 1000000, 1000, Strategy::RandomWalker)
+    }
+
+    #[test]
+    fn poisson_mean_is_the_rate() {
+        let mut rng = SimRng::seed_from_u64(1);
+        let n = 20000;
+        for rate in [0.3, 2.5] {
+            let mean = (0..n).map(|_| poisson(rate, &mut rng)).sum::<u64>() as f64 / n as f64;
+            assert!((mean - rate).abs() < 0.05, "rate {} gave mean {}", rate, mean);
+        }
+        assert_eq!(poisson(0.0, &mut rng), 0);
+    }
+
+    #[test]
+    fn latency_delays_arrival() {
+        let mut scheduler = Scheduler::default();
+        scheduler.set_profile(Some("RandomWalker"), ActivationProfile { rate: None, latency: 2.5, jitter: 0.0 });
+        let mut rng = SimRng::seed_from_u64(1);
+        let order = Order { id: 1, trader_id: 7, side: OrderSide::Bid, price: 10000, amount: 1, timestamp: 0 };
+        let decided = tick_start(3) + 10;
+        scheduler.send(&walker(7), decided, true, vec![order], &mut rng);
+
+        let arrival = decided + 5 * TICK_NS / 2;
+        assert!(matches!(scheduler.pop(), Some((t, ScheduledEvent::CancelAll(7))) if t == arrival));
+        assert!(matches!(scheduler.pop(), Some((t, ScheduledEvent::Arrive(o))) if t == arrival && o.id == 1));
+    }
+
+    #[test]
+    fn jitter_adds_up_to_its_bound() {
+        let mut scheduler = Scheduler::default();
+        scheduler.set_profile(None, ActivationProfile { rate: None, latency: 1.0, jitter: 0.5 });
+        let mut rng = SimRng::seed_from_u64(1);
+        for _ in 0..100 {
+            scheduler.send(&walker(7), 0, true, Vec::new(), &mut rng);
+        }
+        while let Some((t, _)) = scheduler.pop() {
+            assert!((TICK_NS..TICK_NS * 3 / 2).contains(&t));
+        }
+    }
+
+    #[test]
+    fn default_profile_activates_once_within_the_tick() {
+        let mut scheduler = Scheduler::default();
+        let agents: HashMap<u64, Agent> = (1..=5).map(|id| (id, walker(id))).collect();
+        let ids: Vec<u64> = (1..=5).collect();
+        scheduler.begin_tick(4, &agents, &ids, 1.0, &mut SimRng::seed_from_u64(1));
+
+        let mut activated = Vec::new();
+        while let Some((t, event)) = scheduler.pop() {
+            assert!((tick_start(4)..tick_start(5)).contains(&t));
+            if let ScheduledEvent::Activate(id) = event {
+                activated.push(id);
+            }
+        }
+        activated.sort_unstable();
+        assert_eq!(activated, ids);
+    }
+}
@@ -0,0 +1,223 @@
+//! Agents whose decision function is a Rhai script.
+//!
+//! A script defines `fn decide(ctx)` and is called once per tick. `ctx` is a
+//! read-only map:
+//!
+//! | key          | type          | meaning                                   |
+//! |--------------|---------------|-------------------------------------------|
+//! | `id`         | int           | the agent's trader id                     |
+//! | `price`      | int           | last traded price, cents                  |
+//! | `history`    | array of int  | recent prices, oldest first               |
+//! | `best_bid`   | int or `()`   | top of book bid, `()` if the side is empty |
+//! | `best_ask`   | int or `()`   | top of book ask                           |
+//! | `cash`       | int           | cents                                     |
+//! | `inventory`  | int           | units held                                |
+//! | `max_buy`    | int           | units affordable at `price`               |
+//! | `max_sell`   | int           | units sellable at `price`                 |
+//!
+//! `this` is a map private to the agent that persists between calls, for
+//! whatever state the strategy needs (`this.count += 1`).
+//!
+//! Return `()` to do nothing, an order map `#{ side: "bid", price: 10010, amount: 1 }`,
+//! or an array of order maps. Orders the agent cannot afford are dropped.
+//!
+//! Scripts run sandboxed: no `import`, no file access, and a cap on operations,
+//! call depth and collection sizes, so a runaway script errors instead of
+//! stalling the sim. Errors are logged and the agent sits out the tick.
+//!
+//! Only files under `SCRIPT_DIR` are loaded. Paths are taken relative to the
+//! working directory (`scripts/breakout.rhai`) and must still be inside it once
+//! `..` and symlinks are resolved.
+
+use crate::agents::Agent;
+use crate::say;
+use crate::types::{Order, OrderSide};
+use rand::prelude::*;
+use rhai::module_resolvers::DummyModuleResolver;
+use rhai::{Array, CallFnOptions, Dynamic, Engine, Map, Scope, AST};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, RwLock};
+
+fn engine() -> &'static Engine {
+    static ENGINE: OnceLock<Engine> = OnceLock::new();
+    ENGINE.get_or_init(|| {
+        let mut engine = Engine::new();
+        engine.set_module_resolver(DummyModuleResolver::new());
+        engine.set_max_operations(100_000);
+        engine.set_max_call_levels(32);
+        engine.set_max_expr_depths(64, 32);
+        engine.set_max_string_size(10_000);
+        engine.set_max_array_size(10_000);
+        engine.set_max_map_size(1_000);
+        engine.on_print(|msg| say!("[script] {}", msg));
+        engine.on_debug(|msg, _, _| say!("[script] {}", msg));
+        engine
+    })
+}
+
+/// Where scripts live, relative to the working directory.
+pub const SCRIPT_DIR: &str = "scripts";
+
+/// `path` on disk, if it names a file under `SCRIPT_DIR`.
+fn resolve(path: &str) -> Result<PathBuf, String> {
+    let root = Path::new(SCRIPT_DIR).canonicalize().map_err(|e| format!("{}: {}", SCRIPT_DIR, e))?;
+    let file = Path::new(path).canonicalize().map_err(|e| format!("{}: {}", path, e))?;
+    if !file.starts_with(&root) {
+        return Err(format!("{}: scripts must be under {}/", path, SCRIPT_DIR));
+    }
+    Ok(file)
+}
+
+fn compile(path: &str) -> Result<AST, String> {
+    engine().compile_file(resolve(path)?).map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Compile a script file without registering it, to surface errors early.
+/// Blocks on the file read, so async callers run it on a blocking thread.
+pub fn check(path: &str) -> Result<(), String> {
+    compile(path).map(|_| ())
+}
+
+/// A compiled script shared by every agent running it. Reloading swaps the AST
+/// in place, so all of them pick up the new version on their next tick.
+#[derive(Debug)]
+pub struct Script {
+    pub path: String,
+    ast: RwLock<Arc<AST>>,
+}
+
+pub type ScriptHandle = Arc<Script>;
+
+impl Script {
+    fn load(path: &str) -> Result<Self, String> {
+        let ast = compile(path)?;
+        Ok(Self { path: path.to_string(), ast: RwLock::new(Arc::new(ast)) })
+    }
+
+    fn reload(&self) -> Result<(), String> {
+        let ast = compile(&self.path)?;
+        *self.ast.write().unwrap() = Arc::new(ast);
+        Ok(())
+    }
+}
+
+/// Per-agent link to a script plus its private `this` state.
+#[derive(Debug, Clone)]
+pub struct ScriptRuntime {
+    pub handle: ScriptHandle,
+    state: Dynamic,
+}
+
+impl ScriptRuntime {
+    pub fn new(handle: ScriptHandle) -> Self {
+        Self { handle, state: Dynamic::from_map(Map::new()) }
+    }
+
+    /// The agent's `this`, for snapshots.
+    pub fn state(&self) -> &Dynamic {
+        &self.state
+    }
+
+    /// Pick up where a snapshot left off.
+    pub fn with_state(mut self, state: Dynamic) -> Self {
+        self.state = state;
+        self
+    }
+
+    /// Call the script's `decide` for `agent` and convert the result to orders.
+    pub fn decide(&mut self, agent: &Agent, current_price: u64, rng: &mut impl Rng) -> Result<Vec<Order>, String> {
+        let ast = self.handle.ast.read().unwrap().clone();
+        let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(&mut self.state);
+        let result: Dynamic = engine()
+            .call_fn_with_options(options, &mut Scope::new(), &ast, "decide", (context(agent, current_price),))
+            .map_err(|e| format!("{}: {}", self.handle.path, e))?;
+
+        let raw: Vec<Dynamic> = if result.is_unit() {
+            Vec::new()
+        } else if result.is_array() {
+            result.cast::<Array>()
+        } else {
+            vec![result]
+        };
+
+        let mut orders = Vec::with_capacity(raw.len());
+        for item in raw {
+            let map = item.try_cast::<Map>().ok_or_else(|| format!("{}: decide must return (), an order map or an array of them", self.handle.path))?;
+            let side = match map.get("side").and_then(|v| v.clone().into_string().ok()).as_deref() {
+                Some("bid") | Some("buy") => OrderSide::Bid,
+                Some("ask") | Some("sell") => OrderSide::Ask,
+                other => return Err(format!("{}: order side must be \"bid\" or \"ask\", got {:?}", self.handle.path, other)),
+            };
+            let price = map.get("price").and_then(|v| v.as_int().ok()).unwrap_or(current_price as i64).max(1) as u64;
+            let amount = map.get("amount").and_then(|v| v.as_int().ok()).unwrap_or(1).max(0) as u64;
+            let allowed = match side {
+                OrderSide::Bid => agent.can_buy(price, amount),
+                OrderSide::Ask => agent.can_sell(price, amount),
+            };
+            if amount > 0 && allowed {
+                orders.push(Order {
+                    id: rng.next_u64(),
+                    trader_id: agent.id,
+                    side,
+                    price,
+                    amount,
+                    timestamp: 0,
+                });
+            }
+        }
+        Ok(orders)
+    }
+}
+
+fn context(agent: &Agent, current_price: u64) -> Map {
+    let optional = |v: Option<u64>| v.map(|p| Dynamic::from_int(p as i64)).unwrap_or(Dynamic::UNIT);
+    let mut ctx = Map::new();
+    ctx.insert("id".into(), Dynamic::from_int(agent.id as i64));
+    ctx.insert("price".into(), Dynamic::from_int(current_price as i64));
+    ctx.insert("history".into(), Dynamic::from_array(agent.price_history.iter().map(|&p| Dynamic::from_int(p as i64)).collect()));
+    ctx.insert("best_bid".into(), optional(agent.book_top.0));
+    ctx.insert("best_ask".into(), optional(agent.book_top.1));
+    ctx.insert("cash".into(), Dynamic::from_int(agent.cash as i64));
+    ctx.insert("inventory".into(), Dynamic::from_int(agent.inventory as i64));
+    ctx.insert("max_buy".into(), Dynamic::from_int(agent.max_buy(current_price) as i64));
+    ctx.insert("max_sell".into(), Dynamic::from_int(agent.max_sell(current_price) as i64));
+    ctx
+}
+
+/// Scripts loaded in this simulation, by path.
+#[derive(Debug, Default)]
+pub struct ScriptHost {
+    scripts: HashMap<String, ScriptHandle>,
+}
+
+impl ScriptHost {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The shared handle for `path`, compiling it on first use.
+    pub fn handle(&mut self, path: &str) -> Result<ScriptHandle, String> {
+        if let Some(handle) = self.scripts.get(path) {
+            return Ok(handle.clone());
+        }
+        let handle = Arc::new(Script::load(path)?);
+        self.scripts.insert(path.to_string(), handle.clone());
+        Ok(handle)
+    }
+
+    /// Recompile one script, or all of them. A script that fails to compile
+    /// keeps running its previous version.
+    pub fn reload(&mut self, path: Option<&str>) -> Vec<(String, Result<(), String>)> {
+        self.scripts.values()
+            .filter(|s| path.is_none_or(|p| p == s.path))
+            .map(|s| (s.path.clone(), s.reload()))
+            .collect()
+    }
+
+    pub fn paths(&self) -> Vec<String> {
+        let mut paths: Vec<String> = self.scripts.keys().cloned().collect();
+        paths.sort();
+        paths
+    }
+}
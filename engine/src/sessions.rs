@@ -0,0 +1,271 @@
+//! Several independent simulations in one server process.
+//!
+//! Each session is a `Simulation` on its own thread, paced by its own clock,
+//! with its own broadcast and command channels, game player keys, logs and
+//! journal. The server starts with the `default` session; every HTTP and WS
+//! route also exists under `/sessions/<id>/...`, and the unprefixed routes
+//! belong to `default`. Sessions other than `default` log to `sessions/<id>/`
+//! and keep quiet on the console.
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Path},
+    http::request::Parts,
+};
+use std::collections::{HashMap, HashSet};
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc};
+use crate::clock::{tick_start, Clock, Pacing};
+use crate::console;
+use crate::journal::{Entry, Journal};
+use crate::routes::admin::AdminError;
+use crate::say;
+use crate::simulation::Simulation;
+use crate::state::AppState;
+use crate::types::{ServerMessage, SimulationCommand, TimeStatus};
+
+/// The session the unprefixed routes talk to. It lives as long as the server.
+pub const DEFAULT_SESSION: &str = "default";
+/// Where sessions other than `default` keep their logs and journal, a directory each.
+pub const SESSION_DIR: &str = "sessions";
+/// Each session is a thread running flat out under fast pacing, so keep a lid on them.
+pub const MAX_SESSIONS: usize = 16;
+
+/// The server's handle on one running simulation.
+pub struct SimSession {
+    pub id: String,
+    pub tx: broadcast::Sender<ServerMessage>,
+    pub cmd_tx: mpsc::UnboundedSender<SimulationCommand>,
+    /// Game player id -> the key issued when they joined
+    pub player_keys: RwLock<HashMap<u64, String>>,
+}
+
+/// Every running session, by id.
+pub struct Sessions {
+    sessions: RwLock<HashMap<String, Arc<SimSession>>>,
+    /// Ids held for sessions still starting up. Locked after `sessions`.
+    starting: Mutex<HashSet<String>>,
+    /// Pacing for sessions created without one: the server's `--pacing`
+    pub pacing: Pacing,
+}
+
+impl Sessions {
+    pub fn new(default: Arc<SimSession>, pacing: Pacing) -> Self {
+        let sessions = HashMap::from([(default.id.clone(), default)]);
+        Sessions { sessions: RwLock::new(sessions), starting: Mutex::default(), pacing }
+    }
+
+    pub fn get(&self, id: &str) -> Option<Arc<SimSession>> {
+        self.sessions.read().unwrap().get(id).cloned()
+    }
+
+    /// Ids in order.
+    pub fn ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.sessions.read().unwrap().keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    /// Whether `id` is running or starting up.
+    pub fn taken(&self, id: &str) -> bool {
+        let sessions = self.sessions.read().unwrap();
+        sessions.contains_key(id) || self.starting.lock().unwrap().contains(id)
+    }
+
+    /// Hold `id` for a session about to start, unless it is taken or the server
+    /// is full. The id is free again when the reservation drops unfilled, so a
+    /// session that fails to start leaves nothing behind.
+    pub fn reserve(&self, id: &str) -> Result<Reservation<'_>, String> {
+        let sessions = self.sessions.read().unwrap();
+        let mut starting = self.starting.lock().unwrap();
+        if sessions.contains_key(id) || starting.contains(id) {
+            return Err(format!("session {} already exists", id));
+        }
+        if sessions.len() + starting.len() >= MAX_SESSIONS {
+            return Err(format!("at most {} sessions can run at once", MAX_SESSIONS));
+        }
+        starting.insert(id.to_string());
+        Ok(Reservation { sessions: self, id: id.to_string() })
+    }
+
+    /// Stop a session's thread and forget it. `default` stays.
+    pub fn destroy(&self, id: &str) -> Result<(), String> {
+        if id == DEFAULT_SESSION {
+            return Err("the default session cannot be destroyed".to_string());
+        }
+        let session = self.sessions.write().unwrap().remove(id)
+            .ok_or_else(|| format!("no session {}", id))?;
+        let _ = session.cmd_tx.send(SimulationCommand::Shutdown);
+        Ok(())
+    }
+}
+
+/// A session id held while the session starts, from `Sessions::reserve`.
+pub struct Reservation<'a> {
+    sessions: &'a Sessions,
+    id: String,
+}
+
+impl Reservation<'_> {
+    /// Register the session started under this id.
+    pub fn fill(self, session: Arc<SimSession>) {
+        let mut sessions = self.sessions.sessions.write().unwrap();
+        self.sessions.starting.lock().unwrap().remove(&self.id);
+        sessions.insert(self.id.clone(), session);
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        self.sessions.starting.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// Session ids double as directory names, so keep them plain.
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty() && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Begin the run `start` describes as session `id`, with its logs and journal
+/// in `dir`. The session is not registered anywhere yet.
+pub fn launch(id: &str, start: &Entry, dir: &std::path::Path, pacing: Pacing) -> Result<Arc<SimSession>, String> {
+    let sim = start.begin()?.with_logs(dir).map_err(|e| e.to_string())?;
+    let mut journal = Journal::create(&dir.join("journal.bin")).map_err(|e| e.to_string())?;
+    journal.append(start).map_err(|e| e.to_string())?;
+    Ok(spawn(id, sim, pacing, journal))
+}
+
+/// Run `sim` on a new thread as session `id`, journaling to `journal`.
+fn spawn(id: &str, sim: Simulation, pacing: Pacing, journal: Journal) -> Arc<SimSession> {
+    let (tx, _rx) = broadcast::channel::<ServerMessage>(100);
+    let (cmd_tx, cmd_rx) = mpsc::unbounded_channel::<SimulationCommand>();
+    let sim = sim.with_sink(tx.clone());
+    let quiet = id != DEFAULT_SESSION;
+    thread::spawn(move || {
+        console::set_thread_quiet(quiet);
+        simulation_loop(sim, cmd_rx, pacing, journal);
+    });
+    Arc::new(SimSession { id: id.to_string(), tx, cmd_tx, player_keys: Default::default() })
+}
+
+/// Paces `sim` with the wall clock and carries out commands as they arrive,
+/// until told to shut down or the command channel closes. Commands and tick
+/// ends go to `journal`, positioned by the count of events released so far.
+fn simulation_loop(mut sim: Simulation, mut cmd_rx: mpsc::UnboundedReceiver<SimulationCommand>, pacing: Pacing, journal: Journal) {
+    let mut clock = Clock::new(pacing);
+    clock.set_tick_ms(sim.scenario().tick_ms);
+    let mut journal = Some(journal);
+    let mut events = 0;
+
+    say!("--- Simulation Started (Background Thread, {:?} pacing) ---", pacing);
+
+    loop {
+        // Commands take effect between events, as soon as they arrive
+        loop {
+            match cmd_rx.try_recv() {
+                Ok(SimulationCommand::SetPacing { pacing, tick_ms }) => {
+                    say!("God Mode: {:?} pacing{}", pacing, tick_ms.map(|ms| format!(", {} ms ticks", ms)).unwrap_or_default());
+                    if let Some(ms) = tick_ms {
+                        clock.set_tick_ms(ms);
+                    }
+                    clock.set_pacing(pacing, sim.next_event_time().unwrap_or(tick_start(sim.tick())));
+                }
+                Ok(SimulationCommand::QueryTime { reply }) => {
+                    let _ = reply.send(TimeStatus {
+                        tick: sim.tick(),
+                        price: sim.current_price(),
+                        paused: sim.is_paused(),
+                        pacing: clock.pacing(),
+                        tick_ms: clock.tick_ms(),
+                        until: sim.stop_condition(),
+                    });
+                }
+                Ok(SimulationCommand::Shutdown) => {
+                    say!("Session shut down, exiting simulation.");
+                    return;
+                }
+                Ok(cmd) => {
+                    // Resuming and restarting move simulated time; the clock follows
+                    let rebase = matches!(cmd, SimulationCommand::SetPaused(false) | SimulationCommand::RunUntil(_)
+                        | SimulationCommand::Reset { .. } | SimulationCommand::LoadScenario(_)
+                        | SimulationCommand::LoadSnapshot(_));
+                    let load = matches!(cmd, SimulationCommand::LoadScenario(_) | SimulationCommand::LoadSnapshot(_));
+                    let step = matches!(cmd, SimulationCommand::Step { .. });
+                    record(&mut journal, |j| j.command(events, &cmd));
+                    sim.apply(cmd);
+                    if step {
+                        // A step closes its ticks inside the command; mark the last once they're all done
+                        record(&mut journal, |j| j.append(&Entry::Tick { events, tick: sim.tick() - 1 }));
+                    }
+                    if load {
+                        clock.set_tick_ms(sim.scenario().tick_ms);
+                    }
+                    if rebase {
+                        clock.rebase(sim.next_event_time().unwrap_or(tick_start(sim.tick())));
+                    }
+                }
+                Err(mpsc::error::TryRecvError::Empty) => break,
+                Err(mpsc::error::TryRecvError::Disconnected) => {
+                    say!("Command channel closed, exiting simulation.");
+                    return;
+                }
+            }
+        }
+
+        // Release the next event once the clock allows it
+        let Some(at) = sim.next_event_time().filter(|_| !sim.is_paused()) else {
+            thread::sleep(Duration::from_millis(1));
+            continue;
+        };
+        let wait = clock.due(at).saturating_duration_since(Instant::now());
+        if !wait.is_zero() {
+            thread::sleep(wait.min(Duration::from_millis(1)));
+            continue;
+        }
+        clock.advance(at);
+        let closed = sim.step_event();
+        events += 1;
+        if let Some(tick) = closed {
+            record(&mut journal, |j| j.append(&Entry::Tick { events, tick }));
+        }
+    }
+}
+
+/// Write to the journal. If that fails the market carries on unrecorded.
+fn record(journal: &mut Option<Journal>, write: impl FnOnce(&mut Journal) -> std::io::Result<()>) {
+    if let Some(j) = journal.as_mut() {
+        if let Err(e) = write(j) {
+            eprintln!("Journal write failed, no longer journaling: {}", e);
+            *journal = None;
+        }
+    }
+}
+
+/// Extracts the session a request is for: the `:session` path parameter under
+/// `/sessions/:session/...`, or `default` without one.
+pub struct Sim(pub Arc<SimSession>);
+
+impl Deref for Sim {
+    type Target = SimSession;
+
+    fn deref(&self) -> &SimSession {
+        &self.0
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for Sim {
+    type Rejection = AdminError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<AppState>) -> Result<Self, AdminError> {
+        let params = Path::<HashMap<String, String>>::from_request_parts(parts, state).await
+            .map(|Path(params)| params)
+            .unwrap_or_default();
+        let id = params.get("session").map_or(DEFAULT_SESSION, String::as_str);
+        state.sessions.get(id).map(Sim).ok_or_else(|| AdminError::NotFound(format!("no session {}", id)))
+    }
+}
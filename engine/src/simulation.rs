@@ -0,0 +1,1171 @@
+//! The market engine: the order book, the agents and everything that reacts
+//! to them, advanced one scheduled event at a time.
+//!
+//! `Simulation` has no notion of wall-clock time. The server paces it with a
+//! `Clock`, while batch runs, tests and benchmarks just call `step()`:
+//!
+//! ```no_run
+//! use market_engine::scenario::Scenario;
+//! use market_engine::simulation::Simulation;
+//!
+//! let mut sim = Simulation::new(Scenario::default(), 7).unwrap();
+//! for _ in 0..1000 {
+//!     sim.step();
+//! }
+//! println!("{} after {} ticks", sim.current_price(), sim.summary().ticks);
+//! ```
+//!
+//! `snapshot()` saves everything that decides what happens next (book, agents
+//! and their strategy state, the event queue, every random stream) as JSON.
+//! `restore()` picks up from it, and a restored run trades exactly as the
+//! original would have. `fork()` does both, for what-if branches.
+
+use crate::agents::{Agent, BeliefRule, Strategy, BOOK_PRESSURE_WINDOW_BPS};
+use crate::batch::{RunStats, RunSummary};
+use crate::clock::SimTime;
+use crate::game::{Game, GameConfig};
+use crate::indicators::Bar;
+use crate::network::{SocialConfig, SocialGraph};
+use crate::orderbook::OrderBook;
+use crate::population::{PopulationConfig, PopulationEvents, PopulationManager};
+use crate::regime::{MarketRegime, RegimeStatus};
+use crate::say;
+use crate::scenario::{Scenario, ScheduledShock};
+use crate::scheduling::{ScheduledEvent, Scheduler, SchedulerConfig};
+use crate::scripting::{ScriptHost, ScriptRuntime};
+use crate::surveillance::{Surveillance, SurveillanceConfig};
+use crate::types::{ExternalInstruction, Order, OrderSide, ServerMessage, SessionEvent, SimRng, SimulationCommand, Trade};
+use csv::Writer;
+use rand::prelude::*;
+use rhai::Dynamic;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::path::Path;
+use tokio::sync::{broadcast, mpsc};
+
+/// Where a simulation sends its market data: trades, tickers, alerts and the rest.
+pub trait EventSink: Send {
+    fn emit(&mut self, message: ServerMessage);
+}
+
+/// Discards everything.
+impl EventSink for () {
+    fn emit(&mut self, _message: ServerMessage) {}
+}
+
+/// The server's feed to its WebSocket clients. Having no clients is fine.
+impl EventSink for broadcast::Sender<ServerMessage> {
+    fn emit(&mut self, message: ServerMessage) {
+        let _ = self.send(message);
+    }
+}
+
+impl EventSink for std::sync::mpsc::Sender<ServerMessage> {
+    fn emit(&mut self, message: ServerMessage) {
+        let _ = self.send(message);
+    }
+}
+
+/// Written into snapshots; bumped when the saved state changes shape.
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+/// When a `RunUntil` pauses the simulation, checked as each tick closes.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "until", rename_all = "snake_case")]
+pub enum StopCondition {
+    /// The tick has closed
+    Tick { tick: u64 },
+    /// The last price is at or above `price`
+    PriceAbove { price: u64 },
+    /// The last price is at or below `price`
+    PriceBelow { price: u64 },
+    /// The market stopped working: a crash as batch statistics count them
+    /// (see `batch::CRASH_DRAWDOWN`), or one side of the book is empty
+    Halt,
+}
+
+/// One market, from a scenario and a seed.
+pub struct Simulation {
+    scenario: Scenario,
+    /// Seed of the current run, reused when a scenario is loaded
+    seed: u64,
+    /// Everything random in the run descends from the seed: this stream drives
+    /// the loop and God Mode, and each agent draws from its own
+    rng: SimRng,
+    book: OrderBook,
+    agents: HashMap<u64, Agent>,
+    agent_ids: Vec<u64>,
+    graph: SocialGraph,
+    current_price: u64,
+    population: PopulationManager,
+    scripts: ScriptHost,
+    sessions: HashMap<u64, mpsc::UnboundedSender<SessionEvent>>,
+    game: Game,
+    surveillance: Surveillance,
+    scheduler: Scheduler,
+    /// God Mode's market regime and the fundamental it has moved
+    regime: MarketRegime,
+    /// Scenario shocks by tick, and the next one to fire
+    shocks: Vec<ScheduledShock>,
+    next_shock: usize,
+    stats: RunStats,
+    /// Agents that have already taken this tick's social signal
+    looked: HashSet<u64>,
+    /// Last closed tick, fed to each agent's history and indicators
+    bar: Bar,
+    /// The tick in progress
+    tick: u64,
+    paused: bool,
+    /// Pause again once this holds
+    stop: Option<StopCondition>,
+    sink: Box<dyn EventSink>,
+    trade_log: Option<Writer<File>>,
+    /// Tagged orders from the manipulator agents, ground truth for surveillance
+    manipulation_log: Option<Writer<File>>,
+}
+
+impl Simulation {
+    /// The scenario's population at tick 1, with no sink and no logs.
+    pub fn new(scenario: Scenario, seed: u64) -> Result<Self, String> {
+        Self::build(scenario, seed, Vec::new(), SchedulerConfig::default(), MarketRegime::default())
+    }
+
+    /// `new`, with `kept` agents (trading sessions and game players) joining
+    /// the scenario's population, which takes the ids after theirs.
+    fn build(scenario: Scenario, seed: u64, kept: Vec<Agent>, schedule: SchedulerConfig, regime: MarketRegime) -> Result<Self, String> {
+        let mut rng = SimRng::seed_from_u64(seed);
+        let mut scripts = ScriptHost::new();
+        let first_id = kept.iter().map(|a| a.id).max().unwrap_or(0) + 1;
+        let mut agents = HashMap::new();
+        let current_price = scenario.instrument.initial_price;
+        for mut agent in scenario.population(seed, first_id, &mut scripts)?.into_iter().chain(kept) {
+            agent.reseed(seed);
+            // Everyone starts out having seen the opening price
+            agent.update_market_data(Bar::at(current_price));
+            agents.insert(agent.id, agent);
+        }
+        let mut agent_ids: Vec<u64> = agents.keys().copied().collect();
+        agent_ids.sort_unstable();
+        say!("--- Initialized {} agents ---", agents.len());
+
+        let graph = SocialGraph::build(&agent_ids, SocialConfig::default(), &mut rng);
+        let population = PopulationManager::new(population_config(&scenario), &agents, current_price).with_seed(seed);
+        let mut scheduler = Scheduler::new(schedule);
+        scheduler.begin_tick(1, &agents, &agent_ids, regime.conditions().activity, &mut rng);
+        Ok(Self {
+            shocks: scenario.schedule(),
+            scenario,
+            seed,
+            rng,
+            book: OrderBook::new(),
+            agents,
+            agent_ids,
+            graph,
+            current_price,
+            population,
+            scripts,
+            sessions: HashMap::new(),
+            game: Game::new(GameConfig::default()),
+            surveillance: Surveillance::new(SurveillanceConfig::default()),
+            scheduler,
+            regime,
+            next_shock: 0,
+            stats: RunStats::new(current_price),
+            looked: HashSet::new(),
+            bar: Bar::at(current_price),
+            tick: 1,
+            paused: false,
+            stop: None,
+            sink: Box::new(()),
+            trade_log: None,
+            manipulation_log: None,
+        })
+    }
+
+    pub fn with_sink(mut self, sink: impl EventSink + 'static) -> Self {
+        self.sink = Box::new(sink);
+        self
+    }
+
+    /// Log trades to `trades.csv` and manipulator activity to `manipulation.csv` in `dir`.
+    pub fn with_logs(mut self, dir: &Path) -> csv::Result<Self> {
+        self.trade_log = Some(Writer::from_path(dir.join("trades.csv"))?);
+        self.manipulation_log = Some(Writer::from_path(dir.join("manipulation.csv"))?);
+        Ok(self)
+    }
+
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn current_price(&self) -> u64 {
+        self.current_price
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn scenario(&self) -> &Scenario {
+        &self.scenario
+    }
+
+    pub fn book(&self) -> &OrderBook {
+        &self.book
+    }
+
+    pub fn agents(&self) -> &HashMap<u64, Agent> {
+        &self.agents
+    }
+
+    pub fn graph(&self) -> &SocialGraph {
+        &self.graph
+    }
+
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+
+    pub fn surveillance(&self) -> &Surveillance {
+        &self.surveillance
+    }
+
+    /// Paused simulations still step when asked; pausing is for whoever drives them.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn stop_condition(&self) -> Option<StopCondition> {
+        self.stop
+    }
+
+    /// Outcome statistics since the last reset.
+    pub fn summary(&self) -> RunSummary {
+        self.stats.summary(self.seed, self.agents.len())
+    }
+
+    /// Start the outcome statistics over from here, e.g. at a fork.
+    pub fn clear_stats(&mut self) {
+        self.stats = RunStats::new(self.current_price);
+    }
+
+    /// The full state as JSON. Sinks, logs, trading sessions and the pause
+    /// state belong to whoever runs the simulation and are left out.
+    pub fn snapshot(&self) -> Result<String, String> {
+        let scripts = self.agents.values()
+            .filter_map(|a| a.script.as_ref().map(|s| (a.id, s.state())))
+            .collect();
+        serde_json::to_string(&SnapshotRef {
+            version: SNAPSHOT_VERSION,
+            scenario: &self.scenario,
+            seed: self.seed,
+            rng: &self.rng,
+            book: &self.book,
+            agents: &self.agents,
+            agent_ids: &self.agent_ids,
+            scripts,
+            graph: &self.graph,
+            current_price: self.current_price,
+            population: &self.population,
+            game: &self.game,
+            surveillance: &self.surveillance,
+            scheduler: &self.scheduler,
+            regime: self.regime,
+            shocks: &self.shocks,
+            next_shock: self.next_shock,
+            stats: &self.stats,
+            looked: &self.looked,
+            bar: self.bar,
+            tick: self.tick,
+            stop: self.stop,
+        }).map_err(|e| e.to_string())
+    }
+
+    /// A simulation that carries on from `snapshot`, running and with no sink or logs.
+    pub fn restore(snapshot: &str) -> Result<Self, String> {
+        let Snapshot {
+            version, scenario, seed, rng, book, mut agents, agent_ids, mut scripts, graph, current_price,
+            population, game, surveillance, scheduler, regime, shocks, next_shock, stats, looked, bar, tick, stop,
+        } = serde_json::from_str(snapshot).map_err(|e| format!("not a snapshot: {}", e))?;
+        if version != SNAPSHOT_VERSION {
+            return Err(format!("snapshot version {}, this engine reads version {}", version, SNAPSHOT_VERSION));
+        }
+
+        // Compiled scripts aren't saved; load them again and hand back each agent's `this`
+        let mut host = ScriptHost::new();
+        for agent in agents.values_mut() {
+            if let Strategy::Scripted { path } = &agent.strategy {
+                let runtime = ScriptRuntime::new(host.handle(path)?);
+                agent.script = Some(match scripts.remove(&agent.id) {
+                    Some(state) => runtime.with_state(state),
+                    None => runtime,
+                });
+            }
+        }
+
+        Ok(Self {
+            scenario,
+            seed,
+            rng,
+            book,
+            agents,
+            agent_ids,
+            graph,
+            current_price,
+            population,
+            scripts: host,
+            sessions: HashMap::new(),
+            game,
+            surveillance,
+            scheduler,
+            regime,
+            shocks,
+            next_shock,
+            stats,
+            looked,
+            bar,
+            tick,
+            paused: false,
+            stop,
+            sink: Box::new(()),
+            trade_log: None,
+            manipulation_log: None,
+        })
+    }
+
+    /// An independent copy of the market as it stands, for running alongside.
+    pub fn fork(&self) -> Result<Self, String> {
+        Self::restore(&self.snapshot()?)
+    }
+
+    /// When the next event is due in simulated time.
+    pub fn next_event_time(&self) -> Option<SimTime> {
+        self.scheduler.peek_time()
+    }
+
+    /// Run events until the tick in progress closes, and return that tick.
+    pub fn step(&mut self) -> u64 {
+        loop {
+            if let Some(tick) = self.step_event() {
+                return tick;
+            }
+        }
+    }
+
+    /// Process the next scheduled event. Returns the tick it closed, if it was a tick's end.
+    pub fn step_event(&mut self) -> Option<u64> {
+        let (time, event) = self.scheduler.pop()?;
+        let tick = self.tick;
+
+        // Agents act at their scheduled moments in the tick; what they send
+        // reaches the book after their latency
+        match event {
+            ScheduledEvent::Activate(id) => {
+                let agent = self.agents.get_mut(&id)?;
+                // The social signal is sampled once per tick however often the agent acts
+                if self.looked.insert(id) {
+                    agent.update_social_signal(self.graph.signal(id, tick));
+                }
+                agent.update_book_top(self.book.best_bid_price(), self.book.best_ask_price());
+                agent.update_book_pressure(self.book.imbalance(BOOK_PRESSURE_WINDOW_BPS));
+                agent.update_conditions(self.regime.conditions());
+                let orders = agent.act(self.current_price);
+                self.scheduler.send(agent, time, agent.requotes(), orders, &mut self.rng);
+                if let Some(state) = agent.manipulation.as_mut() {
+                    for mut record in state.take_activity() {
+                        record.timestamp = tick;
+                        if let Some(log) = self.manipulation_log.as_mut() {
+                            log.serialize(record).ok();
+                        }
+                    }
+                }
+                None
+            }
+            ScheduledEvent::CancelAll(id) => {
+                self.book.cancel_trader_orders(id);
+                None
+            }
+            ScheduledEvent::Instructions(id) => {
+                let agent = self.agents.get_mut(&id)?;
+                for instruction in std::mem::take(&mut agent.inbox) {
+                    let event = apply_instruction(agent, instruction, &mut self.book, tick);
+                    notify(id, event, &self.sessions, &mut self.game);
+                }
+                None
+            }
+            ScheduledEvent::Arrive(mut order) => {
+                // The sender may have been removed while the order was in flight
+                if self.agents.contains_key(&order.trader_id) {
+                    order.timestamp = tick;
+                    self.book.add_order(order);
+                }
+                None
+            }
+            ScheduledEvent::TickEnd => {
+                let crashes = self.stats.crashes();
+                self.close_tick();
+                if let Some(stop) = self.stop {
+                    let reached = match stop {
+                        StopCondition::Tick { tick: until } => tick >= until,
+                        StopCondition::PriceAbove { price } => self.current_price >= price,
+                        StopCondition::PriceBelow { price } => self.current_price <= price,
+                        StopCondition::Halt => self.stats.crashes() > crashes
+                            || self.book.best_bid_price().is_none()
+                            || self.book.best_ask_price().is_none(),
+                    };
+                    if reached {
+                        say!("Tick {}: Stopped on {:?} @ ${:.2}", tick, stop, self.current_price as f64 / 100.0);
+                        self.paused = true;
+                        self.stop = None;
+                    }
+                }
+                self.tick += 1;
+                self.looked.clear();
+                self.scheduler.begin_tick(self.tick, &self.agents, &self.agent_ids, self.regime.conditions().activity, &mut self.rng);
+                Some(tick)
+            }
+        }
+    }
+
+    /// End-of-tick bookkeeping: shocks, settlement, margin, population,
+    /// surveillance and the tick's market data.
+    fn close_tick(&mut self) {
+        let tick = self.tick;
+
+        // Scenario shocks land before the tick's trades settle
+        while let Some(scheduled) = self.shocks.get(self.next_shock).filter(|s| s.tick <= tick) {
+            say!("Tick {}: Scenario shock {:?}", tick, scheduled.shock);
+            let cmd = scheduled.shock.command();
+            self.god_mode(&cmd);
+            self.next_shock += 1;
+        }
+
+        // The fundamental moves as the regime has it
+        self.regime.step(&mut self.rng);
+
+        // Process trades
+        let open = self.current_price;
+        let mut new_trades = self.book.drain_trades();
+        self.settle_trades(&new_trades);
+
+        // Margin: charge borrowing costs, then force-close accounts below maintenance
+        for id in &self.agent_ids {
+            let Some(agent) = self.agents.get_mut(id) else { continue };
+            let Some((side, quantity)) = agent.margin_call(self.current_price) else { continue };
+            let equity = agent.equity(self.current_price);
+            let unfilled = self.book.add_market_order(Order {
+                id: self.rng.gen(),
+                trader_id: *id,
+                side,
+                price: 0,
+                amount: quantity,
+                timestamp: tick,
+            });
+            say!("Tick {}: LIQUIDATION {} {:?} {}/{} units filled (equity ${:.2})", tick, agent.name, side, quantity - unfilled, quantity, equity as f64 / 100.0);
+            self.stats.liquidations += 1;
+            self.sink.emit(ServerMessage::Liquidation {
+                trader_id: *id,
+                side,
+                quantity,
+                filled: quantity - unfilled,
+                equity,
+            });
+        }
+
+        // Liquidations trade in the tick that forced them
+        let liquidations = self.book.drain_trades();
+        self.settle_trades(&liquidations);
+        new_trades.extend(liquidations);
+        self.bar = Bar::from_trades(open, &new_trades);
+
+        self.graph.expire(tick);
+
+        // Population: bankruptcies, new entrants and, between generations, selection
+        let events = self.population.on_tick(tick, self.current_price, &mut self.agents, &mut self.agent_ids, &mut self.rng);
+        self.settle(&events);
+        if let Some(report) = events.report {
+            say!("--- Generation {} @ tick {}: {} agents, {} bankrupt, {} entrants, {} offspring ---",
+                report.generation, tick, report.population, report.bankruptcies, report.entrants, report.offspring);
+            self.sink.emit(ServerMessage::Generation(report));
+        }
+
+        // Every agent's history and indicators take the closed tick, whether it acted or not
+        for agent in self.agents.values_mut() {
+            agent.update_market_data(self.bar);
+        }
+
+        // Surveillance over everything that hit the book since last tick
+        for alert in self.surveillance.on_tick(tick, &self.book.drain_events(), self.current_price) {
+            say!("Tick {}: ALERT {:?} {:?}: {}", tick, alert.kind, alert.trader_ids, alert.detail);
+            self.stats.alerts += 1;
+            self.sink.emit(ServerMessage::Alert(alert));
+        }
+
+        self.stats.record_tick(self.current_price, self.book.best_bid_price(), self.book.best_ask_price(), &new_trades);
+
+        // Broadcast Ticker (every tick)
+        self.sink.emit(ServerMessage::Ticker {
+            price: self.current_price,
+            tick,
+            best_bid: self.book.best_bid_price().unwrap_or(0),
+            best_ask: self.book.best_ask_price().unwrap_or(0),
+        });
+
+        let interval = self.game.config().leaderboard_interval;
+        if interval > 0 && !self.game.is_empty() && tick.is_multiple_of(interval) {
+            let players = self.game.leaderboard(&self.agents, self.current_price);
+            self.sink.emit(ServerMessage::Leaderboard { tick, players });
+        }
+
+        // Broadcast belief composition so boom/bust cycles can be charted
+        let (chartists, fundamentalists) = self.agents.values()
+            .filter_map(|a| a.belief.as_ref())
+            .fold((0, 0), |(c, f), b| match b.rule {
+                BeliefRule::Chartist => (c + 1, f),
+                BeliefRule::Fundamentalist => (c, f + 1),
+            });
+        if chartists + fundamentalists > 0 {
+            self.sink.emit(ServerMessage::Beliefs { tick, chartists, fundamentalists });
+        }
+
+        // The logs are whole up to the last closed tick, whenever the process stops
+        if let Some(log) = self.trade_log.as_mut() {
+            log.flush().ok();
+        }
+        if let Some(log) = self.manipulation_log.as_mut() {
+            log.flush().ok();
+        }
+    }
+
+    /// Carry out a command. It takes effect before the next event.
+    pub fn apply(&mut self, cmd: SimulationCommand) {
+        match cmd {
+            SimulationCommand::SpawnScripted { .. } | SimulationCommand::ReloadScripts { .. } => {
+                let events = self.handle_script_command(&cmd);
+                self.settle(&events);
+            }
+            SimulationCommand::RegisterSession { .. } | SimulationCommand::SessionInstruction { .. } | SimulationCommand::CloseSession { .. } => {
+                let events = self.handle_session_command(&cmd);
+                self.settle(&events);
+            }
+            SimulationCommand::JoinGame { .. } | SimulationCommand::QueryPlayer { .. } => {
+                let events = self.handle_game_command(&cmd);
+                self.settle(&events);
+            }
+            SimulationCommand::QueryAlerts { since, kind, reply } => {
+                let _ = reply.send(self.surveillance.alerts(since, kind));
+            }
+            SimulationCommand::QueryRegime { reply } => {
+                let _ = reply.send(RegimeStatus { setting: self.regime, conditions: self.regime.conditions() });
+            }
+            SimulationCommand::LinkAccounts { trader_ids } => {
+                say!("Surveillance: linked accounts {:?}", trader_ids);
+                self.surveillance.link(&trader_ids);
+            }
+            SimulationCommand::SetActivation { kind, profile } => {
+                say!("God Mode: {} now act {} with {:.2} ticks latency",
+                    kind.as_deref().unwrap_or("agents"),
+                    profile.rate.map(|r| format!("{:.2}x per tick", r)).unwrap_or_else(|| "once per tick".to_string()),
+                    profile.latency);
+                self.scheduler.set_profile(kind.as_deref(), profile);
+            }
+            SimulationCommand::SetPaused(paused) => {
+                self.paused = paused;
+                say!("God Mode: Simulation {}", if paused { "PAUSED" } else { "RESUMED" });
+            }
+            SimulationCommand::Step { ticks } => {
+                self.paused = true;
+                self.stop = None;
+                for _ in 0..ticks {
+                    self.step();
+                }
+                say!("God Mode: Stepped {} ticks to tick {}", ticks, self.tick);
+            }
+            SimulationCommand::RunUntil(stop) => {
+                say!("God Mode: Running until {:?}", stop);
+                self.stop = Some(stop);
+                self.paused = false;
+            }
+            SimulationCommand::Reset { seed } => {
+                // Without a seed, carry on from the current stream
+                let seed = seed.unwrap_or_else(|| self.rng.gen());
+                self.reset(seed);
+            }
+            SimulationCommand::LoadScenario(next) => self.load_scenario(*next),
+            SimulationCommand::SaveSnapshot { reply } => {
+                let _ = reply.send(self.snapshot());
+            }
+            SimulationCommand::LoadSnapshot(snapshot) => match Simulation::restore(&snapshot) {
+                Ok(next) => {
+                    say!("God Mode: Loaded snapshot at tick {} ({} agents)", next.tick, next.agents.len());
+                    // Dropping the old state closes its trading sessions
+                    let sink = std::mem::replace(&mut self.sink, Box::new(()));
+                    *self = Self {
+                        paused: self.paused,
+                        sink,
+                        trade_log: self.trade_log.take(),
+                        manipulation_log: self.manipulation_log.take(),
+                        ..next
+                    };
+                }
+                Err(e) => say!("God Mode: Snapshot not loaded: {}", e),
+            },
+            cmd => self.god_mode(&cmd),
+        }
+    }
+
+    /// Swap in a new scenario under the run's seed. Trading sessions and game
+    /// players stay; the scenario's agents replace everyone else.
+    fn load_scenario(&mut self, next: Scenario) {
+        let name = next.name.clone();
+        match self.restart(next, self.seed) {
+            Ok(()) => say!("God Mode: Loaded scenario {} ({} agents)", name, self.agents.len()),
+            Err(e) => say!("God Mode: Scenario {} not loaded: {}", name, e),
+        }
+    }
+
+    /// Back to tick 1 under `seed`, trading exactly as a new run of the
+    /// scenario would.
+    fn reset(&mut self, seed: u64) {
+        say!("God Mode: Resetting simulation with seed {}", seed);
+        if let Err(e) = self.restart(self.scenario.clone(), seed) {
+            say!("God Mode: Reset failed, carrying on: {}", e);
+        }
+    }
+
+    /// Start `scenario` afresh under `seed`. Trading sessions and game players
+    /// keep their agents, back on their starting balances; activation
+    /// profiles, the regime and surveillance's linked accounts carry over.
+    /// Everything else is rebuilt as `new` builds it.
+    fn restart(&mut self, scenario: Scenario, seed: u64) -> Result<(), String> {
+        let kept = self.agents.values()
+            .filter(|a| self.sessions.contains_key(&a.id) || self.game.is_player(a.id))
+            .map(|a| Agent::new(a.id, a.name.clone(), a.starting_cash, a.starting_inventory, Strategy::External))
+            .collect();
+        let mut regime = self.regime;
+        regime.restart();
+        let fresh = Self::build(scenario, seed, kept, self.scheduler.config().clone(), regime)?;
+        // Dropping the old state leaves the sessions and game, which are moved over
+        let sink = std::mem::replace(&mut self.sink, Box::new(()));
+        *self = Self {
+            sessions: std::mem::take(&mut self.sessions),
+            game: std::mem::replace(&mut self.game, Game::new(GameConfig::default())),
+            surveillance: self.surveillance.restart(),
+            paused: self.paused,
+            sink,
+            trade_log: self.trade_log.take(),
+            manipulation_log: self.manipulation_log.take(),
+            ..fresh
+        };
+        self.game.restart(&self.agents, self.current_price);
+        Ok(())
+    }
+
+    /// Book `trades` to both sides, log and broadcast them, and move the last price.
+    fn settle_trades(&mut self, trades: &[Trade]) {
+        for trade in trades {
+            if let Some(buyer) = self.agents.get_mut(&trade.buyer_id) { buyer.on_trade(trade); }
+            if let Some(seller) = self.agents.get_mut(&trade.seller_id) { seller.on_trade(trade); }
+            self.graph.record_trade(trade);
+            report_execution(trade, &self.agents, &self.sessions, &mut self.game);
+
+            // Log to CSV
+            if let Some(log) = self.trade_log.as_mut() {
+                log.serialize(trade).ok();
+            }
+
+            // Broadcast Trade
+            self.sink.emit(ServerMessage::Trade {
+                price: trade.price,
+                quantity: trade.amount,
+                buyer_id: trade.buyer_id,
+                seller_id: trade.seller_id,
+            });
+
+            say!("Tick {}: Trade @ ${:.2} ({} units)", self.tick, trade.price as f64 / 100.0, trade.amount);
+            self.current_price = trade.price;
+        }
+    }
+
+    /// Wire agents that joined or left into the book and the social graph.
+    fn settle(&mut self, events: &PopulationEvents) {
+        self.population.settle(events, &mut self.book, &mut self.graph, &mut self.rng);
+    }
+
+    /// Market shocks and order injection from God Mode and scenario schedules.
+    fn god_mode(&mut self, cmd: &SimulationCommand) {
+        let tick = self.tick;
+        let current_price = self.current_price;
+        let book = &mut self.book;
+        let rng = &mut self.rng;
+        match cmd {
+            SimulationCommand::InjectOrder(order) => {
+                say!("God Mode: Injecting order from {:?}", order.side);
+                // Ids come from the run's stream so seeded runs stay reproducible
+                book.add_order(Order { id: rng.gen(), ..*order });
+            }
+            SimulationCommand::FlashCrash { seller_id, quantity, price } => {
+                say!("God Mode: FLASH CRASH incoming! {} units @ ${:.2}", quantity, *price as f64 / 100.0);
+                let crash_order = Order {
+                    id: rng.gen(),
+                    trader_id: *seller_id,
+                    side: OrderSide::Ask,
+                    price: *price,
+                    amount: *quantity,
+                    timestamp: tick,
+                };
+                book.add_order(crash_order);
+            }
+            SimulationCommand::UpdateVolatility(multiplier) => {
+                say!("God Mode: Volatility {:.2}x in the {:?} regime", multiplier, self.regime.regime);
+                self.regime.volatility = *multiplier;
+            }
+            SimulationCommand::SetRegime(regime) => {
+                say!("God Mode: {:?} regime", regime);
+                self.regime.set(*regime);
+            }
+            SimulationCommand::Pump { buyer_id, base_price, magnitude } => {
+                say!("God Mode: PUMP initiated! Creating buy pressure (magnitude: {:.1}x)", magnitude);
+                let start_price = if *base_price == 0 {
+                    current_price + 200
+                } else {
+                    *base_price
+                };
+                let quantity = (2000.0 * magnitude) as u64;
+                for i in 0..5 {
+                    let price = start_price + (i * 50);
+                    let order = Order {
+                        id: rng.gen(),
+                        trader_id: *buyer_id,
+                        side: OrderSide::Bid,
+                        price,
+                        amount: quantity,
+                        timestamp: tick,
+                    };
+                    book.add_order(order);
+                }
+            }
+            SimulationCommand::Dump { seller_id, base_price, magnitude } => {
+                say!("God Mode: DUMP initiated! Creating sell pressure (magnitude: {:.1}x)", magnitude);
+                let start_price = if *base_price == 0 {
+                    current_price.saturating_sub(200)
+                } else {
+                    *base_price
+                };
+                let quantity = (2000.0 * magnitude) as u64;
+                for i in 0..5 {
+                    let price = start_price.saturating_sub(i * 50);
+                    let order = Order {
+                        id: rng.gen(),
+                        trader_id: *seller_id,
+                        side: OrderSide::Ask,
+                        price: price.max(1),
+                        amount: quantity,
+                        timestamp: tick,
+                    };
+                    book.add_order(order);
+                }
+            }
+            SimulationCommand::Earnings { surprise_pct } => {
+                say!("God Mode: EARNINGS announced! Surprise: {:.1}%", surprise_pct);
+                let trader_id = 999_999;
+
+                if *surprise_pct > 0.0 {
+                    let magnitude = (surprise_pct.abs() * 30.0) as u64;
+                    for i in 0..5 {
+                        let price = current_price + (i * 100) + 300;
+                        let order = Order {
+                            id: rng.gen(),
+                            trader_id,
+                            side: OrderSide::Bid,
+                            price,
+                            amount: (1500 + magnitude * 10).min(5000),
+                            timestamp: tick,
+                        };
+                        book.add_order(order);
+                    }
+                } else {
+                    let magnitude = (surprise_pct.abs() * 30.0) as u64;
+                    for i in 0..5 {
+                        let price = current_price.saturating_sub((i * 100) + 300);
+                        let order = Order {
+                            id: rng.gen(),
+                            trader_id,
+                            side: OrderSide::Ask,
+                            price: price.max(1),
+                            amount: (1500 + magnitude * 10).min(5000),
+                            timestamp: tick,
+                        };
+                        book.add_order(order);
+                    }
+                }
+            }
+            SimulationCommand::Tariffs { severity } => {
+                say!("God Mode: TARIFFS announced! Severity: {:.1}", severity);
+                let trader_id = 999_998;
+
+                let quantity = (1000.0 + severity * 500.0) as u64;
+                let price_impact = (severity * 100.0) as u64;
+
+                for i in 0..7 {
+                    let price = current_price.saturating_sub(price_impact + (i * 50));
+                    let order = Order {
+                        id: rng.gen(),
+                        trader_id,
+                        side: OrderSide::Ask,
+                        price: price.max(1),
+                        amount: quantity,
+                        timestamp: tick,
+                    };
+                    book.add_order(order);
+                }
+            }
+            SimulationCommand::RugPull { magnitude } => {
+                say!("God Mode: RUG PULL! Magnitude: {:.1}x", magnitude);
+                let trader_id = 999_997;
+
+                let quantity = (3000.0 * magnitude) as u64;
+                let crash_price = ((current_price as f64) * (1.0 - 0.15 * magnitude)) as u64;
+
+                for i in 0..10 {
+                    let price = crash_price.saturating_sub(i * 20);
+                    let order = Order {
+                        id: rng.gen(),
+                        trader_id,
+                        side: OrderSide::Ask,
+                        price: price.max(1),
+                        amount: quantity,
+                        timestamp: tick,
+                    };
+                    book.add_order(order);
+                }
+            }
+            SimulationCommand::WhaleAccumulation { magnitude } => {
+                say!("God Mode: WHALE ACCUMULATION detected! Magnitude: {:.1}x", magnitude);
+                let trader_id = 999_996;
+
+                let quantity = (2000.0 * magnitude) as u64;
+                let premium = (50.0 * magnitude) as u64;
+
+                for i in 0..8 {
+                    let price = current_price + premium + (i * 20);
+                    let order = Order {
+                        id: rng.gen(),
+                        trader_id,
+                        side: OrderSide::Bid,
+                        price,
+                        amount: quantity,
+                        timestamp: tick,
+                    };
+                    book.add_order(order);
+                }
+            }
+            SimulationCommand::SeedRumour { node, side, strength, duration, reply } => {
+                let seeded = match node.or_else(|| self.graph.top_influencer()) {
+                    Some(node) => self.graph.seed_rumour(node, *side, *strength, *duration, tick).map(|()| node),
+                    None => Err("the social graph is empty".to_string()),
+                };
+                match &seeded {
+                    Ok(node) => say!("God Mode: RUMOUR seeded at agent {} ({:?}, strength {:.1}, {} ticks)", node, side, strength, duration),
+                    Err(e) => say!("God Mode: Rumour ignored, {}", e),
+                }
+                let _ = reply.send(seeded);
+            }
+            // Everything else is routed by `apply`, or needs the wall clock
+            _ => {}
+        }
+    }
+
+    fn handle_script_command(&mut self, cmd: &SimulationCommand) -> PopulationEvents {
+        let mut events = PopulationEvents::default();
+        match cmd {
+            SimulationCommand::SpawnScripted { path, count, cash, inventory } => {
+                let handle = match self.scripts.handle(path) {
+                    Ok(handle) => handle,
+                    Err(e) => {
+                        say!("God Mode: Could not load script {}", e);
+                        return events;
+                    }
+                };
+                for _ in 0..*count {
+                    let id = self.population.next_id();
+                    let agent = Agent::new(id, format!("Script_{}", id), *cash, *inventory, Strategy::Scripted { path: path.clone() })
+                        .with_script(handle.clone());
+                    self.population.admit(agent, &mut self.agents, &mut self.agent_ids, self.current_price);
+                    events.added.push(id);
+                }
+                say!("God Mode: Spawned {} agents running {}", count, path);
+            }
+            SimulationCommand::ReloadScripts { path } => {
+                for (path, result) in self.scripts.reload(path.as_deref()) {
+                    match result {
+                        Ok(()) => say!("God Mode: Reloaded script {}", path),
+                        Err(e) => say!("God Mode: Reload failed, keeping previous version of {}", e),
+                    }
+                }
+            }
+            _ => {}
+        }
+        events
+    }
+
+    fn handle_session_command(&mut self, cmd: &SimulationCommand) -> PopulationEvents {
+        let mut events = PopulationEvents::default();
+        match cmd {
+            SimulationCommand::RegisterSession { name, cash, inventory, reply } => {
+                let id = self.population.next_id();
+                let agent = Agent::new(id, name.clone(), *cash, *inventory, Strategy::External);
+                self.population.admit(agent, &mut self.agents, &mut self.agent_ids, self.current_price);
+                events.added.push(id);
+                self.sessions.insert(id, reply.clone());
+                let _ = reply.send(SessionEvent::Registered { agent_id: id, cash: *cash, inventory: *inventory });
+                say!("Session: {} connected as agent {}", name, id);
+            }
+            SimulationCommand::SessionInstruction { trader_id, instruction } => {
+                if let Some(agent) = self.agents.get_mut(trader_id) {
+                    agent.inbox.push(*instruction);
+                }
+            }
+            SimulationCommand::CloseSession { trader_id } => {
+                self.sessions.remove(trader_id);
+                // The agent leaves with its session; a reconnect gets a new one
+                let session = self.agents.get(trader_id)
+                    .filter(|a| matches!(a.strategy, Strategy::External) && !self.game.is_player(*trader_id));
+                if let Some(agent) = session {
+                    let pulled = self.book.cancel_trader_orders(*trader_id);
+                    say!("Session: {} disconnected, {} resting orders cancelled", agent.name, pulled);
+                    self.population.remove(*trader_id, &mut self.agents, &mut self.agent_ids);
+                    events.removed.push(*trader_id);
+                }
+            }
+            _ => {}
+        }
+        events
+    }
+
+    fn handle_game_command(&mut self, cmd: &SimulationCommand) -> PopulationEvents {
+        let mut events = PopulationEvents::default();
+        match cmd {
+            SimulationCommand::JoinGame { name, reply } => {
+                let id = self.population.next_id();
+                let config = self.game.config();
+                let agent = Agent::new(id, name.clone(), config.starting_cash, config.starting_inventory, Strategy::External);
+                self.game.add_player(&agent, self.current_price);
+                self.population.admit(agent, &mut self.agents, &mut self.agent_ids, self.current_price);
+                events.added.push(id);
+                if let Some(standing) = self.game.leaderboard(&self.agents, self.current_price).into_iter().find(|s| s.player_id == id) {
+                    let _ = reply.send(standing);
+                }
+                say!("Game: {} joined as player {}", name, id);
+            }
+            SimulationCommand::QueryPlayer { player_id, reply } => {
+                let _ = reply.send(self.game.report(*player_id, &self.agents, &self.book, self.current_price));
+            }
+            _ => {}
+        }
+        events
+    }
+}
+
+/// What `snapshot` writes, borrowed from the live simulation.
+#[derive(Serialize)]
+struct SnapshotRef<'a> {
+    version: u32,
+    scenario: &'a Scenario,
+    seed: u64,
+    rng: &'a SimRng,
+    book: &'a OrderBook,
+    agents: &'a HashMap<u64, Agent>,
+    agent_ids: &'a [u64],
+    /// Script agents' `this`, by agent id
+    scripts: HashMap<u64, &'a Dynamic>,
+    graph: &'a SocialGraph,
+    current_price: u64,
+    population: &'a PopulationManager,
+    game: &'a Game,
+    surveillance: &'a Surveillance,
+    scheduler: &'a Scheduler,
+    regime: MarketRegime,
+    shocks: &'a [ScheduledShock],
+    next_shock: usize,
+    stats: &'a RunStats,
+    looked: &'a HashSet<u64>,
+    bar: Bar,
+    tick: u64,
+    stop: Option<StopCondition>,
+}
+
+/// What `restore` reads: the same fields, owned.
+#[derive(Deserialize)]
+struct Snapshot {
+    version: u32,
+    scenario: Scenario,
+    seed: u64,
+    rng: SimRng,
+    book: OrderBook,
+    agents: HashMap<u64, Agent>,
+    agent_ids: Vec<u64>,
+    scripts: HashMap<u64, Dynamic>,
+    graph: SocialGraph,
+    current_price: u64,
+    population: PopulationManager,
+    game: Game,
+    surveillance: Surveillance,
+    scheduler: Scheduler,
+    regime: MarketRegime,
+    shocks: Vec<ScheduledShock>,
+    next_shock: usize,
+    stats: RunStats,
+    looked: HashSet<u64>,
+    bar: Bar,
+    tick: u64,
+    stop: Option<StopCondition>,
+}
+
+/// The scenario's population dynamics, with entrants on its default balances.
+fn population_config(scenario: &Scenario) -> PopulationConfig {
+    PopulationConfig {
+        entry_interval: scenario.population.entry_interval,
+        bankruptcy_equity: scenario.population.bankruptcy_equity,
+        entrant_cash: scenario.defaults.cash,
+        entrant_inventory: scenario.defaults.inventory,
+        genetic: scenario.population.genetic,
+        ..PopulationConfig::default()
+    }
+}
+
+/// Carry out one queued session instruction on the agent's turn.
+fn apply_instruction(agent: &Agent, instruction: ExternalInstruction, book: &mut OrderBook, tick: u64) -> SessionEvent {
+    match instruction {
+        ExternalInstruction::Submit(mut order) => {
+            let (allowed, reason) = match order.side {
+                OrderSide::Bid => (agent.can_buy(order.price, order.amount), "insufficient cash"),
+                OrderSide::Ask => (agent.can_sell(order.price, order.amount), "insufficient inventory"),
+            };
+            if !allowed {
+                return SessionEvent::Rejected { order_id: order.id, reason: reason.to_string() };
+            }
+            order.timestamp = tick;
+            book.add_order(order);
+            SessionEvent::Accepted { order_id: order.id }
+        }
+        ExternalInstruction::Cancel { order_id } => match book.cancel_order(agent.id, order_id) {
+            Some(order) => SessionEvent::Cancelled { order_id, remaining: order.amount },
+            None => SessionEvent::Rejected { order_id, reason: "no resting order with that id".to_string() },
+        },
+    }
+}
+
+/// Pass an order event to whoever drives agent `id`: a trading session, a game player, or neither.
+fn notify(id: u64, event: SessionEvent, sessions: &HashMap<u64, mpsc::UnboundedSender<SessionEvent>>, game: &mut Game) {
+    game.record(id, &event);
+    if let Some(session) = sessions.get(&id) {
+        let _ = session.send(event);
+    }
+}
+
+/// Report a fill to each side of `trade` that is driven from outside.
+fn report_execution(
+    trade: &Trade,
+    agents: &HashMap<u64, Agent>,
+    sessions: &HashMap<u64, mpsc::UnboundedSender<SessionEvent>>,
+    game: &mut Game,
+) {
+    let sides = [
+        (trade.buyer_id, trade.buy_order_id, OrderSide::Bid),
+        (trade.seller_id, trade.sell_order_id, OrderSide::Ask),
+    ];
+    for (trader_id, order_id, side) in sides {
+        if !sessions.contains_key(&trader_id) && !game.is_player(trader_id) {
+            continue;
+        }
+        let Some(agent) = agents.get(&trader_id) else { continue };
+        let event = SessionEvent::Execution {
+            order_id,
+            side,
+            price: trade.price,
+            quantity: trade.amount,
+            cash: agent.cash,
+            inventory: agent.inventory,
+        };
+        notify(trader_id, event, sessions, game);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console;
+
+    /// Everything the market broadcasts over the next `ticks` ticks, as JSON.
+    fn feed(sim: Simulation, ticks: u64) -> Vec<String> {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut sim = sim.with_sink(tx);
+        for _ in 0..ticks {
+            sim.step();
+        }
+        drop(sim);
+        rx.iter().map(|message| serde_json::to_string(&message).unwrap()).collect()
+    }
+
+    #[test]
+    fn same_seed_same_market() {
+        console::set_thread_quiet(true);
+        let first = feed(Simulation::new(Scenario::default(), 11).unwrap(), 200);
+        assert!(first.iter().any(|m| m.contains(r#""type":"trade""#)));
+        assert_eq!(feed(Simulation::new(Scenario::default(), 11).unwrap(), 200), first);
+    }
+
+    #[test]
+    fn closed_session_leaves_the_market() {
+        console::set_thread_quiet(true);
+        let mut sim = Simulation::new(Scenario::default(), 7).unwrap();
+        let (reply, mut events) = mpsc::unbounded_channel();
+        sim.apply(SimulationCommand::RegisterSession { name: "bot".to_string(), cash: 100000, inventory: 10, reply });
+        let Ok(SessionEvent::Registered { agent_id, .. }) = events.try_recv() else { panic!("not registered") };
+        sim.step();
+
+        sim.apply(SimulationCommand::CloseSession { trader_id: agent_id });
+        assert!(!sim.agents().contains_key(&agent_id));
+        sim.step();
+        sim.apply(SimulationCommand::Reset { seed: Some(7) });
+        assert!(!sim.agents().contains_key(&agent_id));
+    }
+
+    #[test]
+    fn sessions_trade_whatever_the_schedule() {
+        use crate::regime::Regime;
+        use crate::scheduling::ActivationProfile;
+
+        console::set_thread_quiet(true);
+        let mut sim = Simulation::new(Scenario::default(), 7).unwrap();
+        sim.apply(SimulationCommand::SetActivation { kind: Some("External".to_string()), profile: ActivationProfile { rate: Some(0.0), ..ActivationProfile::default() } });
+        sim.apply(SimulationCommand::SetRegime(Regime::Calm));
+        let (reply, mut events) = mpsc::unbounded_channel();
+        sim.apply(SimulationCommand::RegisterSession { name: "bot".to_string(), cash: 100000, inventory: 10, reply });
+        let Ok(SessionEvent::Registered { agent_id, .. }) = events.try_recv() else { panic!("not registered") };
+        sim.step();
+
+        for tick in 0..20 {
+            let order = Order { id: tick, trader_id: agent_id, side: OrderSide::Bid, price: 1, amount: 1, timestamp: 0 };
+            sim.apply(SimulationCommand::SessionInstruction { trader_id: agent_id, instruction: ExternalInstruction::Submit(order) });
+            sim.step();
+            assert!(matches!(events.try_recv(), Ok(SessionEvent::Accepted { .. })));
+        }
+    }
+
+    #[test]
+    fn reset_trades_as_a_fresh_run() {
+        console::set_thread_quiet(true);
+        let fresh = feed(Simulation::new(Scenario::default(), 7).unwrap(), 200);
+        assert!(fresh.iter().any(|m| m.contains(r#""type":"trade""#)));
+
+        let mut sim = Simulation::new(Scenario::default(), 7).unwrap();
+        for _ in 0..300 {
+            sim.step();
+        }
+        sim.apply(SimulationCommand::Reset { seed: Some(7) });
+        assert_eq!(feed(sim, 200), fresh);
+    }
+}
@@ -1,7 +1,9 @@
-use tokio::sync::{broadcast, mpsc};
-use crate::types::ServerMessage;
+use std::collections::HashSet;
+use crate::sessions::Sessions;
 
 pub struct AppState {
-    pub tx: broadcast::Sender<ServerMessage>,
-    pub cmd_tx: mpsc::UnboundedSender<crate::types::SimulationCommand>,
+    /// Every running simulation; routes without a session prefix use `default`
+    pub sessions: Sessions,
+    /// Tokens accepted by `/ws/trade` logins
+    pub trading_tokens: HashSet<String>,
 }
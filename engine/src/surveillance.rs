@@ -0,0 +1,455 @@
+//! Market surveillance over the order book's event stream.
+//!
+//! Fed the `BookEvent`s of each tick, it looks for:
+//!
+//! - Wash trades: an account trading with itself or with an account it is
+//!   linked to, above `wash_min_volume` within the window.
+//! - Spoofing: lots of order volume for very little traded, with most orders
+//!   cancelled within `fast_cancel_ticks` of being placed and at least
+//!   `min_cancelled_volume` pulled in all.
+//! - Layering: `min_layers` or more orders at different prices on one side,
+//!   placed and pulled together, while the same account traded on the other side.
+//! - Pump and dump: a run-up of `pump_pct` and a drop of `dump_pct` off the top,
+//!   with accounts that bought the run-up selling into the drop.
+//!
+//! Detection only sees what a real exchange would: orders, cancels and trades.
+//! It never looks at agent strategies or the manipulators' own tags.
+
+use crate::orderbook::BookEvent;
+use crate::types::{Order, OrderSide, Trade};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SurveillanceConfig {
+    /// Lookback for every detector, in ticks
+    pub window_ticks: u64,
+    pub wash_min_volume: u64,
+    /// Orders placed in the window before the order-to-trade check applies
+    pub min_orders: u64,
+    /// Units cancelled in the window before the order-to-trade check applies,
+    /// so accounts that merely refresh small quotes are left alone
+    pub min_cancelled_volume: u64,
+    /// Placed volume per unit traded
+    pub order_to_trade_ratio: f64,
+    pub fast_cancel_ticks: u64,
+    /// Share of cancels that must be fast for a spoofing alert
+    pub fast_cancel_share: f64,
+    pub min_layers: usize,
+    pub pump_pct: f64,
+    pub dump_pct: f64,
+    /// Units an account must have bought into the run-up to be a suspect
+    pub pump_min_volume: u64,
+    /// Ticks before the same account can raise the same alert again
+    pub cooldown_ticks: u64,
+    /// Alerts kept for the REST endpoint
+    pub history: usize,
+}
+
+impl Default for SurveillanceConfig {
+    fn default() -> Self {
+        Self {
+            window_ticks: 50,
+            wash_min_volume: 10,
+            min_orders: 20,
+            min_cancelled_volume: 1000,
+            order_to_trade_ratio: 20.0,
+            fast_cancel_ticks: 2,
+            fast_cancel_share: 0.8,
+            min_layers: 3,
+            pump_pct: 3.0,
+            dump_pct: 2.0,
+            pump_min_volume: 20,
+            cooldown_ticks: 100,
+            history: 500,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    WashTrade,
+    Spoofing,
+    Layering,
+    PumpAndDump,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alert {
+    pub id: u64,
+    pub tick: u64,
+    pub kind: AlertKind,
+    pub trader_ids: Vec<u64>,
+    /// Detector-specific strength: wash volume, order-to-trade ratio, layer
+    /// count, or run-up in percent
+    pub score: f64,
+    pub detail: String,
+}
+
+/// One account's activity in one tick.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct Activity {
+    tick: u64,
+    orders: u64,
+    order_volume: u64,
+    cancels: u64,
+    cancelled_volume: u64,
+    fast_cancels: u64,
+    traded: u64,
+    bought: u64,
+    sold: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Surveillance {
+    config: SurveillanceConfig,
+    activity: BTreeMap<u64, VecDeque<Activity>>, // ordered, so alerts come out in a stable order
+    /// (account, side) -> last tick it traded on that side
+    #[serde(with = "pairs")]
+    last_fill: HashMap<(u64, OrderSide), u64>,
+    #[serde(with = "pairs")]
+    wash: HashMap<(u64, u64), VecDeque<(u64, u64)>>, // pair -> (tick, amount)
+    prices: VecDeque<(u64, u64)>, // (tick, last price)
+    last_pump_peak: Option<u64>,
+    groups: HashMap<u64, u64>, // account -> linked group
+    next_group: u64,
+    #[serde(with = "pairs")]
+    cooldowns: HashMap<(AlertKind, u64), u64>,
+    alerts: VecDeque<Alert>,
+    next_alert: u64,
+}
+
+impl Surveillance {
+    pub fn new(config: SurveillanceConfig) -> Self {
+        Self {
+            config,
+            activity: BTreeMap::new(),
+            last_fill: HashMap::new(),
+            wash: HashMap::new(),
+            prices: VecDeque::new(),
+            last_pump_peak: None,
+            groups: HashMap::new(),
+            next_group: 0,
+            cooldowns: HashMap::new(),
+            alerts: VecDeque::new(),
+            next_alert: 1,
+        }
+    }
+
+    /// A fresh monitor for a restarted market: no history or alerts, but the
+    /// same config and linked accounts.
+    pub fn restart(&self) -> Self {
+        Self {
+            groups: self.groups.clone(),
+            next_group: self.next_group,
+            ..Self::new(self.config)
+        }
+    }
+
+    /// Treat these accounts as one beneficial owner for wash trade checks.
+    /// Linking an account that is already in a group merges the groups.
+    pub fn link(&mut self, trader_ids: &[u64]) {
+        let existing: HashSet<u64> = trader_ids.iter().filter_map(|id| self.groups.get(id).copied()).collect();
+        let group = existing.iter().min().copied().unwrap_or_else(|| {
+            self.next_group += 1;
+            self.next_group
+        });
+        for g in self.groups.values_mut() {
+            if existing.contains(g) {
+                *g = group;
+            }
+        }
+        for &id in trader_ids {
+            self.groups.insert(id, group);
+        }
+    }
+
+    fn linked(&self, a: u64, b: u64) -> bool {
+        a == b || matches!((self.groups.get(&a), self.groups.get(&b)), (Some(x), Some(y)) if x == y)
+    }
+
+    /// Most recent alerts first, optionally only those after `since` (an alert id) or of one kind.
+    pub fn alerts(&self, since: Option<u64>, kind: Option<AlertKind>) -> Vec<Alert> {
+        self.alerts.iter().rev()
+            .filter(|a| since.is_none_or(|s| a.id > s) && kind.is_none_or(|k| a.kind == k))
+            .cloned()
+            .collect()
+    }
+
+    /// Consume one tick of book events and return any new alerts.
+    pub fn on_tick(&mut self, tick: u64, events: &[BookEvent], last_price: u64) -> Vec<Alert> {
+        let mut raised = Vec::new();
+        let mut pulled: BTreeMap<(u64, OrderSide), Vec<Order>> = BTreeMap::new();
+
+        for event in events {
+            match *event {
+                BookEvent::Placed(order) => {
+                    let a = self.bucket(order.trader_id, tick);
+                    a.orders += 1;
+                    a.order_volume += order.amount;
+                }
+                BookEvent::Cancelled(order) => {
+                    let fast = tick.saturating_sub(order.timestamp) <= self.config.fast_cancel_ticks;
+                    let a = self.bucket(order.trader_id, tick);
+                    a.cancels += 1;
+                    a.cancelled_volume += order.amount;
+                    if fast {
+                        a.fast_cancels += 1;
+                        pulled.entry((order.trader_id, order.side)).or_default().push(order);
+                    }
+                }
+                BookEvent::Traded(trade) => {
+                    self.on_trade(tick, &trade, &mut raised);
+                }
+            }
+        }
+
+        for ((trader_id, side), orders) in pulled {
+            self.check_layering(tick, trader_id, side, &orders, &mut raised);
+        }
+        self.check_spoofing(tick, &mut raised);
+        self.check_pump_and_dump(tick, last_price, &mut raised);
+        self.prune(tick);
+
+        for alert in &raised {
+            self.alerts.push_back(alert.clone());
+            if self.alerts.len() > self.config.history {
+                self.alerts.pop_front();
+            }
+        }
+        raised
+    }
+
+    fn bucket(&mut self, trader_id: u64, tick: u64) -> &mut Activity {
+        let window = self.activity.entry(trader_id).or_default();
+        if window.back().is_none_or(|a| a.tick != tick) {
+            window.push_back(Activity { tick, ..Activity::default() });
+        }
+        window.back_mut().unwrap()
+    }
+
+    fn on_trade(&mut self, tick: u64, trade: &Trade, raised: &mut Vec<Alert>) {
+        for (id, bought, sold) in [(trade.buyer_id, trade.amount, 0), (trade.seller_id, 0, trade.amount)] {
+            let a = self.bucket(id, tick);
+            a.traded += trade.amount;
+            a.bought += bought;
+            a.sold += sold;
+        }
+        self.last_fill.insert((trade.buyer_id, OrderSide::Bid), tick);
+        self.last_fill.insert((trade.seller_id, OrderSide::Ask), tick);
+
+        if !self.linked(trade.buyer_id, trade.seller_id) {
+            return;
+        }
+        let pair = (trade.buyer_id.min(trade.seller_id), trade.buyer_id.max(trade.seller_id));
+        let history = self.wash.entry(pair).or_default();
+        history.push_back((tick, trade.amount));
+        let volume: u64 = history.iter().map(|&(_, amount)| amount).sum();
+        if volume < self.config.wash_min_volume {
+            return;
+        }
+        let (detail, ids) = if pair.0 == pair.1 {
+            (format!("account {} traded {} units with itself", pair.0, volume), vec![pair.0])
+        } else {
+            (format!("linked accounts {} and {} traded {} units with each other", pair.0, pair.1, volume), vec![pair.0, pair.1])
+        };
+        self.raise(raised, tick, AlertKind::WashTrade, ids, volume as f64, detail);
+    }
+
+    fn check_layering(&mut self, tick: u64, trader_id: u64, side: OrderSide, orders: &[Order], raised: &mut Vec<Alert>) {
+        let levels: HashSet<u64> = orders.iter().map(|o| o.price).collect();
+        if levels.len() < self.config.min_layers {
+            return;
+        }
+        // The layers only make sense as cover for a trade on the other side
+        let opposite = match side {
+            OrderSide::Bid => OrderSide::Ask,
+            OrderSide::Ask => OrderSide::Bid,
+        };
+        let placed = orders.iter().map(|o| o.timestamp).min().unwrap_or(tick);
+        if self.last_fill.get(&(trader_id, opposite)).is_none_or(|&filled| filled < placed) {
+            return;
+        }
+        let volume: u64 = orders.iter().map(|o| o.amount).sum();
+        let detail = format!(
+            "account {} pulled {} {:?} orders ({} units) across {} price levels after trading the other side",
+            trader_id, orders.len(), side, volume, levels.len()
+        );
+        self.raise(raised, tick, AlertKind::Layering, vec![trader_id], levels.len() as f64, detail);
+    }
+
+    fn check_spoofing(&mut self, tick: u64, raised: &mut Vec<Alert>) {
+        let mut suspects = Vec::new();
+        for (&trader_id, window) in &self.activity {
+            let total = window.iter().fold(Activity::default(), |t, a| Activity {
+                orders: t.orders + a.orders,
+                order_volume: t.order_volume + a.order_volume,
+                cancels: t.cancels + a.cancels,
+                cancelled_volume: t.cancelled_volume + a.cancelled_volume,
+                fast_cancels: t.fast_cancels + a.fast_cancels,
+                traded: t.traded + a.traded,
+                ..t
+            });
+            if total.orders < self.config.min_orders || total.cancels == 0 || total.cancelled_volume < self.config.min_cancelled_volume {
+                continue;
+            }
+            let ratio = total.order_volume as f64 / total.traded.max(1) as f64;
+            let fast_share = total.fast_cancels as f64 / total.cancels as f64;
+            if ratio >= self.config.order_to_trade_ratio && fast_share >= self.config.fast_cancel_share {
+                suspects.push((trader_id, ratio, fast_share, total));
+            }
+        }
+        for (trader_id, ratio, fast_share, total) in suspects {
+            let detail = format!(
+                "account {} placed {} units for {} traded (ratio {:.0}), {:.0}% of {} cancels within {} ticks",
+                trader_id, total.order_volume, total.traded, ratio, fast_share * 100.0, total.cancels, self.config.fast_cancel_ticks
+            );
+            self.raise(raised, tick, AlertKind::Spoofing, vec![trader_id], ratio, detail);
+        }
+    }
+
+    fn check_pump_and_dump(&mut self, tick: u64, last_price: u64, raised: &mut Vec<Alert>) {
+        if last_price == 0 {
+            return;
+        }
+        self.prices.push_back((tick, last_price));
+
+        let Some(&(peak_tick, peak)) = self.prices.iter().max_by_key(|&&(t, p)| (p, std::cmp::Reverse(t))) else { return };
+        if self.last_pump_peak == Some(peak_tick) || peak_tick == tick {
+            return;
+        }
+        let Some(&(trough_tick, trough)) = self.prices.iter().filter(|&&(t, _)| t < peak_tick).min_by_key(|&&(_, p)| p) else { return };
+        let rise = (peak as f64 / trough.max(1) as f64 - 1.0) * 100.0;
+        let fall = (1.0 - last_price as f64 / peak as f64) * 100.0;
+        if rise < self.config.pump_pct || fall < self.config.dump_pct {
+            return;
+        }
+
+        // Bought the run-up, then sold at least half of it back into the drop
+        let mut suspects: Vec<u64> = self.activity.iter()
+            .filter(|(_, window)| {
+                let net = |from: u64, to: u64| window.iter()
+                    .filter(|a| a.tick > from && a.tick <= to)
+                    .fold(0i64, |n, a| n + a.bought as i64 - a.sold as i64);
+                let pumped = net(trough_tick, peak_tick);
+                let dumped = -net(peak_tick, tick);
+                pumped >= self.config.pump_min_volume as i64 && dumped * 2 >= pumped
+            })
+            .map(|(&id, _)| id)
+            .collect();
+        if suspects.is_empty() {
+            return;
+        }
+        suspects.sort_unstable();
+        self.last_pump_peak = Some(peak_tick);
+        let detail = format!(
+            "price ran {:.1}% from ${:.2} to ${:.2} (ticks {}-{}) then fell {:.1}%; {} account(s) bought the run-up and sold the drop",
+            rise, trough as f64 / 100.0, peak as f64 / 100.0, trough_tick, peak_tick, fall, suspects.len()
+        );
+        self.raise(raised, tick, AlertKind::PumpAndDump, suspects, rise, detail);
+    }
+
+    fn raise(&mut self, raised: &mut Vec<Alert>, tick: u64, kind: AlertKind, trader_ids: Vec<u64>, score: f64, detail: String) {
+        let key = (kind, trader_ids.first().copied().unwrap_or(0));
+        if self.cooldowns.get(&key).is_some_and(|&at| tick < at + self.config.cooldown_ticks) {
+            return;
+        }
+        self.cooldowns.insert(key, tick);
+        raised.push(Alert { id: self.next_alert, tick, kind, trader_ids, score, detail });
+        self.next_alert += 1;
+    }
+
+    /// Forget everything older than the window.
+    fn prune(&mut self, tick: u64) {
+        let horizon = tick.saturating_sub(self.config.window_ticks);
+        self.activity.retain(|_, window| {
+            while window.front().is_some_and(|a| a.tick <= horizon) {
+                window.pop_front();
+            }
+            !window.is_empty()
+        });
+        self.wash.retain(|_, history| {
+            while history.front().is_some_and(|&(t, _)| t <= horizon) {
+                history.pop_front();
+            }
+            !history.is_empty()
+        });
+        while self.prices.front().is_some_and(|&(t, _)| t <= horizon) {
+            self.prices.pop_front();
+        }
+        self.cooldowns.retain(|_, &mut at| tick < at + self.config.cooldown_ticks);
+    }
+}
+
+/// Maps with tuple keys as lists of `[key, value]`, since JSON keys must be strings.
+mod pairs {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::HashMap;
+    use std::hash::Hash;
+
+    pub fn serialize<K: Serialize, V: Serialize, S: Serializer>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(map.iter())
+    }
+
+    pub fn deserialize<'de, K, V, D>(deserializer: D) -> Result<HashMap<K, V>, D::Error>
+    where
+        K: Deserialize<'de> + Eq + Hash,
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        Vec::<(K, V)>::deserialize(deserializer).map(|pairs| pairs.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(buyer_id: u64, seller_id: u64, amount: u64, tick: u64) -> BookEvent {
+        BookEvent::Traded(Trade { buyer_id, seller_id, price: 10000, amount, timestamp: tick, buy_order_id: 0, sell_order_id: 0 })
+    }
+
+    fn order(trader_id: u64, amount: u64, placed: u64) -> Order {
+        Order { id: trader_id * 1000 + placed, trader_id, side: OrderSide::Bid, price: 9990, amount, timestamp: placed }
+    }
+
+    fn kinds(alerts: &[Alert]) -> Vec<(AlertKind, Vec<u64>)> {
+        alerts.iter().map(|a| (a.kind, a.trader_ids.clone())).collect()
+    }
+
+    #[test]
+    fn self_trade_is_a_wash_trade() {
+        let mut surveillance = Surveillance::new(SurveillanceConfig::default());
+        let alerts = surveillance.on_tick(1, &[trade(7, 7, 10, 1), trade(1, 2, 10, 1)], 10000);
+        assert_eq!(kinds(&alerts), vec![(AlertKind::WashTrade, vec![7])]);
+    }
+
+    #[test]
+    fn linked_accounts_wash_trade() {
+        let mut surveillance = Surveillance::new(SurveillanceConfig::default());
+        surveillance.link(&[1, 2]);
+        // Under wash_min_volume, then over it
+        assert!(surveillance.on_tick(1, &[trade(1, 2, 4, 1)], 10000).is_empty());
+        let alerts = surveillance.on_tick(2, &[trade(2, 1, 6, 2)], 10000);
+        assert_eq!(kinds(&alerts), vec![(AlertKind::WashTrade, vec![1, 2])]);
+    }
+
+    #[test]
+    fn fast_cancels_are_spoofing_and_slow_ones_are_not() {
+        let mut surveillance = Surveillance::new(SurveillanceConfig::default());
+        let mut alerts = Vec::new();
+        for tick in 1..=30 {
+            // Account 9 pulls each order the tick after placing it, account 10 ten ticks after
+            let mut events = vec![BookEvent::Placed(order(9, 100, tick)), BookEvent::Placed(order(10, 100, tick))];
+            if tick > 1 {
+                events.push(BookEvent::Cancelled(order(9, 100, tick - 1)));
+            }
+            if tick > 10 {
+                events.push(BookEvent::Cancelled(order(10, 100, tick - 10)));
+            }
+            alerts.extend(surveillance.on_tick(tick, &events, 10000));
+        }
+        assert_eq!(kinds(&alerts), vec![(AlertKind::Spoofing, vec![9])]);
+    }
+}
@@ -0,0 +1,292 @@
+//! Parameter sweeps: many headless batches of one scenario, each with a few
+//! fields changed, boiled down to one row of outcome metrics per combination.
+//!
+//! A sweep file names the fields by path into the scenario JSON. Groups can be
+//! addressed by name as well as by index:
+//!
+//! ```json
+//! {
+//!   "scenario": "scenarios/bubble.json",
+//!   "ticks": 3000,
+//!   "runs": 8,
+//!   "seed": 1,
+//!   "mode": "grid",
+//!   "params": [
+//!     { "path": "groups.Trend.count", "values": [5, 15, 30, 60] },
+//!     { "path": "groups.MM.strategy.spread_bps", "range": [20, 200], "steps": 4 }
+//!   ]
+//! }
+//! ```
+//!
+//! `"mode": {"random": {"samples": 40}}` draws 40 combinations instead, each
+//! value uniform over its range (or picked from its values).
+
+use crate::batch::RunSummary;
+use crate::scenario::Scenario;
+use crate::types::SimRng;
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SweepSpec {
+    /// Base scenario file; the built-in scenario if omitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scenario: Option<String>,
+    pub ticks: u64,
+    /// Seeds per combination. Every combination gets the same seeds.
+    #[serde(default = "one")]
+    pub runs: usize,
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default)]
+    pub mode: SweepMode,
+    pub params: Vec<SweepParam>,
+}
+
+fn one() -> usize {
+    1
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SweepMode {
+    /// Every combination of every parameter's values
+    #[default]
+    Grid,
+    /// `samples` combinations drawn from the sweep seed
+    Random { samples: usize },
+}
+
+/// One swept field: explicit `values`, or `steps` evenly spaced points over
+/// `range` (just the range for random sweeps).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SweepParam {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub values: Vec<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<[f64; 2]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub steps: Option<usize>,
+}
+
+impl SweepParam {
+    fn grid(&self) -> Vec<f64> {
+        if !self.values.is_empty() {
+            return self.values.clone();
+        }
+        let Some([lo, hi]) = self.range else { return Vec::new() };
+        let steps = self.steps.unwrap_or(5).max(2);
+        (0..steps).map(|i| lo + (hi - lo) * i as f64 / (steps - 1) as f64).collect()
+    }
+
+    fn sample(&self, rng: &mut SimRng) -> f64 {
+        match self.range {
+            _ if !self.values.is_empty() => self.values[rng.gen_range(0..self.values.len())],
+            Some([lo, hi]) if lo < hi => rng.gen_range(lo..hi),
+            Some([lo, _]) => lo,
+            None => 0.0,
+        }
+    }
+}
+
+/// A validated sweep: the base scenario and every combination to run.
+#[derive(Debug, Clone)]
+pub struct Sweep {
+    pub spec: SweepSpec,
+    base: Value,
+    combinations: Vec<Vec<f64>>,
+}
+
+impl Sweep {
+    /// Read a sweep file, load its base scenario, and check that every
+    /// combination gives a valid scenario before anything runs.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+        let spec: SweepSpec = serde_json::from_str(&text).map_err(|e| format!("{}: {}", path, e))?;
+        let base = match &spec.scenario {
+            Some(scenario) => Scenario::load(scenario)?,
+            None => Scenario::default(),
+        };
+        Sweep::new(spec, &base).map_err(|errors| format!("{}: {}", path, errors.join("; ")))
+    }
+
+    pub fn new(spec: SweepSpec, base: &Scenario) -> Result<Self, Vec<String>> {
+        let mut errors = Vec::new();
+        if spec.ticks == 0 {
+            errors.push("ticks: must be at least 1".to_string());
+        }
+        if spec.runs == 0 {
+            errors.push("runs: must be at least 1".to_string());
+        }
+        if spec.params.is_empty() {
+            errors.push("params: nothing to sweep".to_string());
+        }
+        for (i, param) in spec.params.iter().enumerate() {
+            match (param.values.is_empty(), param.range) {
+                (true, None) => errors.push(format!("params[{}]: needs values or a range", i)),
+                (false, Some(_)) => errors.push(format!("params[{}]: give values or a range, not both", i)),
+                (_, Some([lo, hi])) if lo > hi => errors.push(format!("params[{}].range: [{}, {}] is empty", i, lo, hi)),
+                _ => {}
+            }
+        }
+        if let SweepMode::Random { samples: 0 } = spec.mode {
+            errors.push("mode.random.samples: must be at least 1".to_string());
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let combinations = match spec.mode {
+            SweepMode::Grid => spec.params.iter().fold(vec![Vec::new()], |combinations, param| {
+                combinations.iter()
+                    .flat_map(|combination| param.grid().into_iter().map(move |value| {
+                        let mut next = combination.clone();
+                        next.push(value);
+                        next
+                    }))
+                    .collect()
+            }),
+            SweepMode::Random { samples } => {
+                let mut rng = SimRng::seed_from_u64(spec.seed);
+                (0..samples).map(|_| spec.params.iter().map(|p| p.sample(&mut rng)).collect()).collect()
+            }
+        };
+        if combinations.len() * spec.runs > 100_000 {
+            return Err(vec![format!("{} combinations x {} runs is over the 100000 run limit", combinations.len(), spec.runs)]);
+        }
+
+        let base = serde_json::to_value(base).map_err(|e| vec![e.to_string()])?;
+        let sweep = Self { spec, base, combinations };
+        for (k, combination) in sweep.combinations.iter().enumerate() {
+            if let Err(e) = sweep.scenario(combination) {
+                errors.push(format!("combination {} {:?}: {}", k, combination, e));
+            }
+        }
+        if errors.is_empty() { Ok(sweep) } else { Err(errors) }
+    }
+
+    pub fn combinations(&self) -> &[Vec<f64>] {
+        &self.combinations
+    }
+
+    /// The base scenario with `values` written to the swept paths.
+    pub fn scenario(&self, values: &[f64]) -> Result<Scenario, String> {
+        let mut scenario = self.base.clone();
+        for (param, &value) in self.spec.params.iter().zip(values) {
+            set(&mut scenario, &param.path, value)?;
+        }
+        let scenario: Scenario = serde_json::from_value(scenario).map_err(|e| e.to_string())?;
+        scenario.validate().map_err(|errors| errors.join("; "))?;
+        Ok(scenario)
+    }
+}
+
+/// Write `value` at a dotted path. List elements are picked by index or by
+/// their `name` field. Integers stay integers, so counts can be swept.
+fn set(root: &mut Value, path: &str, value: f64) -> Result<(), String> {
+    let number = |old: &Value| {
+        if old.is_u64() || old.is_i64() {
+            Value::from(value.round() as i64)
+        } else {
+            Value::from(value)
+        }
+    };
+    let segments: Vec<&str> = path.split('.').collect();
+    let mut node = root;
+    for (i, segment) in segments.iter().enumerate() {
+        node = match node {
+            Value::Array(items) => {
+                let index = match segment.parse::<usize>() {
+                    Ok(index) => index,
+                    Err(_) => items.iter()
+                        .position(|item| item.get("name").and_then(Value::as_str) == Some(*segment))
+                        .ok_or_else(|| format!("{}: nothing named '{}'", path, segment))?,
+                };
+                items.get_mut(index).ok_or_else(|| format!("{}: no element {}", path, index))?
+            }
+            Value::Object(fields) if i + 1 == segments.len() => {
+                // Optional fields may be missing from the base; add them
+                let new = number(fields.get(*segment).unwrap_or(&Value::Null));
+                fields.insert(segment.to_string(), new);
+                return Ok(());
+            }
+            Value::Object(fields) => fields.get_mut(*segment).ok_or_else(|| format!("{}: no field '{}'", path, segment))?,
+            _ => return Err(format!("{}: '{}' is not inside an object or list", path, segment)),
+        };
+    }
+    let new = number(node);
+    *node = new;
+    Ok(())
+}
+
+/// Outcome metrics for one combination, averaged over its runs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SweepRow {
+    pub combination: usize,
+    pub values: Vec<f64>,
+    /// Runs averaged over
+    pub runs: usize,
+    /// Runs whose scenario could not be built, left out of the averages
+    pub failed: usize,
+    pub volatility: f64,
+    pub volatility_std: f64,
+    pub kurtosis: f64,
+    /// Fraction of runs with at least one crash
+    pub crash_probability: f64,
+    pub crashes_per_1k_ticks: f64,
+    pub max_drawdown: f64,
+    pub spread_bps: f64,
+    /// Mean final price over initial price, minus one
+    pub return_pct: f64,
+}
+
+impl SweepRow {
+    pub fn aggregate(combination: usize, values: &[f64], runs: &[RunSummary], failed: usize) -> Self {
+        let n = runs.len().max(1) as f64;
+        let mean = |f: fn(&RunSummary) -> f64| runs.iter().map(f).sum::<f64>() / n;
+        let volatility = mean(|r| r.volatility);
+        let ticks: u64 = runs.iter().map(|r| r.ticks).sum();
+        let crashes: u64 = runs.iter().map(|r| r.crashes).sum();
+        Self {
+            combination,
+            values: values.to_vec(),
+            runs: runs.len(),
+            failed,
+            volatility,
+            volatility_std: (runs.iter().map(|r| (r.volatility - volatility).powi(2)).sum::<f64>() / n).sqrt(),
+            kurtosis: mean(|r| r.kurtosis),
+            crash_probability: runs.iter().filter(|r| r.crashes > 0).count() as f64 / n,
+            crashes_per_1k_ticks: if ticks > 0 { crashes as f64 * 1000.0 / ticks as f64 } else { 0.0 },
+            max_drawdown: mean(|r| r.max_drawdown),
+            spread_bps: mean(|r| r.mean_spread_bps),
+            return_pct: mean(|r| r.final_price as f64 / r.initial_price.max(1) as f64 - 1.0) * 100.0,
+        }
+    }
+
+    /// CSV header for a sweep over `params`: one column per swept path, then the metrics.
+    pub fn header(params: &[SweepParam]) -> Vec<String> {
+        let mut header = vec!["combination".to_string()];
+        header.extend(params.iter().map(|p| p.path.clone()));
+        header.extend([
+            "runs", "failed", "volatility", "volatility_std", "kurtosis", "crash_probability",
+            "crashes_per_1k_ticks", "max_drawdown", "spread_bps", "return_pct",
+        ].map(String::from));
+        header
+    }
+
+    pub fn record(&self) -> Vec<String> {
+        let mut record = vec![self.combination.to_string()];
+        record.extend(self.values.iter().map(|v| v.to_string()));
+        record.push(self.runs.to_string());
+        record.push(self.failed.to_string());
+        record.extend([
+            self.volatility, self.volatility_std, self.kurtosis, self.crash_probability,
+            self.crashes_per_1k_ticks, self.max_drawdown, self.spread_bps, self.return_pct,
+        ].map(|v| v.to_string()));
+        record
+    }
+}
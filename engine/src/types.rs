@@ -1,6 +1,19 @@
 use serde::{Deserialize, Serialize};
+use crate::game::{PlayerReport, Standing};
+use crate::population::GenerationReport;
+use crate::regime::{Regime, RegimeStatus};
+use crate::clock::Pacing;
+use crate::scenario::Scenario;
+use crate::scheduling::ActivationProfile;
+use crate::simulation::StopCondition;
+use crate::surveillance::{Alert, AlertKind};
+use tokio::sync::mpsc;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// The random stream type behind every seeded run. It is the generator inside
+/// `rand`'s `StdRng`, so seeds give the same draws, but its state can be saved.
+pub type SimRng = rand_chacha::ChaCha12Rng;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub enum OrderSide {
     Bid, // Buy
     Ask, // Sell
@@ -23,6 +36,8 @@ pub struct Trade {
     pub price: u64,
     pub amount: u64,
     pub timestamp: u64,
+    pub buy_order_id: u64,
+    pub sell_order_id: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -41,22 +56,69 @@ pub enum ServerMessage {
         quantity: u64,
         buyer_id: u64,
         seller_id: u64,
-    }
+    },
+    #[serde(rename = "liquidation")]
+    Liquidation {
+        trader_id: u64,
+        side: OrderSide,
+        quantity: u64,
+        filled: u64,
+        equity: i64,
+    },
+    #[serde(rename = "generation")]
+    Generation(GenerationReport),
+    #[serde(rename = "beliefs")]
+    Beliefs {
+        tick: u64,
+        chartists: u64,
+        fundamentalists: u64,
+    },
+    /// Suspicious activity flagged by surveillance
+    #[serde(rename = "alert")]
+    Alert(Alert),
+    /// Game players ranked by PnL
+    #[serde(rename = "leaderboard")]
+    Leaderboard {
+        tick: u64,
+        players: Vec<Standing>,
+    },
 }
 
 /// Commands sent from the control plane (Axum handlers) to the simulation thread.
-#[derive(Debug, Clone)]
+/// Serializable for the journal; reply channels are left out and come back closed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SimulationCommand {
     /// Manually inject a buy or sell order into the book
     InjectOrder(Order),
     /// Pause/resume the simulation loop
     SetPaused(bool),
-    /// Reset the order book and all agent inventories
-    Reset,
+    /// Pause, then run `ticks` ticks straight away, whatever the pacing
+    Step { ticks: u64 },
+    /// Change the server's wall-clock pacing, and the tick length under `Pacing::Ticked`
+    SetPacing { pacing: Pacing, tick_ms: Option<u64> },
+    /// Resume, and pause again at the end of the first tick where `StopCondition` holds
+    RunUntil(StopCondition),
+    /// Tick, pacing and pause state to `reply`
+    QueryTime { #[serde(skip, default = "closed")] reply: mpsc::UnboundedSender<TimeStatus> },
+    /// Reset the order book and all agent inventories, and reseed every random
+    /// stream (`None` = draw a seed from the current stream)
+    Reset { seed: Option<u64> },
+    /// Replace the market with a validated scenario: new agents (sessions and
+    /// players stay), balances, price and shock schedule, then reset
+    LoadScenario(Box<Scenario>),
+    /// The simulation's full state as snapshot JSON to `reply`
+    SaveSnapshot { #[serde(skip, default = "closed")] reply: mpsc::UnboundedSender<Result<String, String>> },
+    /// Replace the whole market with a snapshot. Trading sessions are closed;
+    /// the pause state and the logs carry on.
+    LoadSnapshot(String),
     /// Inject massive sell orders to simulate a flash crash
     FlashCrash { seller_id: u64, quantity: u64, price: u64 },
-    /// Adjust volatility multiplier for all random walkers
+    /// Scale the regime's noise trader jitter, arrival rates and fundamental volatility
     UpdateVolatility(f64),
+    /// Switch market regime, with the volatility multiplier back at 1
+    SetRegime(Regime),
+    /// The regime and what it has agents trading under to `reply`
+    QueryRegime { #[serde(skip, default = "closed")] reply: mpsc::UnboundedSender<RegimeStatus> },
     /// Create sustained buy pressure (multiple large bids)
     Pump { buyer_id: u64, base_price: u64, magnitude: f64 },
     /// Create sustained sell pressure (multiple large asks)
@@ -69,4 +131,85 @@ pub enum SimulationCommand {
     RugPull { magnitude: f64 },
     /// Whale accumulation (sustained buying)
     WhaleAccumulation { magnitude: f64 },
+    /// Add `count` agents running the Rhai script at `path`
+    SpawnScripted { path: String, count: usize, cash: u64, inventory: u64 },
+    /// Recompile one script (or all loaded scripts) in place
+    ReloadScripts { path: Option<String> },
+    /// Create an `External` agent for a trading session; events go to `reply`
+    RegisterSession { name: String, cash: u64, inventory: u64, #[serde(skip, default = "closed")] reply: mpsc::UnboundedSender<SessionEvent> },
+    /// Queue an order or cancel from a session, applied on the agent's turn
+    SessionInstruction { trader_id: u64, instruction: ExternalInstruction },
+    /// Session closed: pull the agent's resting orders and stop reporting to it
+    CloseSession { trader_id: u64 },
+    /// Add a game player with the game's starting balances; the new standing goes to `reply`
+    JoinGame { name: String, #[serde(skip, default = "closed")] reply: mpsc::UnboundedSender<Standing> },
+    /// Look up a player's account; `None` goes to `reply` if there is no such player
+    QueryPlayer { player_id: u64, #[serde(skip, default = "closed")] reply: mpsc::UnboundedSender<Option<PlayerReport>> },
+    /// Recent surveillance alerts, newest first, to `reply`
+    QueryAlerts { since: Option<u64>, kind: Option<AlertKind>, #[serde(skip, default = "closed")] reply: mpsc::UnboundedSender<Vec<Alert>> },
+    /// Tell surveillance these accounts share an owner, so trades between them are wash trades
+    LinkAccounts { trader_ids: Vec<u64> },
+    /// Change how often an agent class acts and how slow its orders are (`None` = every class without its own profile)
+    SetActivation { kind: Option<String>, profile: ActivationProfile },
+    /// Plant a rumour at a node of the social graph (`None` = top influencer); the node, or why not, to `reply`
+    SeedRumour {
+        node: Option<u64>,
+        side: OrderSide,
+        strength: f64,
+        duration: u64,
+        #[serde(skip, default = "closed")]
+        reply: mpsc::UnboundedSender<Result<u64, String>>,
+    },
+    /// Stop the session's thread; the simulation and its logs are dropped
+    Shutdown,
+}
+
+/// A reply channel nobody listens on, for commands read back from a journal.
+fn closed<T>() -> mpsc::UnboundedSender<T> {
+    mpsc::unbounded_channel().0
+}
+
+/// Where the simulation is in time and how fast it is going.
+#[derive(Debug, Clone, Serialize)]
+pub struct TimeStatus {
+    /// The tick in progress
+    pub tick: u64,
+    pub price: u64,
+    pub paused: bool,
+    pub pacing: Pacing,
+    /// Wall-clock length of a tick under `Pacing::Ticked`
+    pub tick_ms: u64,
+    /// Pending `RunUntil` condition
+    pub until: Option<StopCondition>,
+}
+
+/// Something an externally driven agent asked for, held until its turn in the tick.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum ExternalInstruction {
+    Submit(Order),
+    Cancel { order_id: u64 },
+}
+
+/// Messages a trading session client sends over `/ws/trade`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// Must be the first message. `token` is checked against the server's list.
+    Login { token: String, name: String },
+    Submit { side: OrderSide, price: u64, quantity: u64, client_order_id: Option<String> },
+    Cancel { order_id: u64 },
+}
+
+/// Private messages to one trading session, interleaved with the public market data.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SessionEvent {
+    Registered { agent_id: u64, cash: u64, inventory: u64 },
+    /// Order id assigned; the order reaches the book on the agent's next turn
+    Received { order_id: u64, client_order_id: Option<String> },
+    Accepted { order_id: u64 },
+    Rejected { order_id: u64, reason: String },
+    Execution { order_id: u64, side: OrderSide, price: u64, quantity: u64, cash: u64, inventory: u64 },
+    Cancelled { order_id: u64, remaining: u64 },
+    Error { message: String },
 }